@@ -0,0 +1,47 @@
+//! Regression benchmarks for the work the shell hook does on every `cd`
+//!
+//! Run with `cargo bench --features bench`; `auto-nvm bench` reports the same measurements
+//! plus the end-to-end `switch` process.
+
+use auto_nvm::backend::Nvm;
+use auto_nvm::bench::{self, Fixture};
+use auto_nvm::VersionSpec;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn resolution(c: &mut Criterion) {
+    let fixture = Fixture::create().expect("Failed to create bench fixture");
+    let nvm = Nvm::with_dir(&fixture.nvm_dir);
+
+    let mut group = c.benchmark_group("resolve");
+    for (depth, dir) in &fixture.dirs {
+        group.bench_with_input(BenchmarkId::from_parameter(depth), dir, |b, dir| {
+            b.iter(|| bench::resolve_from(dir, &nvm))
+        });
+    }
+    group.finish();
+}
+
+fn index_cache(c: &mut Criterion) {
+    let fixture = Fixture::create().expect("Failed to create bench fixture");
+    let client = fixture.index_client();
+    let spec: VersionSpec = "22".parse().unwrap();
+
+    c.bench_function("index cache", |b| {
+        b.iter(|| bench::resolve_cached(&client, &spec))
+    });
+}
+
+fn emission(c: &mut Criterion) {
+    let fixture = Fixture::create().expect("Failed to create bench fixture");
+
+    let mut group = c.benchmark_group("emit");
+    for backend in fixture.backends() {
+        group.bench_function(backend.kind().as_str(), |b| {
+            b.iter(|| bench::emit(fixture.deepest(), backend.as_ref()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, resolution, index_cache, emission);
+criterion_main!(benches);
@@ -7,14 +7,28 @@ __auto_nvm_original_cd() {
     builtin cd "$@"
 }
 
+# Run auto-nvm switch to check for .nvmrc
+__auto_nvm_switch() {
+    if [ -n "$(auto-nvm --quiet switch --shell bash --print 2>/dev/null)" ]; then
+        eval "$(auto-nvm --quiet switch --shell bash)"
+    fi
+}
+
 # Override cd to trigger auto-nvm on directory change
 cd() {
     __auto_nvm_original_cd "$@" || return
+    __auto_nvm_switch
+}
 
-    # Run auto-nvm switch to check for .nvmrc
-    if [ -n "$(auto-nvm --quiet switch --print 2>/dev/null)" ]; then
-        eval "$(auto-nvm --quiet switch)"
-    fi
+# pushd and popd change directory without calling cd
+pushd() {
+    builtin pushd "$@" || return
+    __auto_nvm_switch
+}
+
+popd() {
+    builtin popd "$@" || return
+    __auto_nvm_switch
 }
 
 # Startup check function
@@ -24,8 +38,8 @@ __auto_nvm_startup_check() {
         export __AUTO_NVM_STARTUP_CHECKED=1
 
         # Check for .nvmrc in current directory and switch if found
-        if [ -n "$(auto-nvm --quiet switch --print 2>/dev/null)" ]; then
-            eval "$(auto-nvm --quiet switch 2>/dev/null)" || true
+        if [ -n "$(auto-nvm --quiet switch --shell bash --print 2>/dev/null)" ]; then
+            eval "$(auto-nvm --quiet switch --shell bash)" || true
         fi
     fi
 }
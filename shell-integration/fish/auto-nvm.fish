@@ -7,9 +7,10 @@ function cd --description 'Change directory and run auto-nvm'
     builtin cd $argv
 
     # Run auto-nvm switch to check for .nvmrc
-    set -l nvm_output (auto-nvm --quiet switch --print 2>/dev/null)
+    set -l nvm_output (auto-nvm --quiet switch --shell fish --print 2>/dev/null)
     if test -n "$nvm_output"
-        eval (auto-nvm --quiet switch)
+        # One command per line; `eval (...)` would join them into one
+        auto-nvm --quiet switch --shell fish | source
     end
 end
 
@@ -20,9 +21,9 @@ function __auto_nvm_startup_check
         set -g __AUTO_NVM_STARTUP_CHECKED 1
 
         # Check for .nvmrc in current directory and switch if found
-        set -l nvm_output (auto-nvm --quiet switch --print 2>/dev/null)
+        set -l nvm_output (auto-nvm --quiet switch --shell fish --print 2>/dev/null)
         if test -n "$nvm_output"
-            eval (auto-nvm --quiet switch 2>/dev/null); or true
+            auto-nvm --quiet switch --shell fish | source; or true
         end
     end
 end
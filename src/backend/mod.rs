@@ -0,0 +1,170 @@
+use crate::error::Result;
+use crate::nvm::{self, Backend};
+use crate::resolve;
+use crate::spec::VersionSpec;
+use std::path::{Path, PathBuf};
+
+/// A Node.js version manager that auto-nvm resolves versions against and
+/// emits switch commands for
+pub trait VersionManager {
+    /// Which backend this is
+    fn kind(&self) -> Backend;
+
+    /// Installed Node.js versions (`vX.Y.Z`), newest first
+    fn installed_versions(&self) -> Result<Vec<String>>;
+
+    /// Directory holding nvm-style alias files (`default`, `lts/*`), if the
+    /// backend has one
+    fn alias_dir(&self) -> Option<&Path> {
+        None
+    }
+
+    /// The installed version a spec resolves to, if any
+    fn resolve(&self, spec: &VersionSpec) -> Result<Option<String>> {
+        let installed = self.installed_versions()?;
+        Ok(resolve::resolve_version(spec, &installed, self.alias_dir()))
+    }
+
+    /// Version of the `node` currently on PATH
+    fn active_version(&self) -> Option<String> {
+        nvm::get_current_version().ok()
+    }
+
+    /// Shell command that activates `spec` in the calling shell, or `None`
+    /// for a range no installed version satisfies
+    fn use_command(&self, spec: &VersionSpec) -> Option<String> {
+        match spec {
+            // nvm does not understand npm ranges; hand it the installed match
+            VersionSpec::Range(_) => self
+                .resolve(spec)
+                .ok()
+                .flatten()
+                .map(|version| format!("nvm use {}", version)),
+            // zsh and fish fail on a glob that matches nothing
+            VersionSpec::LtsOffset(0) => Some(format!("nvm use {}", resolve::shell_quote("lts/*"))),
+            _ => Some(format!("nvm use {}", spec)),
+        }
+    }
+}
+
+/// nvm (https://github.com/nvm-sh/nvm), reading `$NVM_DIR` directly
+#[derive(Debug, Clone, Default)]
+pub struct Nvm {
+    dir: Option<PathBuf>,
+}
+
+impl Nvm {
+    /// nvm installed in `$NVM_DIR` (or `~/.nvm`)
+    pub fn new() -> Self {
+        Self {
+            dir: nvm::nvm_dir(),
+        }
+    }
+
+    /// nvm installed in the given directory
+    pub fn with_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: Some(dir.into()),
+        }
+    }
+}
+
+impl VersionManager for Nvm {
+    fn kind(&self) -> Backend {
+        Backend::Nvm
+    }
+
+    fn installed_versions(&self) -> Result<Vec<String>> {
+        match &self.dir {
+            Some(dir) => nvm::list_installed_versions_in(dir),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // nvm aliases (default, lts/*) live in the nvm directory
+    fn alias_dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+}
+
+/// nvm-windows (https://github.com/coreybutler/nvm-windows), reading `%NVM_HOME%`
+#[derive(Debug, Clone, Default)]
+pub struct NvmWindows {
+    home: Option<PathBuf>,
+}
+
+impl NvmWindows {
+    pub fn new() -> Self {
+        Self {
+            home: std::env::var_os("NVM_HOME")
+                .filter(|home| !home.is_empty())
+                .map(PathBuf::from),
+        }
+    }
+
+    /// nvm-windows installed in the given `NVM_HOME`
+    pub fn with_home(home: impl Into<PathBuf>) -> Self {
+        Self {
+            home: Some(home.into()),
+        }
+    }
+}
+
+impl VersionManager for NvmWindows {
+    fn kind(&self) -> Backend {
+        Backend::NvmWindows
+    }
+
+    fn installed_versions(&self) -> Result<Vec<String>> {
+        match &self.home {
+            Some(home) => nvm::list_version_dirs(home),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// The version manager used on this platform
+pub fn detect() -> Box<dyn VersionManager> {
+    match nvm::detect_backend() {
+        Backend::Nvm => Box::new(Nvm::new()),
+        Backend::NvmWindows => Box::new(NvmWindows::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_nvm_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        for version in ["v18.17.1", "v20.10.0"] {
+            fs::create_dir_all(dir.path().join("versions/node").join(version)).unwrap();
+        }
+
+        let backend = Nvm::with_dir(dir.path());
+        assert_eq!(backend.kind(), Backend::Nvm);
+        assert_eq!(
+            backend.installed_versions().unwrap(),
+            vec!["v20.10.0", "v18.17.1"]
+        );
+
+        let spec = VersionSpec::parse("20").unwrap();
+        assert_eq!(backend.resolve(&spec).unwrap().as_deref(), Some("v20.10.0"));
+        assert_eq!(backend.use_command(&spec).as_deref(), Some("nvm use 20"));
+
+        let range = VersionSpec::parse(">=18 <20").unwrap();
+        assert_eq!(
+            backend.use_command(&range).as_deref(),
+            Some("nvm use v18.17.1")
+        );
+        let range = VersionSpec::parse(">=22").unwrap();
+        assert_eq!(backend.use_command(&range), None);
+        let lts = VersionSpec::parse("lts").unwrap();
+        assert_eq!(
+            backend.use_command(&lts).as_deref(),
+            Some("nvm use 'lts/*'")
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use crate::backend::{Nvm, NvmWindows, VersionManager};
+use crate::config::Config;
+use crate::error::{io_error, Result};
+use crate::index::IndexClient;
+use crate::nvmrc;
+use crate::resolve;
+use crate::spec::VersionSpec;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// Depths below a version file at which resolution is timed
+pub const DEPTHS: &[usize] = &[0, 2, 4, 8];
+
+/// Versions installed in the synthetic nvm and nvm-windows directories
+const INSTALLED: &[&str] = &[
+    "v18.17.0", "v18.17.1", "v18.20.4", "v20.10.0", "v20.18.0", "v22.9.0", "v22.11.0", "v22.20.0",
+    "v24.1.0", "v24.10.0",
+];
+
+/// Version files, one per project, cycling through the kinds of spec
+const SPECS: &[&str] = &["22", "v22.11.0", "lts/*", ">=20 <23", "18.17", "24"];
+
+/// A synthetic workspace: projects with an .nvmrc, directories nested below
+/// them, fake nvm and nvm-windows installs and a cached index.json
+///
+/// Everything lives under one temporary directory removed on drop.
+pub struct Fixture {
+    _dir: TempDir,
+    pub root: PathBuf,
+    /// `(depth, dir)`: `dir` is `depth` levels below the nearest .nvmrc
+    pub dirs: Vec<(usize, PathBuf)>,
+    pub nvm_dir: PathBuf,
+    pub nvm_home: PathBuf,
+    pub cache_dir: PathBuf,
+    /// Empty `HOME` for processes run against the fixture
+    pub home: PathBuf,
+}
+
+impl Fixture {
+    pub fn create() -> Result<Self> {
+        let temp = std::env::temp_dir();
+        let dir = tempfile::Builder::new()
+            .prefix("auto-nvm-bench-")
+            .tempdir()
+            .map_err(io_error("create", &temp))?;
+        let root = dir.path().to_path_buf();
+        let fixture = Self {
+            _dir: dir,
+            dirs: Vec::new(),
+            nvm_dir: root.join("nvm"),
+            nvm_home: root.join("nvm-windows"),
+            cache_dir: root.join("cache"),
+            home: root.join("home"),
+            root,
+        };
+        fixture.populate()
+    }
+
+    fn populate(mut self) -> Result<Self> {
+        let create_dir = |dir: &Path| fs::create_dir_all(dir).map_err(io_error("create", dir));
+        create_dir(&self.home)?;
+
+        for version in INSTALLED {
+            create_dir(&self.nvm_dir.join("versions/node").join(version))?;
+            create_dir(&self.nvm_home.join(version))?;
+        }
+        // `lts/*` resolves through nvm's alias files
+        let alias = self.nvm_dir.join("alias/lts");
+        create_dir(&alias)?;
+        fs::write(alias.join("*"), "lts/jod\n").map_err(io_error("write", &alias))?;
+        fs::write(alias.join("jod"), "v22.20.0\n").map_err(io_error("write", &alias))?;
+
+        for (index, &depth) in DEPTHS.iter().enumerate() {
+            let project = self
+                .root
+                .join("workspace")
+                .join(format!("project-{}", index));
+            create_dir(&project)?;
+            let nvmrc = project.join(".nvmrc");
+            let spec = SPECS[index % SPECS.len()];
+            fs::write(&nvmrc, format!("{}\n", spec)).map_err(io_error("write", &nvmrc))?;
+
+            let dir = (0..depth).fold(project, |dir, level| dir.join(format!("src-{}", level)));
+            create_dir(&dir)?;
+            self.dirs.push((depth, dir));
+        }
+
+        self.index_client().store(&synthetic_index())?;
+        Ok(self)
+    }
+
+    /// The backends `switch` can emit commands for, over this fixture
+    pub fn backends(&self) -> Vec<Box<dyn VersionManager>> {
+        vec![
+            Box::new(Nvm::with_dir(&self.nvm_dir)),
+            Box::new(NvmWindows::with_home(&self.nvm_home)),
+        ]
+    }
+
+    /// Index client reading the fixture's cache
+    pub fn index_client(&self) -> IndexClient {
+        IndexClient::new(&Config::new()).with_cache_dir(Some(self.cache_dir.clone()))
+    }
+
+    /// The deepest directory, furthest from its .nvmrc
+    pub fn deepest(&self) -> &Path {
+        self.dirs
+            .last()
+            .map(|(_, dir)| dir.as_path())
+            .unwrap_or(&self.root)
+    }
+}
+
+/// Find the nearest .nvmrc above `dir` and resolve it against `backend`
+pub fn resolve_from(dir: &Path, backend: &dyn VersionManager) -> Result<resolve::Resolution> {
+    let project = match nvmrc::find_nvmrc_upwards(dir)? {
+        Some(nvmrc) => nvmrc.parent().map(Path::to_path_buf).unwrap_or_default(),
+        None => dir.to_path_buf(),
+    };
+    resolve::resolve_dir(&project, backend, None, None)
+}
+
+/// What `switch` prints for `dir`: resolve, then build the backend's command
+pub fn emit(dir: &Path, backend: &dyn VersionManager) -> Result<Option<String>> {
+    let resolution = resolve_from(dir, backend)?;
+    Ok(resolution
+        .spec
+        .as_ref()
+        .and_then(|spec| backend.use_command(spec)))
+}
+
+/// Read the cached index and resolve a spec against it, as `check` does
+pub fn resolve_cached(client: &IndexClient, spec: &VersionSpec) -> Option<String> {
+    let index = client.cached()?;
+    index.resolve(spec).map(|release| release.version.clone())
+}
+
+/// index.json with a few hundred releases, newest first, like nodejs.org's
+fn synthetic_index() -> String {
+    let mut releases = Vec::new();
+    for major in (4..=24).rev() {
+        let lts = (major % 2 == 0 && major >= 4).then(|| format!("\"codename{}\"", major));
+        for minor in (0..10).rev() {
+            for patch in (0..3).rev() {
+                releases.push(format!(
+                    r#"{{"version":"v{}.{}.{}","date":"2024-01-01","lts":{},"files":["linux-x64"]}}"#,
+                    major,
+                    minor,
+                    patch,
+                    lts.as_deref().unwrap_or("false")
+                ));
+            }
+        }
+    }
+    format!("[{}]", releases.join(","))
+}
+
+/// Percentiles of a set of timings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Summary {
+    pub name: String,
+    pub iterations: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+}
+
+/// Run `f` `iterations` times (after one warm-up run) and summarise the timings
+pub fn measure<T>(name: impl Into<String>, iterations: usize, mut f: impl FnMut() -> T) -> Summary {
+    std::hint::black_box(f());
+    let mut timings: Vec<Duration> = (0..iterations.max(1))
+        .map(|_| {
+            let start = Instant::now();
+            std::hint::black_box(f());
+            start.elapsed()
+        })
+        .collect();
+    timings.sort();
+    Summary {
+        name: name.into(),
+        iterations: timings.len(),
+        p50_ms: percentile(&timings, 50),
+        p95_ms: percentile(&timings, 95),
+    }
+}
+
+/// Nearest-rank percentile of sorted timings, in milliseconds
+fn percentile(sorted: &[Duration], percent: usize) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (percent * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1].as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentile() {
+        let timings: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&timings, 50), 50.0);
+        assert_eq!(percentile(&timings, 95), 95.0);
+        assert_eq!(percentile(&timings[..1], 95), 1.0);
+        assert_eq!(percentile(&[], 50), 0.0);
+    }
+
+    #[test]
+    fn test_fixture() -> Result<()> {
+        let fixture = Fixture::create()?;
+        let root = fixture.root.clone();
+
+        for (depth, dir) in &fixture.dirs {
+            let resolution = resolve_from(dir, &Nvm::with_dir(&fixture.nvm_dir))?;
+            assert!(resolution.installed, "depth {}: {:?}", depth, resolution);
+        }
+        let commands: Vec<Option<String>> = fixture
+            .backends()
+            .iter()
+            .map(|backend| emit(fixture.deepest(), backend.as_ref()))
+            .collect::<Result<_>>()?;
+        // `>=20 <23` is handed to nvm as the installed match
+        let expected = Some("nvm use v22.20.0".to_string());
+        assert_eq!(commands, [expected.clone(), expected]);
+
+        let spec = "22".parse()?;
+        assert_eq!(
+            resolve_cached(&fixture.index_client(), &spec).as_deref(),
+            Some("v22.9.2")
+        );
+
+        drop(fixture);
+        assert!(!root.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+use crate::Cli;
+use anyhow::{anyhow, Context, Result};
+use auto_nvm::backend;
+use auto_nvm::nvm::{self, ShellType};
+use auto_nvm::resolve;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Completes installed versions for the arguments that take one, falling back
+/// to the generated function (`GENERATED`) for everything else
+const BASH_DYNAMIC: &str = r#"_auto_nvm_dynamic() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}" cmd="" word
+    for word in "${COMP_WORDS[@]:1:COMP_CWORD-1}"; do
+        case "$word" in
+            -*) ;;
+            *) cmd="$word"; break ;;
+        esac
+    done
+    case "$cmd:$prev" in
+        exec:--version | run:--version | install:--reinstall-packages-from | \
+        migrate-globals:--from | migrate-globals:--to) ;;
+        pin:* | use:*)
+            if [[ "$prev" == --file || "$cur" == -* ]]; then
+                GENERATED "$@"
+                return
+            fi
+            ;;
+        *)
+            GENERATED "$@"
+            return
+            ;;
+    esac
+    COMPREPLY=( $(compgen -W "$(auto-nvm complete-versions 2>/dev/null)" -- "$cur") )
+}
+
+"#;
+
+/// Completion function for the `:VERSION:` and pin spec actions
+const ZSH_DYNAMIC: &str = r#"
+_auto_nvm_versions() {
+    local -a versions
+    versions=(${(f)"$(auto-nvm complete-versions 2>/dev/null)"})
+    _describe -t versions 'installed version' versions
+}
+"#;
+
+const FISH_DYNAMIC: &str = r#"
+# Installed versions and aliases
+complete -c auto-nvm -n "__fish_auto_nvm_using_subcommand pin; or __fish_auto_nvm_using_subcommand use" -f -a "(auto-nvm complete-versions 2>/dev/null)"
+complete -c auto-nvm -n "__fish_auto_nvm_using_subcommand exec; or __fish_auto_nvm_using_subcommand run" -l version -r -f -a "(auto-nvm complete-versions 2>/dev/null)"
+complete -c auto-nvm -n "__fish_auto_nvm_using_subcommand install" -l reinstall-packages-from -r -f -a "(auto-nvm complete-versions 2>/dev/null)"
+complete -c auto-nvm -n "__fish_auto_nvm_using_subcommand migrate-globals" -l from -l to -r -f -a "(auto-nvm complete-versions 2>/dev/null)"
+"#;
+
+/// Completion script for `shell`
+///
+/// bash, zsh and fish also complete installed versions and aliases for
+/// `pin` and `--version`-style options, asking the binary at completion time.
+pub fn script(shell: Shell) -> String {
+    let mut buffer = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), "auto-nvm", &mut buffer);
+    let script = String::from_utf8_lossy(&buffer).to_string();
+
+    match shell {
+        Shell::Bash => {
+            // The generated function's name differs between clap_complete
+            // releases; take it from its `complete -F` registration
+            let Some(generated) = script
+                .split("complete -F ")
+                .nth(1)
+                .and_then(|rest| rest.split_whitespace().next())
+                .map(str::to_string)
+            else {
+                return script;
+            };
+            format!(
+                "{}{}",
+                BASH_DYNAMIC.replace("GENERATED", &generated),
+                script.replace(
+                    &format!("complete -F {} ", generated),
+                    "complete -F _auto_nvm_dynamic "
+                )
+            )
+        }
+        Shell::Zsh => {
+            let script = script
+                .replace(":VERSION:_default'", ":VERSION:_auto_nvm_versions'")
+                .replace(
+                    "'::spec -- Version to pin (defaults to the active version):_default'",
+                    "'::spec -- Version to pin (defaults to the active version):_auto_nvm_versions'",
+                );
+            match script.split_once('\n') {
+                Some((compdef, rest)) => format!("{}\n{}{}", compdef, ZSH_DYNAMIC, rest),
+                None => script,
+            }
+        }
+        Shell::Fish => format!("{}{}", script, FISH_DYNAMIC),
+        _ => script,
+    }
+}
+
+/// Installed versions, newest first, followed by nvm aliases
+pub fn version_candidates() -> Vec<String> {
+    let mut candidates = backend::detect().installed_versions().unwrap_or_default();
+    if let Some(dir) = nvm::nvm_dir() {
+        candidates.extend(nvm::list_aliases_in(&dir).unwrap_or_default());
+    }
+    candidates
+}
+
+/// Where completions for `shell` are installed; bash and fish load them
+/// from there on demand
+pub fn install_path(shell: ShellType) -> Result<PathBuf> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not determine home directory"))?;
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".local/share"));
+
+    Ok(match shell {
+        ShellType::Bash => data_home.join("bash-completion/completions/auto-nvm"),
+        ShellType::Zsh => data_home.join("zsh/site-functions/_auto-nvm"),
+        ShellType::Fish => home.join(".config/fish/completions/auto-nvm.fish"),
+        ShellType::PowerShell => {
+            crate::shell::get_config_file_path(shell)?.with_file_name("auto-nvm-completions.ps1")
+        }
+    })
+}
+
+/// Lines the shell integration block needs to load the installed completions
+/// for `shell`: zsh only looks in `fpath` and PowerShell only runs the
+/// profile, so neither finds the file by itself
+pub fn loader(shell: ShellType) -> Option<String> {
+    let path = install_path(shell).ok().filter(|path| path.is_file())?;
+    loader_for(shell, &path)
+}
+
+fn loader_for(shell: ShellType, path: &Path) -> Option<String> {
+    match shell {
+        // compdef exists when compinit already ran; otherwise compinit
+        // finds _auto-nvm in fpath later
+        ShellType::Zsh => Some(format!(
+            "# Completions (auto-nvm setup --completions)\nfpath=({} $fpath)\nif (( $+functions[compdef] )); then\n    autoload -Uz _auto-nvm && compdef _auto-nvm auto-nvm\nfi",
+            resolve::shell_quote(&path.parent()?.to_string_lossy())
+        )),
+        ShellType::PowerShell => Some(format!(
+            "# Completions (auto-nvm setup --completions)\n. '{}'",
+            path.to_string_lossy().replace('\'', "''")
+        )),
+        ShellType::Bash | ShellType::Fish => None,
+    }
+}
+
+fn clap_shell(shell: ShellType) -> Shell {
+    match shell {
+        ShellType::Bash => Shell::Bash,
+        ShellType::Zsh => Shell::Zsh,
+        ShellType::Fish => Shell::Fish,
+        ShellType::PowerShell => Shell::PowerShell,
+    }
+}
+
+/// Write the completion script for `shell` to its install path
+pub fn install(shell: ShellType) -> Result<PathBuf> {
+    let path = install_path(shell)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(&path, script(clap_shell(shell)))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// The completion files installed for any shell
+fn installed() -> Vec<(ShellType, PathBuf)> {
+    [
+        ShellType::Bash,
+        ShellType::Zsh,
+        ShellType::Fish,
+        ShellType::PowerShell,
+    ]
+    .into_iter()
+    .filter_map(|shell| Some((shell, install_path(shell).ok()?)))
+    .filter(|(_, path)| path.is_file())
+    .collect()
+}
+
+/// Regenerate the completion files that are installed and out of date
+pub fn refresh_installed() -> Result<Vec<PathBuf>> {
+    let mut refreshed = Vec::new();
+    for (shell, path) in installed() {
+        if fs::read_to_string(&path).ok() != Some(script(clap_shell(shell))) {
+            refreshed.push(install(shell)?);
+        }
+    }
+    Ok(refreshed)
+}
+
+/// Delete the completion files installed for any shell
+pub fn remove_installed() -> Result<()> {
+    for (_, path) in installed() {
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dynamic_version_completion() {
+        let bash = script(Shell::Bash);
+        assert!(bash.contains("complete -F _auto_nvm_dynamic "));
+        assert!(!bash.contains("complete -F _auto-nvm "));
+        assert!(bash.contains("_auto-nvm() {"));
+        assert!(bash.contains("            _auto-nvm \"$@\"\n"));
+
+        // A clap_complete upgrade changing these actions must not silently
+        // drop version completion
+        let zsh = script(Shell::Zsh);
+        assert!(zsh.starts_with("#compdef auto-nvm\n"));
+        assert!(zsh.contains(
+            "'--version=[Version to use instead of the one in the version file]:VERSION:_auto_nvm_versions'"
+        ));
+        assert!(zsh.contains("(defaults to the active version):_auto_nvm_versions'"));
+        assert!(!zsh.contains(":VERSION:_default"));
+
+        let fish = script(Shell::Fish);
+        assert!(fish.contains("function __fish_auto_nvm_using_subcommand"));
+        assert!(fish.contains("-l version -r -f -a \"(auto-nvm complete-versions"));
+
+        for shell in [Shell::PowerShell, Shell::Elvish] {
+            assert!(script(shell).contains("auto-nvm"));
+        }
+    }
+
+    #[test]
+    fn test_loader() {
+        let zsh = loader_for(
+            ShellType::Zsh,
+            Path::new("/home/me/.local/share/zsh/site-functions/_auto-nvm"),
+        )
+        .unwrap();
+        assert!(zsh.contains("\nfpath=('/home/me/.local/share/zsh/site-functions' $fpath)\n"));
+        assert!(zsh.contains("compdef _auto-nvm auto-nvm"));
+
+        let powershell = loader_for(
+            ShellType::PowerShell,
+            Path::new("/home/o'neil/auto-nvm-completions.ps1"),
+        )
+        .unwrap();
+        assert!(powershell.ends_with("\n. '/home/o''neil/auto-nvm-completions.ps1'"));
+
+        let bash = Path::new("/home/me/.local/share/bash-completion/completions/auto-nvm");
+        assert_eq!(loader_for(ShellType::Bash, bash), None);
+    }
+}
@@ -1,26 +1,75 @@
+use crate::error::{Error, Result};
+use crate::nvmrc::VersionSource;
+use std::path::PathBuf;
+
 /// Basic configuration for auto-nvm
 #[derive(Debug, Clone, Default)]
 pub struct Config {
     /// Enable quiet mode (suppress non-error output)
     pub quiet: bool,
+    /// Version file type written by `pin`, as set in AUTO_NVM_VERSION_FILE
+    pub version_file: Option<String>,
+    /// Directories scanned for projects by `list` (from AUTO_NVM_PROJECT_ROOTS)
+    pub project_roots: Vec<PathBuf>,
+    /// Node.js distribution mirror (from AUTO_NVM_NODE_MIRROR; falls back to
+    /// NVM_NODEJS_ORG_MIRROR)
+    pub node_mirror: Option<String>,
 }
 
 impl Config {
     /// Create a new config with default values
-    #[allow(dead_code)]
     pub fn new() -> Self {
         Self::default()
     }
 
-    /// Create config from command-line arguments
+    /// Create config from command-line arguments and environment variables
     pub fn from_cli(quiet: bool) -> Self {
-        Self { quiet }
+        Self {
+            quiet,
+            version_file: std::env::var("AUTO_NVM_VERSION_FILE")
+                .ok()
+                .filter(|value| !value.trim().is_empty()),
+            project_roots: std::env::var_os("AUTO_NVM_PROJECT_ROOTS")
+                .map(|value| {
+                    std::env::split_paths(&value)
+                        .filter(|path| !path.as_os_str().is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            node_mirror: std::env::var("AUTO_NVM_NODE_MIRROR")
+                .ok()
+                .filter(|mirror| !mirror.is_empty()),
+        }
     }
 
     /// Check if quiet mode is enabled
     pub fn is_quiet(&self) -> bool {
         self.quiet
     }
+
+    /// The version file type `pin` writes, if one is configured
+    pub fn version_file(&self) -> Result<Option<VersionSource>> {
+        let value = match &self.version_file {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        parse_version_source(value).map(Some).ok_or_else(|| {
+            let expected: Vec<&str> = VersionSource::ALL.iter().map(|s| s.as_str()).collect();
+            Error::InvalidEnv {
+                name: "AUTO_NVM_VERSION_FILE",
+                value: value.clone(),
+                expected: expected.join(", "),
+            }
+        })
+    }
+}
+
+/// Parse a version file type such as `nvmrc`, `.node-version` or `package-json-volta`
+pub fn parse_version_source(value: &str) -> Option<VersionSource> {
+    use clap::ValueEnum;
+
+    let value = value.trim().trim_start_matches('.');
+    VersionSource::from_str(value, true).ok()
 }
 
 #[cfg(test)]
@@ -45,6 +94,37 @@ mod tests {
         assert!(!config.is_quiet());
     }
 
+    #[test]
+    fn test_parse_version_source() {
+        assert_eq!(parse_version_source("nvmrc"), Some(VersionSource::Nvmrc));
+        assert_eq!(
+            parse_version_source(".node-version"),
+            Some(VersionSource::NodeVersion)
+        );
+        assert_eq!(
+            parse_version_source("package-json-volta"),
+            Some(VersionSource::PackageJsonVolta)
+        );
+        assert_eq!(parse_version_source("unknown"), None);
+    }
+
+    #[test]
+    fn test_version_file() {
+        let mut config = Config::new();
+        assert_eq!(config.version_file().unwrap(), None);
+
+        config.version_file = Some(".node-version".to_string());
+        assert_eq!(
+            config.version_file().unwrap(),
+            Some(VersionSource::NodeVersion)
+        );
+
+        config.version_file = Some("nvmrc.txt".to_string());
+        let error = config.version_file().unwrap_err().to_string();
+        assert!(error.contains("AUTO_NVM_VERSION_FILE"));
+        assert!(error.contains("nvmrc, node-version, tool-versions"));
+    }
+
     #[test]
     fn test_new_config() {
         let config = Config::new();
@@ -0,0 +1,352 @@
+use crate::nvm;
+use crate::release::Schedule;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+
+    /// ANSI colour of the severity label
+    fn color(&self) -> &'static str {
+        match self {
+            Severity::Error => "\x1b[1;31m",
+            Severity::Warning => "\x1b[1;33m",
+        }
+    }
+}
+
+/// A problem in a version file, pointing at where it is and how to fix it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// 1-based line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// 1-based column and length in characters of the offending text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<(usize, usize)>,
+    /// Replacement to offer as "did you mean"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+    /// The line the span points into, for rendering
+    #[serde(skip)]
+    pub source_line: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            file: None,
+            line: None,
+            span: None,
+            suggestion: None,
+            source_line: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    /// Point at `len` characters from `column` on `line` (all 1-based)
+    pub fn at(mut self, line: usize, column: usize, len: usize) -> Self {
+        self.line = Some(line);
+        self.span = Some((column, len.max(1)));
+        self
+    }
+
+    pub fn with_file(mut self, file: &Path) -> Self {
+        self.file = Some(file.to_path_buf());
+        self
+    }
+
+    pub fn with_source_line(mut self, line: &str) -> Self {
+        self.source_line = Some(line.to_string());
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Option<String>) -> Self {
+        self.suggestion = suggestion;
+        self
+    }
+
+    /// `file:line:column`, as far as known
+    pub fn location(&self) -> Option<String> {
+        let file = self.file.as_ref().map(|file| file.display().to_string());
+        let position = match (self.line, self.span) {
+            (Some(line), Some((column, _))) => Some(format!("{}:{}", line, column)),
+            (Some(line), None) => Some(line.to_string()),
+            _ => None,
+        };
+        match (file, position) {
+            (Some(file), Some(position)) => Some(format!("{}:{}", file, position)),
+            (Some(file), None) => Some(file),
+            (None, Some(position)) => Some(format!("line {}", position)),
+            (None, None) => None,
+        }
+    }
+
+    /// One-line form for error chains and logs
+    pub fn summary(&self) -> String {
+        let mut summary = self.message.clone();
+        match (self.line, self.span) {
+            (Some(line), Some((column, _))) => {
+                summary.push_str(&format!(" (line {}, column {})", line, column))
+            }
+            (Some(line), None) => summary.push_str(&format!(" (line {})", line)),
+            _ => {}
+        }
+        if let Some(suggestion) = &self.suggestion {
+            summary.push_str(&format!("; did you mean `{}`?", suggestion));
+        }
+        summary
+    }
+
+    /// Render with the offending line and a caret under the span, in colour
+    /// when `color` is set
+    pub fn render(&self, color: bool) -> String {
+        let paint = |code: &str, text: &str| {
+            if color {
+                format!("{}{}\x1b[0m", code, text)
+            } else {
+                text.to_string()
+            }
+        };
+        let gutter_width = self.line.map_or(0, |line| line.to_string().len());
+        let gutter = " ".repeat(gutter_width);
+        let bar = paint("\x1b[1;34m", "|");
+
+        let mut out = format!(
+            "{}: {}",
+            paint(self.severity.color(), self.severity.as_str()),
+            paint("\x1b[1m", &self.message)
+        );
+        if let Some(location) = self.location() {
+            out.push_str(&format!(
+                "\n{}{} {}",
+                gutter,
+                paint("\x1b[1;34m", "-->"),
+                location
+            ));
+        }
+        if let (Some(line), Some(source), Some((column, len))) =
+            (self.line, &self.source_line, self.span)
+        {
+            let marker = format!(
+                "{}{}",
+                " ".repeat(column.saturating_sub(1)),
+                "^".repeat(len)
+            );
+            out.push_str(&format!("\n{} {}", gutter, bar));
+            out.push_str(&format!(
+                "\n{} {} {}",
+                paint("\x1b[1;34m", &line.to_string()),
+                bar,
+                source
+            ));
+            out.push_str(&format!(
+                "\n{} {} {}",
+                gutter,
+                bar,
+                paint(self.severity.color(), &marker)
+            ));
+        }
+        if let Some(suggestion) = &self.suggestion {
+            out.push_str(&format!(
+                "\n{} {} {}: did you mean `{}`?",
+                gutter,
+                paint("\x1b[1;34m", "="),
+                paint("\x1b[1m", "help"),
+                suggestion
+            ));
+        }
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(false))
+    }
+}
+
+/// Whether to colour output written to a stream; honours `NO_COLOR`
+pub fn use_color(is_terminal: bool) -> bool {
+    is_terminal && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Keywords a mistyped spec is compared against
+const KEYWORDS: &[&str] = &["lts/*", "node", "stable", "latest", "system", "default"];
+
+/// A likely intended spec for text that does not parse or names no release
+pub fn suggest_spec(text: &str) -> Option<String> {
+    let text = text.trim();
+    let lower = text.to_ascii_lowercase();
+
+    // `lts` alone is not understood by nvm
+    if lower == "lts" {
+        return Some("lts/*".to_string());
+    }
+
+    if let Some(name) = lower.strip_prefix("lts/") {
+        let schedule = Schedule::bundled();
+        let codenames: Vec<String> = schedule
+            .lines()
+            .filter_map(|(_, line)| line.codename.as_deref())
+            .map(str::to_ascii_lowercase)
+            .collect();
+        if codenames.iter().any(|codename| codename == name) {
+            return None;
+        }
+        return closest(name, codenames.iter().map(String::as_str))
+            .map(|codename| format!("lts/{}", codename));
+    }
+
+    // Stray dots or a fourth component: keep the numbers that are there
+    let numeric = lower.trim_start_matches('v');
+    if !numeric.is_empty()
+        && numeric.starts_with(|c: char| c.is_ascii_digit())
+        && numeric.chars().all(|c| c.is_ascii_digit() || c == '.')
+    {
+        let parts: Vec<&str> = numeric.split('.').filter(|part| !part.is_empty()).collect();
+        let prefix = if text.starts_with('v') { "v" } else { "" };
+        return Some(format!(
+            "{}{}",
+            prefix,
+            parts[..parts.len().min(3)].join(".")
+        ));
+    }
+
+    closest(&lower, KEYWORDS.iter().copied()).map(str::to_string)
+}
+
+/// The installed version to offer for a spec that matched nothing: the newest
+/// of the same major.minor, else the newest of the same major, if any
+pub fn suggest_installed(major: u64, minor: Option<u64>, installed: &[String]) -> Option<String> {
+    let newest = |minor: Option<u64>| {
+        installed
+            .iter()
+            .find(|version| {
+                nvm::parse_version(version)
+                    .is_some_and(|(m, n, _)| m == major && minor.is_none_or(|minor| minor == n))
+            })
+            .cloned()
+    };
+    minor
+        .and_then(|minor| newest(Some(minor)))
+        .or_else(|| newest(None))
+}
+
+/// The candidate within two edits of `text`, if any
+fn closest<'a>(text: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .map(|candidate| (edit_distance(text, candidate), candidate))
+        .filter(|(distance, _)| *distance > 0 && *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let diagnostic = Diagnostic::error("Invalid version format: 18..1")
+            .with_file(Path::new(".nvmrc"))
+            .at(2, 3, 5)
+            .with_source_line("  18..1")
+            .with_suggestion(Some("18.1".to_string()));
+        assert_eq!(
+            diagnostic.render(false),
+            "error: Invalid version format: 18..1\n\
+             \x20--> .nvmrc:2:3\n\
+             \x20 |\n\
+             2 |   18..1\n\
+             \x20 |   ^^^^^\n\
+             \x20 = help: did you mean `18.1`?"
+        );
+        assert!(diagnostic.render(true).contains("\x1b[1;31merror\x1b[0m"));
+        assert_eq!(
+            diagnostic.summary(),
+            "Invalid version format: 18..1 (line 2, column 3); did you mean `18.1`?"
+        );
+
+        let bare = Diagnostic::warning("Node.js 18 is not installed");
+        assert_eq!(bare.render(false), "warning: Node.js 18 is not installed");
+    }
+
+    #[test]
+    fn test_suggest_spec() {
+        assert_eq!(suggest_spec("lts").as_deref(), Some("lts/*"));
+        assert_eq!(
+            suggest_spec("lts/hydorgen").as_deref(),
+            Some("lts/hydrogen")
+        );
+        assert_eq!(suggest_spec("18..1").as_deref(), Some("18.1"));
+        assert_eq!(suggest_spec("v18.17.").as_deref(), Some("v18.17"));
+        assert_eq!(suggest_spec("18.17.0.1").as_deref(), Some("18.17.0"));
+        assert_eq!(suggest_spec("stabel").as_deref(), Some("stable"));
+        assert_eq!(suggest_spec("not-a-valid-version"), None);
+    }
+
+    #[test]
+    fn test_suggest_installed() {
+        let installed = vec![
+            "v20.10.0".to_string(),
+            "v18.19.1".to_string(),
+            "v18.17.1".to_string(),
+        ];
+        assert_eq!(
+            suggest_installed(18, None, &installed).as_deref(),
+            Some("v18.19.1")
+        );
+        assert_eq!(
+            suggest_installed(18, Some(17), &installed).as_deref(),
+            Some("v18.17.1")
+        );
+        assert_eq!(
+            suggest_installed(18, Some(18), &installed).as_deref(),
+            Some("v18.19.1")
+        );
+        assert_eq!(suggest_installed(16, None, &installed), None);
+    }
+}
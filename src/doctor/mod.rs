@@ -0,0 +1,527 @@
+use crate::{shell, uninstall};
+use anyhow::{Context, Result};
+use auto_nvm::config::Config;
+use auto_nvm::nvm::{self, ShellType};
+use auto_nvm::nvmrc;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Outcome of a single diagnostic check
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Result of a single diagnostic check, with a suggested fix when it did not pass
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// A line in a shell config file that redefines or hooks directory changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct CdOverride {
+    pub file_path: PathBuf,
+    pub line_number: usize,
+    pub line_content: String,
+}
+
+/// Patterns that indicate another tool is hooking directory changes; `cd`
+/// definitions themselves are found by [`defines_cd`]
+const CD_OVERRIDE_PATTERNS: &[&str] = &[
+    "chpwd_functions",
+    "add-zsh-hook chpwd",
+    "load-nvmrc",
+    "--on-variable PWD",
+];
+
+/// Diagnostic runner for `auto-nvm doctor`
+pub struct Doctor {
+    config: Config,
+}
+
+impl Doctor {
+    pub fn new(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Run all checks, print the report and return true if no check failed
+    pub fn execute(&self, json: bool) -> Result<bool> {
+        let results = run_checks();
+        let healthy = results.iter().all(|r| r.status != CheckStatus::Fail);
+
+        if json {
+            let output = serde_json::to_string_pretty(&results)
+                .context("Failed to serialize doctor results")?;
+            println!("{}", output);
+            return Ok(healthy);
+        }
+
+        for result in &results {
+            // Passing checks are noise in quiet mode; problems are always shown
+            if self.config.is_quiet() && result.status == CheckStatus::Pass {
+                continue;
+            }
+
+            let glyph = match result.status {
+                CheckStatus::Pass => "✓",
+                CheckStatus::Warn => "⚠",
+                CheckStatus::Fail => "✗",
+            };
+            println!("{} {}: {}", glyph, result.name, result.message);
+            if let Some(fix) = &result.fix {
+                println!("    → {}", fix);
+            }
+        }
+
+        if !self.config.is_quiet() {
+            let warnings = results
+                .iter()
+                .filter(|r| r.status == CheckStatus::Warn)
+                .count();
+            let failures = results
+                .iter()
+                .filter(|r| r.status == CheckStatus::Fail)
+                .count();
+            println!();
+            println!(
+                "{} checks, {} warnings, {} failures",
+                results.len(),
+                warnings,
+                failures
+            );
+        }
+
+        Ok(healthy)
+    }
+}
+
+/// Run every diagnostic check in order
+pub fn run_checks() -> Vec<CheckResult> {
+    let shell = nvm::detect_shell();
+    let config_path = shell::get_config_file_path(shell).ok();
+    let rc_files = uninstall::path::get_shell_config_files().unwrap_or_default();
+
+    vec![
+        check_nvm(),
+        check_shell(shell, config_path.as_deref()),
+        check_binary_on_path(),
+        check_integration(shell, config_path.as_deref()),
+        check_duplicate_integration(shell, config_path.as_deref(), &rc_files),
+        check_cd_overrides(&rc_files),
+        check_node_matches_nvm_bin(),
+        check_version_file(),
+    ]
+}
+
+fn check_nvm() -> CheckResult {
+    const NAME: &str = "nvm";
+
+    match nvm::detect_nvm() {
+        Ok(true) => match std::env::var("NVM_DIR") {
+            Ok(dir) => CheckResult::pass(NAME, format!("nvm found (NVM_DIR={})", dir)),
+            Err(_) => CheckResult::pass(NAME, "nvm found"),
+        },
+        _ => CheckResult::fail(
+            NAME,
+            "nvm was not found",
+            "Install nvm (https://github.com/nvm-sh/nvm) and make sure NVM_DIR is exported",
+        ),
+    }
+}
+
+fn check_shell(shell: ShellType, config_path: Option<&Path>) -> CheckResult {
+    const NAME: &str = "shell";
+
+    match config_path {
+        Some(path) => CheckResult::pass(
+            NAME,
+            format!("{:?} detected, config file {}", shell, path.display()),
+        ),
+        None => CheckResult::fail(
+            NAME,
+            format!(
+                "{:?} detected, but its config file could not be located",
+                shell
+            ),
+            "Set SHELL to your login shell and make sure HOME is set",
+        ),
+    }
+}
+
+fn check_binary_on_path() -> CheckResult {
+    const NAME: &str = "binary";
+
+    match which::which("auto-nvm") {
+        Ok(path) => CheckResult::pass(NAME, format!("auto-nvm on PATH at {}", path.display())),
+        Err(_) => CheckResult::fail(
+            NAME,
+            "auto-nvm is not on PATH, so the shell hook cannot call it",
+            "Add the install directory to PATH, e.g. export PATH=\"$HOME/.local/bin:$PATH\"",
+        ),
+    }
+}
+
+fn check_integration(shell: ShellType, config_path: Option<&Path>) -> CheckResult {
+    const NAME: &str = "integration";
+
+    let config_path = match config_path {
+        Some(path) => path,
+        None => {
+            return CheckResult::fail(
+                NAME,
+                "No shell config file to inspect",
+                "Run: auto-nvm setup",
+            )
+        }
+    };
+
+    if !shell::check_already_configured(config_path) {
+        return CheckResult::fail(
+            NAME,
+            format!("No auto-nvm hook in {}", config_path.display()),
+            "Run: auto-nvm setup",
+        );
+    }
+
+    match shell::is_integration_outdated(config_path, shell) {
+        Ok(false) => CheckResult::pass(
+            NAME,
+            format!("Hook installed and up to date in {}", config_path.display()),
+        ),
+        Ok(true) => CheckResult::warn(
+            NAME,
+            format!(
+                "Hook in {} differs from the one this version generates",
+                config_path.display()
+            ),
+            "Run: auto-nvm setup --refresh",
+        ),
+        Err(e) => CheckResult::warn(
+            NAME,
+            format!("Could not read {}: {}", config_path.display(), e),
+            "Check the file permissions of your shell config file",
+        ),
+    }
+}
+
+/// Which shell reads a startup file, judged by its name
+fn rc_file_shell(file: &Path) -> Option<ShellType> {
+    match file.file_name()?.to_str()? {
+        ".bashrc" | ".bash_profile" | ".profile" => Some(ShellType::Bash),
+        ".zshrc" | ".zprofile" => Some(ShellType::Zsh),
+        "config.fish" => Some(ShellType::Fish),
+        _ => None,
+    }
+}
+
+/// Hooks in two startup files of the same shell (e.g. `.bashrc` and
+/// `.bash_profile`) both run; hooks for different shells never do
+fn check_duplicate_integration(
+    shell: ShellType,
+    config_path: Option<&Path>,
+    rc_files: &[PathBuf],
+) -> CheckResult {
+    const NAME: &str = "duplicate-hooks";
+
+    let others: Vec<String> = rc_files
+        .iter()
+        .filter(|file| Some(file.as_path()) != config_path)
+        .filter(|file| rc_file_shell(file) == Some(shell))
+        .filter(|file| shell::check_already_configured(file))
+        .map(|file| file.display().to_string())
+        .collect();
+
+    if others.is_empty() {
+        CheckResult::pass(
+            NAME,
+            format!("No other {:?} config file contains an auto-nvm hook", shell),
+        )
+    } else {
+        CheckResult::warn(
+            NAME,
+            format!("Additional auto-nvm hooks found in: {}", others.join(", ")),
+            "Keep the hook only in the config file setup wrote to",
+        )
+    }
+}
+
+fn check_cd_overrides(rc_files: &[PathBuf]) -> CheckResult {
+    const NAME: &str = "cd-overrides";
+
+    let overrides: Vec<CdOverride> = rc_files
+        .iter()
+        .filter(|file| file.exists())
+        .flat_map(|file| find_cd_overrides(file).unwrap_or_default())
+        .collect();
+
+    if overrides.is_empty() {
+        return CheckResult::pass(NAME, "No other tool overrides cd");
+    }
+
+    let locations: Vec<String> = overrides
+        .iter()
+        .map(|o| {
+            format!(
+                "{}:{} ({})",
+                o.file_path.display(),
+                o.line_number,
+                o.line_content
+            )
+        })
+        .collect();
+
+    CheckResult::warn(
+        NAME,
+        format!("Other cd hooks found: {}", locations.join("; ")),
+        "Remove the other hook, or make sure it runs before the auto-nvm block",
+    )
+}
+
+/// Find lines outside the auto-nvm block that wrap `cd` or hook directory changes
+pub fn find_cd_overrides(file_path: &Path) -> Result<Vec<CdOverride>> {
+    let content = fs::read_to_string(file_path)
+        .with_context(|| format!("Failed to read file {}", file_path.display()))?;
+
+    let mut overrides = Vec::new();
+    let mut in_auto_nvm_block = false;
+
+    for (line_number, line) in content.lines().enumerate() {
+        if line.contains("AUTO-NVM START") {
+            in_auto_nvm_block = true;
+            continue;
+        }
+        if in_auto_nvm_block {
+            if line.contains("AUTO-NVM END") {
+                in_auto_nvm_block = false;
+            }
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') {
+            continue;
+        }
+
+        if defines_cd(trimmed) || CD_OVERRIDE_PATTERNS.iter().any(|p| trimmed.contains(p)) {
+            overrides.push(CdOverride {
+                file_path: file_path.to_path_buf(),
+                line_number: line_number + 1,
+                line_content: trimmed.to_string(),
+            });
+        }
+    }
+
+    Ok(overrides)
+}
+
+/// Whether a (trimmed) line defines `cd` itself: `cd()`, `cd ()`,
+/// `function cd`, or `alias cd=`, but not `mycd()` or `_fzf_cd()`
+fn defines_cd(line: &str) -> bool {
+    if line.starts_with("alias cd=") {
+        return true;
+    }
+    let (name, is_function) = match line.strip_prefix("function ") {
+        Some(rest) => (rest.trim_start(), true),
+        None => (line, false),
+    };
+    let Some(rest) = name.strip_prefix("cd") else {
+        return false;
+    };
+    let rest = rest.trim_start();
+    rest.starts_with("()") || (is_function && (rest.is_empty() || rest.starts_with(['{', '-'])))
+}
+
+fn check_node_matches_nvm_bin() -> CheckResult {
+    const NAME: &str = "node-path";
+
+    let node_path = match which::which("node") {
+        Ok(path) => path,
+        Err(_) => {
+            return CheckResult::warn(
+                NAME,
+                "No node binary on PATH",
+                "Run: nvm use --lts (or cd into a project with a .nvmrc)",
+            )
+        }
+    };
+
+    let nvm_bin = match std::env::var("NVM_BIN") {
+        Ok(bin) => PathBuf::from(bin),
+        Err(_) => {
+            return CheckResult::warn(
+                NAME,
+                format!("node at {}, but NVM_BIN is not set", node_path.display()),
+                "Source nvm.sh in your shell config so nvm manages node",
+            )
+        }
+    };
+
+    if node_path.parent() == Some(nvm_bin.as_path()) {
+        CheckResult::pass(
+            NAME,
+            format!("node on PATH matches NVM_BIN ({})", nvm_bin.display()),
+        )
+    } else {
+        CheckResult::fail(
+            NAME,
+            format!(
+                "node on PATH is {}, but NVM_BIN is {}",
+                node_path.display(),
+                nvm_bin.display()
+            ),
+            "Make sure nvm is sourced after any other PATH changes that add node",
+        )
+    }
+}
+
+fn check_version_file() -> CheckResult {
+    const NAME: &str = "nvmrc";
+
+    let current_dir = match std::env::current_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            return CheckResult::warn(
+                NAME,
+                format!("Could not inspect current directory: {}", e),
+                "Run auto-nvm doctor from an accessible directory",
+            )
+        }
+    };
+
+    match nvmrc::find_version_spec(&current_dir) {
+        Ok(Some((path, version))) => {
+            CheckResult::pass(NAME, format!("{} requests {}", path.display(), version))
+        }
+        Ok(None) => CheckResult::pass(NAME, "No version file in current directory"),
+        Err(e) => CheckResult::fail(
+            NAME,
+            e.to_string(),
+            "Put a single version such as 18.17.0, v18 or lts in the version file",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_find_cd_overrides() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let content = format!(
+            r#"# My config
+alias ll='ls -la'
+# cd() {{ builtin cd "$@"; }}
+cd() {{ builtin cd "$@" && ls; }}
+{}
+add-zsh-hook chpwd load-nvmrc
+"#,
+            shell::generate_integration_script(ShellType::Bash)
+        );
+        fs::write(temp_file.path(), content).unwrap();
+
+        let overrides = find_cd_overrides(temp_file.path()).unwrap();
+        assert_eq!(overrides.len(), 2);
+        assert_eq!(overrides[0].line_number, 4);
+        assert!(overrides[1].line_content.contains("add-zsh-hook"));
+    }
+
+    #[test]
+    fn test_defines_cd() {
+        for line in [
+            "cd() { builtin cd \"$@\"; }",
+            "cd () {",
+            "function cd {",
+            "function cd() {",
+            "function cd --description 'Change directory'",
+            "function cd",
+            "alias cd='z'",
+        ] {
+            assert!(defines_cd(line), "{}", line);
+        }
+        for line in [
+            "mycd() { builtin cd \"$@\"; }",
+            "_fzf_cd() {",
+            "function cdl {",
+            "function mycd",
+            "cd \"$HOME\"",
+            "alias cdd='cd ..'",
+        ] {
+            assert!(!defines_cd(line), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_check_duplicate_integration() {
+        let home = tempfile::tempdir().unwrap();
+        let primary = home.path().join(".bashrc");
+        let other = home.path().join(".bash_profile");
+        let zshrc = home.path().join(".zshrc");
+        let script = shell::generate_integration_script(ShellType::Bash);
+        fs::write(&primary, &script).unwrap();
+        fs::write(&other, "alias ll='ls -la'\n").unwrap();
+        fs::write(&zshrc, shell::generate_integration_script(ShellType::Zsh)).unwrap();
+
+        let rc_files = vec![primary.clone(), other.clone(), zshrc];
+        let result = check_duplicate_integration(ShellType::Bash, Some(&primary), &rc_files);
+        assert_eq!(result.status, CheckStatus::Pass);
+
+        fs::write(&other, &script).unwrap();
+        let result = check_duplicate_integration(ShellType::Bash, Some(&primary), &rc_files);
+        assert_eq!(result.status, CheckStatus::Warn);
+        assert!(result.message.contains(".bash_profile"));
+        assert!(!result.message.contains(".zshrc"));
+        assert!(result.fix.is_some());
+    }
+
+    #[test]
+    fn test_check_result_serialization() {
+        let result = CheckResult::fail("nvm", "nvm was not found", "Install nvm");
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["status"], "fail");
+        assert_eq!(json["fix"], "Install nvm");
+
+        let result = CheckResult::pass("nvm", "nvm found");
+        let json = serde_json::to_value(&result).unwrap();
+        assert!(json.get("fix").is_none());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::diagnostic::Diagnostic;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+
+/// Errors returned by the auto-nvm library
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading or writing a file or directory failed
+    #[error("Failed to {action} {}", path.display())]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The current working directory is not accessible
+    #[error("Failed to determine the current directory")]
+    CurrentDir(#[source] io::Error),
+
+    /// A version specification is not in a recognised format
+    #[error("{message}")]
+    InvalidSpec { spec: String, message: String },
+
+    /// An .nvmrc cannot be parsed
+    #[error("{}", .0.summary())]
+    NvmrcSyntax(Box<Diagnostic>),
+
+    /// A version file exists but holds no version
+    #[error("{} file is empty or contains only whitespace", file_name(path))]
+    EmptyVersionFile { path: PathBuf },
+
+    /// A file that has to be edited in place does not exist
+    #[error("No {} in {}", file_name(path), path.parent().unwrap_or(path).display())]
+    MissingFile { path: PathBuf },
+
+    /// A JSON document does not have the expected structure
+    #[error("{message}")]
+    InvalidJson { message: String },
+
+    /// A JSON document could not be parsed or produced
+    #[error("{context}")]
+    Json {
+        context: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// The `packageManager` field of package.json is not `name@version`
+    #[error("{message}")]
+    InvalidPackageManager { value: String, message: String },
+
+    /// An environment variable holds a value auto-nvm does not understand
+    #[error("Invalid {name} value {value:?}; expected one of: {expected}")]
+    InvalidEnv {
+        name: &'static str,
+        value: String,
+        expected: String,
+    },
+
+    /// A release schedule is malformed
+    #[error("{message}")]
+    InvalidSchedule { message: String },
+
+    /// An error in a specific file
+    #[error("{}: {error}", path.display())]
+    InFile { path: PathBuf, error: Box<Error> },
+
+    /// A program could not be started
+    #[error("Failed to run {program}")]
+    Spawn {
+        program: String,
+        #[source]
+        source: io::Error,
+    },
+
+    /// A program ran but failed
+    #[error("{program} exited with {status}: {message}")]
+    CommandFailed {
+        program: String,
+        status: ExitStatus,
+        message: String,
+    },
+
+    /// nvm could not be found
+    #[error("nvm is not available on this system")]
+    NvmNotFound,
+
+    /// No Node.js version is active in the current environment
+    #[error("No Node.js version currently active")]
+    NoActiveVersion,
+
+    /// A standard directory (home, cache, data) could not be determined
+    #[error("Could not determine the {0} directory")]
+    NoDirectory(&'static str),
+
+    /// A download failed
+    #[error("Failed to fetch {url}")]
+    Http {
+        url: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// A server answered with a status auto-nvm cannot handle
+    #[error("Unexpected {status} from {url}")]
+    UnexpectedResponse { url: String, status: u16 },
+}
+
+/// Result type of the auto-nvm library
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// Attach the file an error occurred in
+    pub(crate) fn in_file(self, path: &Path) -> Self {
+        Error::InFile {
+            path: path.to_path_buf(),
+            error: Box::new(self),
+        }
+    }
+}
+
+/// `map_err` adapter turning an I/O error into [`Error::Io`]
+pub(crate) fn io_error(action: &'static str, path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |source| Error::Io {
+        action,
+        path,
+        source,
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
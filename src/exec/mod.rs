@@ -0,0 +1,185 @@
+use anyhow::{anyhow, Context, Result};
+use auto_nvm::{nvm, nvmrc, resolve, VersionSpec};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A Node.js version installed in the nvm directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeInstallation {
+    pub version: String,
+    pub dir: PathBuf,
+}
+
+impl NodeInstallation {
+    /// Directory containing `node`, `npm`, `npx` and global package binaries
+    pub fn bin_dir(&self) -> PathBuf {
+        self.dir.join("bin")
+    }
+
+    /// Directory containing globally installed packages
+    pub fn node_modules_dir(&self) -> PathBuf {
+        self.dir.join("lib").join("node_modules")
+    }
+}
+
+/// Read and validate the version spec from the version file in the given
+/// directory
+pub fn read_version_spec(dir: &Path) -> Result<Option<VersionSpec>> {
+    Ok(nvmrc::find_version_spec(dir)?.map(|(_, spec)| spec))
+}
+
+/// Find the installed Node.js version that satisfies the given spec
+pub fn find_installation(spec: &VersionSpec) -> Result<NodeInstallation> {
+    let nvm_dir =
+        nvm::nvm_dir().ok_or_else(|| anyhow!("Could not find the nvm directory; set NVM_DIR"))?;
+    find_installation_in(&nvm_dir, spec)
+}
+
+/// Find the installed Node.js version in the given nvm directory that satisfies the spec
+pub fn find_installation_in(nvm_dir: &Path, spec: &VersionSpec) -> Result<NodeInstallation> {
+    // Fast path: exact versions map straight to a directory without listing
+    if spec.is_exact() {
+        let dir = nvm::version_dir(nvm_dir, &spec.to_string());
+        if dir.is_dir() {
+            let version = dir
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            return Ok(NodeInstallation { version, dir });
+        }
+    }
+
+    let installed = nvm::list_installed_versions_in(nvm_dir)?;
+
+    let version = resolve::resolve_version(spec, &installed, Some(nvm_dir)).ok_or_else(|| {
+        anyhow!(
+            "Node.js {} is not installed. Run: auto-nvm install {}",
+            spec,
+            spec
+        )
+    })?;
+
+    Ok(NodeInstallation {
+        dir: nvm::version_dir(nvm_dir, &version),
+        version,
+    })
+}
+
+/// Build the environment variables that activate the given installation
+///
+/// Other nvm-managed versions are removed from PATH so the installation's
+/// binaries always win.
+pub fn build_env(
+    installation: &NodeInstallation,
+    current_path: Option<&OsStr>,
+) -> Result<Vec<(OsString, OsString)>> {
+    let bin_dir = installation.bin_dir();
+    let versions_root = installation
+        .dir
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let mut paths = vec![bin_dir.clone()];
+    if let Some(current_path) = current_path {
+        paths.extend(
+            std::env::split_paths(current_path)
+                .filter(|entry| !(entry.starts_with(&versions_root) && entry.ends_with("bin"))),
+        );
+    }
+    let path = std::env::join_paths(paths).context("Failed to build PATH")?;
+
+    Ok(vec![
+        (OsString::from("PATH"), path),
+        (OsString::from("NVM_BIN"), bin_dir.into_os_string()),
+        (
+            OsString::from("NODE_PATH"),
+            installation.node_modules_dir().into_os_string(),
+        ),
+    ])
+}
+
+/// Run a program with the given environment, replacing the current process on Unix
+pub fn exec_command(
+    program: &OsStr,
+    args: &[OsString],
+    envs: &[(OsString, OsString)],
+) -> Result<()> {
+    let mut command = Command::new(program);
+    command.args(args).envs(envs.iter().map(|(k, v)| (k, v)));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // exec only returns on failure
+        let error = command.exec();
+        Err(anyhow!(
+            "Failed to execute {}: {}",
+            program.to_string_lossy(),
+            error
+        ))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let status = command
+            .status()
+            .with_context(|| format!("Failed to execute {}", program.to_string_lossy()))?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_installation_in() {
+        let nvm_dir = tempfile::tempdir().unwrap();
+        for version in ["v18.17.0", "v20.10.0"] {
+            fs::create_dir_all(nvm_dir.path().join("versions/node").join(version)).unwrap();
+        }
+
+        let installation = find_installation_in(nvm_dir.path(), &"18".parse().unwrap()).unwrap();
+        assert_eq!(installation.version, "v18.17.0");
+        assert_eq!(
+            installation.bin_dir(),
+            nvm_dir.path().join("versions/node/v18.17.0/bin")
+        );
+
+        let error = find_installation_in(nvm_dir.path(), &"16".parse().unwrap()).unwrap_err();
+        assert!(error.to_string().contains("Run: auto-nvm install 16"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_env_replaces_other_versions() {
+        let installation = NodeInstallation {
+            version: "v20.10.0".to_string(),
+            dir: PathBuf::from("/home/user/.nvm/versions/node/v20.10.0"),
+        };
+        let current_path =
+            OsString::from("/home/user/.nvm/versions/node/v18.17.0/bin:/usr/local/bin:/usr/bin");
+
+        let envs = build_env(&installation, Some(&current_path)).unwrap();
+        let get = |key: &str| {
+            envs.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.to_string_lossy().to_string())
+                .unwrap()
+        };
+
+        assert_eq!(
+            get("PATH"),
+            "/home/user/.nvm/versions/node/v20.10.0/bin:/usr/local/bin:/usr/bin"
+        );
+        assert_eq!(get("NVM_BIN"), "/home/user/.nvm/versions/node/v20.10.0/bin");
+        assert_eq!(
+            get("NODE_PATH"),
+            "/home/user/.nvm/versions/node/v20.10.0/lib/node_modules"
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use crate::exec::{self, NodeInstallation};
+use anyhow::{anyhow, Context, Result};
+use auto_nvm::index;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Packages that ship with Node.js itself and must not be reinstalled
+const BUNDLED_PACKAGES: &[&str] = &["npm", "corepack"];
+
+/// A package installed globally under a Node.js version
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalPackage {
+    pub name: String,
+    pub version: String,
+    /// Installed with `npm link`; these point at a local checkout and cannot
+    /// be reinstalled from the registry
+    pub linked: bool,
+}
+
+impl GlobalPackage {
+    /// Argument for `npm install -g`, pinned to the installed version unless `latest`
+    pub fn install_arg(&self, latest: bool) -> String {
+        if latest {
+            self.name.clone()
+        } else {
+            format!("{}@{}", self.name, self.version)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PackageManifest {
+    name: Option<String>,
+    version: Option<String>,
+}
+
+/// Read the globally installed packages of an installation from its
+/// `lib/node_modules`, without running npm
+///
+/// Packages bundled with Node.js (npm, corepack) are left out. The result is
+/// sorted by name.
+pub fn list_global_packages(installation: &NodeInstallation) -> Result<Vec<GlobalPackage>> {
+    let node_modules = installation.node_modules_dir();
+    let mut packages = Vec::new();
+
+    if !node_modules.is_dir() {
+        return Ok(packages);
+    }
+
+    for entry in read_dir_sorted(&node_modules)? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // .bin, .package-lock.json and friends
+        if name.starts_with('.') {
+            continue;
+        }
+
+        if name.starts_with('@') {
+            for scoped in read_dir_sorted(&entry.path())? {
+                packages.extend(read_package(&scoped.path())?);
+            }
+        } else {
+            packages.extend(read_package(&entry.path())?);
+        }
+    }
+
+    packages.retain(|package| !BUNDLED_PACKAGES.contains(&package.name.as_str()));
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(packages)
+}
+
+/// Packages of `from` that are missing from `to`, or installed there at an
+/// older version
+///
+/// A package `to` already has at the same or a newer version is never
+/// touched, and with `latest` neither is one it has at any version.
+pub fn packages_to_migrate(
+    from: &[GlobalPackage],
+    to: &[GlobalPackage],
+    latest: bool,
+) -> Vec<GlobalPackage> {
+    from.iter()
+        .filter(
+            |package| match to.iter().find(|existing| existing.name == package.name) {
+                Some(existing) => !latest && index::is_newer(&package.version, &existing.version),
+                None => true,
+            },
+        )
+        .cloned()
+        .collect()
+}
+
+/// Install packages globally under the target installation using its own npm
+pub fn install_packages(
+    target: &NodeInstallation,
+    packages: &[GlobalPackage],
+    latest: bool,
+) -> Result<()> {
+    if packages.is_empty() {
+        return Ok(());
+    }
+
+    let npm = target
+        .bin_dir()
+        .join(if cfg!(windows) { "npm.cmd" } else { "npm" });
+    if !npm.exists() {
+        return Err(anyhow!(
+            "npm not found for Node.js {} ({})",
+            target.version,
+            npm.display()
+        ));
+    }
+
+    let envs = exec::build_env(target, std::env::var_os("PATH").as_deref())?;
+    let status = Command::new(&npm)
+        .args(["install", "--global"])
+        .args(packages.iter().map(|package| package.install_arg(latest)))
+        .envs(envs)
+        .status()
+        .with_context(|| format!("Failed to run {}", npm.display()))?;
+
+    if !status.success() {
+        return Err(anyhow!("npm install --global exited with {}", status));
+    }
+
+    Ok(())
+}
+
+fn read_dir_sorted(dir: &Path) -> Result<Vec<fs::DirEntry>> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    Ok(entries)
+}
+
+/// Read the manifest of a package directory; `None` if it has none
+fn read_package(dir: &Path) -> Result<Option<GlobalPackage>> {
+    let manifest_path = dir.join("package.json");
+    let content = match fs::read_to_string(&manifest_path) {
+        Ok(content) => content,
+        Err(_) => return Ok(None),
+    };
+    let manifest: PackageManifest = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid {}", manifest_path.display()))?;
+
+    let (name, version) = match (manifest.name, manifest.version) {
+        (Some(name), Some(version)) => (name, version),
+        _ => return Ok(None),
+    };
+    let linked = dir
+        .symlink_metadata()
+        .map(|metadata| metadata.file_type().is_symlink())
+        .unwrap_or(false);
+
+    Ok(Some(GlobalPackage {
+        name,
+        version,
+        linked,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn write_package(node_modules: &Path, name: &str, version: &str) {
+        let dir = node_modules.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("package.json"),
+            format!(r#"{{"name": "{}", "version": "{}"}}"#, name, version),
+        )
+        .unwrap();
+    }
+
+    fn package(name: &str, version: &str) -> GlobalPackage {
+        GlobalPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            linked: false,
+        }
+    }
+
+    #[test]
+    fn test_list_global_packages() {
+        let dir = tempfile::tempdir().unwrap();
+        let installation = NodeInstallation {
+            version: "v18.17.1".to_string(),
+            dir: dir.path().to_path_buf(),
+        };
+        let node_modules = installation.node_modules_dir();
+        write_package(&node_modules, "typescript", "5.3.3");
+        write_package(&node_modules, "npm", "9.6.7");
+        write_package(&node_modules, "@antfu/ni", "0.21.12");
+        write_package(&node_modules, "pnpm", "8.15.1");
+        fs::create_dir_all(node_modules.join(".bin")).unwrap();
+        fs::create_dir_all(node_modules.join("broken")).unwrap();
+
+        let packages = list_global_packages(&installation).unwrap();
+        assert_eq!(
+            packages,
+            vec![
+                package("@antfu/ni", "0.21.12"),
+                package("pnpm", "8.15.1"),
+                package("typescript", "5.3.3"),
+            ]
+        );
+
+        let missing = NodeInstallation {
+            version: "v20.10.0".to_string(),
+            dir: PathBuf::from("/nonexistent"),
+        };
+        assert!(list_global_packages(&missing).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_packages_to_migrate() {
+        let from = vec![
+            package("eslint", "8.57.0"),
+            package("pnpm", "8.15.1"),
+            package("prettier", "3.2.5"),
+            package("typescript", "5.3.3"),
+        ];
+        let to = vec![
+            package("pnpm", "8.15.1"),
+            package("prettier", "3.0.0"),
+            package("typescript", "5.4.2"),
+        ];
+
+        // typescript 5.4.2 is newer than the source's 5.3.3 and is kept
+        assert_eq!(
+            packages_to_migrate(&from, &to, false),
+            vec![package("eslint", "8.57.0"), package("prettier", "3.2.5")]
+        );
+        assert_eq!(
+            packages_to_migrate(&from, &to, true),
+            vec![package("eslint", "8.57.0")]
+        );
+        assert_eq!(from[1].install_arg(false), "pnpm@8.15.1");
+        assert_eq!(from[1].install_arg(true), "pnpm");
+    }
+}
@@ -0,0 +1,231 @@
+use super::{Topic, TOPICS};
+use crate::Cli;
+use anyhow::{Context, Result};
+use clap::CommandFactory;
+use clap_mangen::roff::{bold, roman, Inline, Roff};
+use clap_mangen::Man;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A rendered man page
+pub struct Page {
+    /// File name, e.g. `auto-nvm-pin.1`
+    pub name: String,
+    /// Section directory, e.g. `man1`
+    pub section: &'static str,
+    pub content: String,
+}
+
+/// Pages for `auto-nvm`, every subcommand (section 1) and every help topic
+/// (section 7)
+pub fn pages() -> Result<Vec<Page>> {
+    let mut cmd = Cli::command();
+    // Gives subcommands their `auto-nvm-pin` page names
+    cmd.build();
+
+    let mut pages = Vec::new();
+    command_pages(&cmd, &mut pages)?;
+    if let Some(main) = pages.first_mut() {
+        // Page names have no apostrophes, so no second preamble is needed
+        main.content.push_str(&see_also(&cmd).to_roff());
+    }
+    for topic in TOPICS {
+        pages.push(Page {
+            name: format!("auto-nvm-{}.7", topic.name),
+            section: "man7",
+            content: topic_page(topic).render(),
+        });
+    }
+    Ok(pages)
+}
+
+fn command_pages(cmd: &clap::Command, pages: &mut Vec<Page>) -> Result<()> {
+    let man = Man::new(cmd.clone()).source(format!("auto-nvm {}", env!("CARGO_PKG_VERSION")));
+    let mut content = Vec::new();
+    man.render(&mut content)
+        .context("Failed to render man page")?;
+    pages.push(Page {
+        name: man.get_filename(),
+        section: "man1",
+        content: String::from_utf8_lossy(&content).to_string(),
+    });
+
+    for sub in cmd.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        command_pages(sub, pages)?;
+    }
+    Ok(())
+}
+
+/// SEE ALSO section of the main page, naming the other pages
+fn see_also(cmd: &clap::Command) -> Roff {
+    let mut names: Vec<String> = cmd
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set())
+        .map(|sub| format!("auto-nvm-{}(1)", sub.get_name()))
+        .collect();
+    names.extend(
+        TOPICS
+            .iter()
+            .map(|topic| format!("auto-nvm-{}(7)", topic.name)),
+    );
+
+    let mut roff = Roff::new();
+    roff.control("SH", ["SEE ALSO"])
+        .text([roman(names.join(", "))]);
+    roff
+}
+
+/// A help topic's Markdown as a man page
+fn topic_page(topic: &Topic) -> Roff {
+    let title = format!("AUTO-NVM-{}", topic.name.to_uppercase());
+    let source = format!("auto-nvm {}", env!("CARGO_PKG_VERSION"));
+    let mut roff = Roff::new();
+    roff.control("TH", [title.as_str(), "7", "", source.as_str()])
+        .control("SH", ["NAME"])
+        .text([roman(format!(
+            "auto-nvm-{} - {}",
+            topic.name, topic.summary
+        ))]);
+
+    let mut in_code = false;
+    let mut paragraph: Vec<&str> = Vec::new();
+    let flush = |roff: &mut Roff, paragraph: &mut Vec<&str>| {
+        if !paragraph.is_empty() {
+            roff.control("PP", []).text(inline(&paragraph.join(" ")));
+            paragraph.clear();
+        }
+    };
+
+    for line in topic.text.lines() {
+        if line.starts_with("```") {
+            flush(&mut roff, &mut paragraph);
+            if in_code {
+                roff.control("fi", []).control("RE", []);
+            } else {
+                roff.control("RS", ["4"]).control("nf", []);
+            }
+            in_code = !in_code;
+        } else if in_code {
+            roff.text([roman(line)]);
+        } else if line.starts_with("# ") {
+            // The title; NAME already says what the page is
+        } else if let Some(heading) = line.strip_prefix("## ") {
+            flush(&mut roff, &mut paragraph);
+            roff.control("SH", [heading.to_uppercase().as_str()]);
+        } else if let Some(item) = line.strip_prefix("- ") {
+            flush(&mut roff, &mut paragraph);
+            roff.control("IP", ["\\(bu", "2"]).text(inline(item));
+        } else if line.trim().is_empty() {
+            flush(&mut roff, &mut paragraph);
+        } else {
+            paragraph.push(line.trim());
+        }
+    }
+    flush(&mut roff, &mut paragraph);
+    roff
+}
+
+/// Text with `code` spans in bold
+fn inline(text: &str) -> Vec<Inline> {
+    text.split('`')
+        .enumerate()
+        .filter(|(_, part)| !part.is_empty())
+        .map(|(index, part)| {
+            if index % 2 == 1 {
+                bold(part)
+            } else {
+                roman(part)
+            }
+        })
+        .collect()
+}
+
+/// The user's man directory (`$XDG_DATA_HOME/man`), which `man` searches
+/// when the binary is in `~/.local/bin`
+pub fn default_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")))
+        .map(|dir| dir.join("man"))
+}
+
+/// Write every page below `dir` (`dir/man1/auto-nvm.1`, ...)
+pub fn install(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::new();
+    for page in pages()? {
+        let section = dir.join(page.section);
+        fs::create_dir_all(&section)
+            .with_context(|| format!("Failed to create {}", section.display()))?;
+        let path = section.join(&page.name);
+        fs::write(&path, page.content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Whether the pages are installed in `dir`
+pub fn is_installed(dir: &Path) -> bool {
+    dir.join("man1/auto-nvm.1").is_file()
+}
+
+/// Delete the pages from `dir`
+pub fn remove(dir: &Path) -> Result<()> {
+    for page in pages()? {
+        let path = dir.join(page.section).join(&page.name);
+        if path.is_file() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages() -> Result<()> {
+        let pages = pages()?;
+        let page = |name: &str| {
+            pages
+                .iter()
+                .find(|page| page.name == name)
+                .unwrap_or_else(|| panic!("no page {}", name))
+        };
+
+        let main = page("auto-nvm.1");
+        assert!(main.content.contains("\n.TH auto-nvm 1 "));
+        assert!(main.content.contains(", auto\\-nvm\\-hooks(7)\n"));
+        assert_eq!(main.content.matches(".ds Aq").count(), 2);
+        assert!(page("auto-nvm-pin.1").content.contains("\\-\\-exact"));
+        assert!(page("auto-nvm-shims-install.1")
+            .content
+            .contains(".TH auto-nvm-shims-install 1"));
+        assert!(!pages
+            .iter()
+            .any(|page| page.name.contains("complete-versions")));
+
+        let hooks = &page("auto-nvm-hooks.7").content;
+        assert!(hooks.contains("\n.TH AUTO-NVM-HOOKS 7 "));
+        assert!(hooks.contains(".SH \"KEEPING HOOKS CURRENT\""));
+        assert!(hooks.contains(".nf\nauto\\-nvm \\-\\-quiet switch \\-\\-shell bash\n.fi\n.RE\n"));
+        assert!(!hooks.contains("```"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_and_remove() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let written = install(dir.path())?;
+        assert!(is_installed(dir.path()));
+        assert!(dir.path().join("man7/auto-nvm-config.7").is_file());
+
+        remove(dir.path())?;
+        assert!(written.iter().all(|path| !path.exists()));
+        assert!(!is_installed(dir.path()));
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use crate::Cli;
+use anyhow::{anyhow, Result};
+use clap::CommandFactory;
+
+pub mod man;
+
+/// Documentation that is not about a single command, shared by
+/// `auto-nvm help <topic>`, the man pages and docs/topics/
+pub struct Topic {
+    pub name: &'static str,
+    pub summary: &'static str,
+    /// Markdown source
+    pub text: &'static str,
+}
+
+pub const TOPICS: &[Topic] = &[
+    Topic {
+        name: "version-files",
+        summary: "Version files auto-nvm reads and the version formats they may contain",
+        text: include_str!("../../docs/topics/version-files.md"),
+    },
+    Topic {
+        name: "config",
+        summary: "Environment variables and per-project auto-nvm.toml",
+        text: include_str!("../../docs/topics/config.md"),
+    },
+    Topic {
+        name: "hooks",
+        summary: "How the shell hooks switch versions, and keeping them current",
+        text: include_str!("../../docs/topics/hooks.md"),
+    },
+];
+
+pub fn find_topic(name: &str) -> Option<&'static Topic> {
+    TOPICS.iter().find(|topic| topic.name == name)
+}
+
+/// Listing appended to `auto-nvm --help`
+pub fn topics_help() -> String {
+    let mut help = String::from("Help topics (auto-nvm help <TOPIC>):\n");
+    for topic in TOPICS {
+        help.push_str(&format!("  {:<15}{}\n", topic.name, topic.summary));
+    }
+    help
+}
+
+/// Help for `auto-nvm help [COMMAND...|TOPIC]`
+pub fn help_text(names: &[String]) -> Result<String> {
+    if let [name] = names {
+        if let Some(topic) = find_topic(name) {
+            return Ok(render_text(topic.text));
+        }
+    }
+
+    let mut cmd = Cli::command();
+    cmd.build();
+    let mut current = &mut cmd;
+    for name in names {
+        current = current.find_subcommand_mut(name).ok_or_else(|| {
+            let topics: Vec<&str> = TOPICS.iter().map(|topic| topic.name).collect();
+            anyhow!(
+                "Unknown command or help topic: {} (topics: {})",
+                names.join(" "),
+                topics.join(", ")
+            )
+        })?;
+    }
+    Ok(current.render_long_help().to_string())
+}
+
+/// Markdown as plain terminal text: heading markers and code fences are
+/// dropped and code is indented
+pub fn render_text(markdown: &str) -> String {
+    let mut text = String::new();
+    let mut in_code = false;
+    for line in markdown.lines() {
+        if line.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            text.push_str("    ");
+            text.push_str(line);
+        } else {
+            let heading = line.strip_prefix("## ").or_else(|| line.strip_prefix("# "));
+            text.push_str(heading.unwrap_or(line));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topics() {
+        for topic in TOPICS {
+            let text = help_text(&[topic.name.to_string()]).unwrap();
+            assert!(!text.contains("```"), "{}", topic.name);
+            assert!(!text.starts_with('#'), "{}", topic.name);
+        }
+        let hooks = render_text(find_topic("hooks").unwrap().text);
+        assert!(hooks.starts_with("Shell hooks\n"));
+        assert!(hooks.contains("\n    auto-nvm --quiet switch --shell bash\n"));
+
+        assert!(help_text(&["shims".into(), "install".into()])
+            .unwrap()
+            .contains("Usage: auto-nvm shims install"));
+        assert!(help_text(&["ls".into()]).unwrap().contains("--scan"));
+        let error = help_text(&["hook".into()]).unwrap_err().to_string();
+        assert!(error.contains("topics: version-files, config, hooks"));
+    }
+}
@@ -0,0 +1,412 @@
+use crate::config::Config;
+use crate::error::{io_error, Error, Result};
+use crate::log;
+use crate::nvm;
+use crate::spec::VersionSpec;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Official Node.js distribution mirror
+pub const DEFAULT_MIRROR: &str = "https://nodejs.org/dist";
+
+/// How long a downloaded index is used before it is revalidated
+const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Network timeout for index requests
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// User-Agent sent with every request to a mirror
+pub const USER_AGENT: &str = concat!("auto-nvm/", env!("CARGO_PKG_VERSION"));
+
+/// A release listed in the mirror's index.json
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    #[serde(default)]
+    pub date: String,
+    /// LTS codename, or `None` for Current releases (`false` in index.json)
+    #[serde(default, deserialize_with = "lts_codename")]
+    pub lts: Option<String>,
+    /// Available builds, e.g. `linux-x64`, `osx-arm64-tar`
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+fn lts_codename<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(codename) => Some(codename),
+        _ => None,
+    })
+}
+
+/// The list of published Node.js releases, newest first
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteIndex {
+    pub releases: Vec<Release>,
+}
+
+impl RemoteIndex {
+    pub fn parse(json: &str) -> Result<Self> {
+        let mut releases: Vec<Release> =
+            serde_json::from_str(json).map_err(|source| Error::Json {
+                context: "Invalid index.json".to_string(),
+                source,
+            })?;
+        releases.retain(|release| nvm::parse_version(&release.version).is_some());
+        releases.sort_by_key(|release| std::cmp::Reverse(nvm::parse_version(&release.version)));
+        Ok(Self { releases })
+    }
+
+    /// Newest release satisfying a spec: aliases (`node`, `lts/*`,
+    /// `lts/iron`, `lts/-1`), partial or exact versions, or npm-style ranges
+    pub fn resolve(&self, spec: &VersionSpec) -> Option<&Release> {
+        match spec {
+            VersionSpec::Latest => self.releases.first(),
+            VersionSpec::LtsOffset(offset) => {
+                let mut codenames: Vec<&str> = Vec::new();
+                for codename in self.releases.iter().filter_map(|r| r.lts.as_deref()) {
+                    if !codenames.contains(&codename) {
+                        codenames.push(codename);
+                    }
+                }
+                let codename = codenames.get(*offset as usize)?;
+                self.releases
+                    .iter()
+                    .find(|r| r.lts.as_deref() == Some(*codename))
+            }
+            VersionSpec::LtsCodename(name) => self.releases.iter().find(|r| {
+                r.lts
+                    .as_deref()
+                    .is_some_and(|codename| codename.eq_ignore_ascii_case(name))
+            }),
+            // Local aliases have no meaning on a mirror
+            VersionSpec::Alias(_) | VersionSpec::System => None,
+            VersionSpec::Exact { .. } | VersionSpec::Partial { .. } | VersionSpec::Range(_) => {
+                self.releases.iter().find(|release| {
+                    nvm::parse_version(&release.version)
+                        .is_some_and(|version| spec.matches(&version))
+                })
+            }
+        }
+    }
+
+    /// Newest release of a major version
+    pub fn latest_in_major(&self, major: u64) -> Option<&Release> {
+        self.releases.iter().find(|release| {
+            nvm::parse_version(&release.version).is_some_and(|(m, _, _)| m == major)
+        })
+    }
+}
+
+/// Cache bookkeeping stored next to the cached index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheMeta {
+    url: String,
+    etag: Option<String>,
+    fetched_at: u64,
+}
+
+/// Fetches index.json from a mirror, with an ETag/TTL cache
+pub struct IndexClient {
+    mirror: String,
+    cache_dir: Option<PathBuf>,
+    ttl: Duration,
+}
+
+impl IndexClient {
+    /// Client for the configured mirror, else `NVM_NODEJS_ORG_MIRROR`, else nodejs.org
+    pub fn new(config: &Config) -> Self {
+        let mirror = config
+            .node_mirror
+            .clone()
+            .or_else(|| std::env::var("NVM_NODEJS_ORG_MIRROR").ok())
+            .filter(|mirror| !mirror.is_empty())
+            .unwrap_or_else(|| DEFAULT_MIRROR.to_string());
+
+        let ttl = std::env::var("AUTO_NVM_INDEX_TTL")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TTL);
+
+        Self {
+            mirror,
+            cache_dir: default_cache_dir(),
+            ttl,
+        }
+    }
+
+    /// Use another cache directory (`None` disables caching)
+    pub fn with_cache_dir(mut self, cache_dir: Option<PathBuf>) -> Self {
+        self.cache_dir = cache_dir;
+        self
+    }
+
+    /// Base URL of the mirror, e.g. `https://nodejs.org/dist`
+    pub fn mirror(&self) -> &str {
+        self.mirror.trim_end_matches('/')
+    }
+
+    fn index_url(&self) -> String {
+        format!("{}/index.json", self.mirror.trim_end_matches('/'))
+    }
+
+    /// The cached index regardless of its age, without touching the network
+    pub fn cached(&self) -> Option<RemoteIndex> {
+        let Some((meta, content)) = self.read_cache() else {
+            log::debug("index cache miss");
+            return None;
+        };
+        if meta.url != self.index_url() {
+            log::debug(format!("index cache miss: cached for {}", meta.url));
+            return None;
+        }
+        log::debug(format!(
+            "index cache hit ({}s old)",
+            now().saturating_sub(meta.fetched_at)
+        ));
+        RemoteIndex::parse(&content).ok()
+    }
+
+    /// The index, from the cache while it is fresh, else from the mirror
+    ///
+    /// A stale cache is revalidated with `If-None-Match`; if the mirror is
+    /// unreachable the stale copy is used rather than failing.
+    pub fn fetch(&self) -> Result<RemoteIndex> {
+        let url = self.index_url();
+
+        if let Some(path) = url.strip_prefix("file://") {
+            let content = fs::read_to_string(path).map_err(io_error("read", Path::new(path)))?;
+            return RemoteIndex::parse(&content);
+        }
+
+        let cached = self.read_cache().filter(|(meta, _)| meta.url == url);
+        if let Some((meta, content)) = &cached {
+            let age = now().saturating_sub(meta.fetched_at);
+            if age < self.ttl.as_secs() {
+                log::debug(format!("index cache hit ({}s old)", age));
+                return RemoteIndex::parse(content);
+            }
+            log::debug(format!("index cache stale ({}s old), revalidating", age));
+        } else {
+            log::debug(format!("index cache miss, fetching {}", url));
+        }
+
+        let etag = cached.as_ref().and_then(|(meta, _)| meta.etag.as_deref());
+        let (content, etag) = match http_get(&url, etag) {
+            Ok(Some(fetched)) => fetched,
+            Ok(None) => {
+                // 304 Not Modified: keep the cached body, restart the TTL
+                let (meta, content) = cached.ok_or_else(|| Error::UnexpectedResponse {
+                    url: url.clone(),
+                    status: 304,
+                })?;
+                (content, meta.etag)
+            }
+            Err(e) => match cached {
+                Some((_, content)) => return RemoteIndex::parse(&content),
+                None => return Err(e),
+            },
+        };
+
+        let index = RemoteIndex::parse(&content)?;
+        // Caching is best effort; a read-only cache dir must not break resolution
+        let _ = self.write_cache(
+            &CacheMeta {
+                url,
+                etag,
+                fetched_at: now(),
+            },
+            &content,
+        );
+
+        Ok(index)
+    }
+
+    /// Parse an index.json obtained elsewhere and cache it as if it had just
+    /// been fetched from the mirror
+    pub fn store(&self, content: &str) -> Result<RemoteIndex> {
+        let index = RemoteIndex::parse(content)?;
+        let meta = CacheMeta {
+            url: self.index_url(),
+            etag: None,
+            fetched_at: now(),
+        };
+        self.write_cache(&meta, content)
+            .map_err(|source| Error::Io {
+                action: "write",
+                path: self.cache_dir.clone().unwrap_or_default(),
+                source,
+            })?;
+        Ok(index)
+    }
+
+    fn read_cache(&self) -> Option<(CacheMeta, String)> {
+        let dir = self.cache_dir.as_ref()?;
+        let meta = fs::read_to_string(dir.join("index.meta.json")).ok()?;
+        let meta: CacheMeta = serde_json::from_str(&meta).ok()?;
+        let content = fs::read_to_string(dir.join("index.json")).ok()?;
+        Some((meta, content))
+    }
+
+    fn write_cache(&self, meta: &CacheMeta, content: &str) -> std::io::Result<()> {
+        let Some(dir) = self.cache_dir.as_ref() else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("index.json"), content)?;
+        fs::write(
+            dir.join("index.meta.json"),
+            serde_json::to_string_pretty(meta)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// Cache directory (`AUTO_NVM_CACHE_DIR`, else the user cache dir)
+pub fn default_cache_dir() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var("AUTO_NVM_CACHE_DIR") {
+        if !dir.is_empty() {
+            return Some(PathBuf::from(dir));
+        }
+    }
+
+    dirs::cache_dir().map(|dir| dir.join("auto-nvm"))
+}
+
+/// GET a URL, returning the body and ETag, or `None` on 304 Not Modified
+fn http_get(url: &str, etag: Option<&str>) -> Result<Option<(String, Option<String>)>> {
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    let mut request = agent.get(url).set("User-Agent", USER_AGENT);
+    if let Some(etag) = etag {
+        request = request.set("If-None-Match", etag);
+    }
+
+    let response = request.call().map_err(|source| Error::Http {
+        url: url.to_string(),
+        source: Box::new(source),
+    })?;
+    if response.status() == 304 {
+        return Ok(None);
+    }
+
+    let etag = response.header("ETag").map(str::to_string);
+    let mut body = String::new();
+    response
+        .into_reader()
+        .read_to_string(&mut body)
+        .map_err(|source| Error::Http {
+            url: url.to_string(),
+            source: Box::new(source),
+        })?;
+
+    Ok(Some((body, etag)))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Whether `candidate` is a newer version than `current`
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    match (nvm::parse_version(candidate), nvm::parse_version(current)) {
+        (Some(candidate), Some(current)) => candidate > current,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const INDEX: &str = r#"[
+        {"version": "v21.7.3", "date": "2024-04-10", "lts": false, "files": ["linux-x64"]},
+        {"version": "v20.12.2", "date": "2024-04-10", "lts": "Iron", "files": ["linux-x64"]},
+        {"version": "v20.10.0", "date": "2023-11-22", "lts": "Iron", "files": ["linux-x64"]},
+        {"version": "v18.20.2", "date": "2024-04-10", "lts": "Hydrogen", "files": ["linux-x64"]},
+        {"version": "v18.17.1", "date": "2023-08-09", "lts": "Hydrogen", "files": ["linux-x64"]}
+    ]"#;
+
+    fn version<'a>(index: &'a RemoteIndex, spec: &str) -> Option<&'a str> {
+        let spec = VersionSpec::parse(spec).unwrap();
+        index.resolve(&spec).map(|release| release.version.as_str())
+    }
+
+    #[test]
+    fn test_resolve() {
+        let index = RemoteIndex::parse(INDEX).unwrap();
+        assert_eq!(version(&index, "node"), Some("v21.7.3"));
+        assert_eq!(version(&index, "lts/*"), Some("v20.12.2"));
+        assert_eq!(version(&index, "lts/hydrogen"), Some("v18.20.2"));
+        assert_eq!(version(&index, "lts/-1"), Some("v18.20.2"));
+        assert_eq!(version(&index, "20"), Some("v20.12.2"));
+        assert_eq!(version(&index, "v18.17"), Some("v18.17.1"));
+        assert_eq!(version(&index, "20.10.0"), Some("v20.10.0"));
+        assert_eq!(version(&index, "^18.17.0"), Some("v18.20.2"));
+        assert_eq!(version(&index, ">=19 <21"), Some("v20.12.2"));
+        assert_eq!(version(&index, "16"), None);
+        assert_eq!(
+            index.latest_in_major(18).map(|r| r.version.as_str()),
+            Some("v18.20.2")
+        );
+    }
+
+    #[test]
+    fn test_fetch_from_file_mirror() {
+        let mirror = tempdir().unwrap();
+        fs::write(mirror.path().join("index.json"), INDEX).unwrap();
+
+        let config = Config {
+            node_mirror: Some(format!("file://{}", mirror.path().display())),
+            ..Config::default()
+        };
+        let client = IndexClient::new(&config).with_cache_dir(None);
+        let index = client.fetch().unwrap();
+        assert_eq!(index.releases.len(), 5);
+        assert_eq!(index.releases[1].lts.as_deref(), Some("Iron"));
+    }
+
+    #[test]
+    fn test_cache_is_keyed_by_url() {
+        let cache = tempdir().unwrap();
+        let config = Config {
+            node_mirror: Some("https://mirror.example/dist/".to_string()),
+            ..Config::default()
+        };
+        let client = IndexClient::new(&config).with_cache_dir(Some(cache.path().to_path_buf()));
+        assert!(client.cached().is_none());
+
+        client
+            .write_cache(
+                &CacheMeta {
+                    url: "https://mirror.example/dist/index.json".to_string(),
+                    etag: Some("\"abc\"".to_string()),
+                    fetched_at: now(),
+                },
+                INDEX,
+            )
+            .unwrap();
+        assert!(client.cached().is_some());
+        // Served from the fresh cache without any network access
+        assert_eq!(client.fetch().unwrap().releases.len(), 5);
+
+        let other =
+            IndexClient::new(&Config::default()).with_cache_dir(Some(cache.path().to_path_buf()));
+        assert_ne!(other.index_url(), client.index_url());
+        assert!(other.cached().is_none());
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("v20.12.2", "v20.10.0"));
+        assert!(!is_newer("v20.10.0", "20.10.0"));
+        assert!(!is_newer("lts", "v20.10.0"));
+    }
+}
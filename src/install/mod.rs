@@ -0,0 +1,499 @@
+use anyhow::{anyhow, Context, Result};
+use auto_nvm::config::Config;
+use auto_nvm::index::{self, IndexClient};
+use auto_nvm::{nvm, VersionSpec};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Timeouts for archive downloads; there is no overall limit since archives
+/// are large and connections may be slow
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Node.js platform name and architecture of the running system, as used in
+/// release file names (e.g. `linux`, `x64`)
+pub fn platform() -> Result<(&'static str, &'static str)> {
+    let os = match std::env::consts::OS {
+        "linux" => "linux",
+        "macos" => "darwin",
+        "aix" => "aix",
+        other => return Err(anyhow!("Installing Node.js on {} is not supported", other)),
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        "arm" => "armv7l",
+        "powerpc64" => "ppc64",
+        "s390x" => "s390x",
+        other => return Err(anyhow!("Installing Node.js on {} is not supported", other)),
+    };
+    Ok((os, arch))
+}
+
+/// Release archive name, e.g. `node-v20.12.2-linux-x64.tar.xz`
+pub fn archive_name(version: &str, os: &str, arch: &str) -> String {
+    format!("node-{}-{}-{}.tar.xz", version, os, arch)
+}
+
+/// Find the SHA-256 checksum of a file in SHASUMS256.txt content
+pub fn parse_shasums(content: &str, file_name: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        (parts.next()? == file_name).then(|| hash.to_ascii_lowercase())
+    })
+}
+
+/// Result of an install
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallOutcome {
+    pub version: String,
+    pub dir: PathBuf,
+    pub already_installed: bool,
+}
+
+/// Downloads, verifies and unpacks Node.js releases into the nvm directory
+pub struct Installer {
+    config: Config,
+    client: IndexClient,
+    nvm_dir: PathBuf,
+    download_dir: PathBuf,
+    /// Also verify the GPG signature of SHASUMS256.txt
+    pub verify_signature: bool,
+}
+
+impl Installer {
+    pub fn new(config: Config) -> Result<Self> {
+        // Installing works without nvm itself, so ~/.nvm may not exist yet
+        let nvm_dir = nvm::nvm_dir()
+            .or_else(|| dirs::home_dir().map(|home| home.join(".nvm")))
+            .ok_or_else(|| anyhow!("Could not find the nvm directory; set NVM_DIR"))?;
+        let download_dir = index::default_cache_dir()
+            .ok_or_else(|| anyhow!("Could not determine cache directory"))?
+            .join("downloads");
+
+        Ok(Self {
+            client: IndexClient::new(&config),
+            config,
+            nvm_dir,
+            download_dir,
+            verify_signature: false,
+        })
+    }
+
+    /// Install the newest release matching `spec`
+    pub fn install(&self, spec: &VersionSpec) -> Result<InstallOutcome> {
+        let remote = self.client.fetch()?;
+        let release = remote.resolve(spec).ok_or_else(|| {
+            anyhow!(
+                "No Node.js release matches {} on {}",
+                spec,
+                self.client.mirror()
+            )
+        })?;
+        let version = release.version.clone();
+
+        let target = nvm::version_dir(&self.nvm_dir, &version);
+        if target.join("bin").join("node").exists() {
+            return Ok(InstallOutcome {
+                version,
+                dir: target,
+                already_installed: true,
+            });
+        }
+
+        let (os, arch) = platform()?;
+        let file_name = archive_name(&version, os, arch);
+        let base_url = format!("{}/{}", self.client.mirror(), version);
+
+        let shasums = self.fetch_text(&format!("{}/SHASUMS256.txt", base_url))?;
+        if self.verify_signature {
+            self.verify_shasums_signature(&base_url, &shasums)?;
+        }
+        let expected = parse_shasums(&shasums, &file_name)
+            .ok_or_else(|| anyhow!("{} is not listed in SHASUMS256.txt", file_name))?;
+
+        fs::create_dir_all(&self.download_dir).with_context(|| {
+            format!("Failed to create directory {}", self.download_dir.display())
+        })?;
+        let archive = self.download_dir.join(&file_name);
+        if !archive.exists() {
+            self.download(&format!("{}/{}", base_url, file_name), &archive)?;
+        }
+
+        let actual = sha256_file(&archive)?;
+        if actual != expected {
+            // Remove the archive so the next attempt downloads it again
+            let _ = fs::remove_file(&archive);
+            return Err(anyhow!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                file_name,
+                expected,
+                actual
+            ));
+        }
+
+        self.status(&format!("Extracting {}...", file_name));
+        extract(&archive, &target)?;
+        let _ = fs::remove_file(&archive);
+
+        Ok(InstallOutcome {
+            version,
+            dir: target,
+            already_installed: false,
+        })
+    }
+
+    fn status(&self, message: &str) {
+        if !self.config.is_quiet() {
+            eprintln!("{}", message);
+        }
+    }
+
+    fn agent() -> ureq::Agent {
+        ureq::AgentBuilder::new()
+            .timeout_connect(CONNECT_TIMEOUT)
+            .timeout_read(READ_TIMEOUT)
+            .user_agent(index::USER_AGENT)
+            .build()
+    }
+
+    fn fetch_text(&self, url: &str) -> Result<String> {
+        if let Some(path) = url.strip_prefix("file://") {
+            return fs::read_to_string(path).with_context(|| format!("Failed to read {}", path));
+        }
+
+        Self::agent()
+            .get(url)
+            .call()
+            .with_context(|| format!("Failed to fetch {}", url))?
+            .into_string()
+            .with_context(|| format!("Failed to read {}", url))
+    }
+
+    /// Download `url` to `dest`, resuming a previous partial download
+    ///
+    /// Data is written to `<dest>.part` and only renamed once complete.
+    fn download(&self, url: &str, dest: &Path) -> Result<()> {
+        let part = PathBuf::from(format!("{}.part", dest.display()));
+        let label = dest
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        if let Some(path) = url.strip_prefix("file://") {
+            fs::copy(path, &part).with_context(|| format!("Failed to copy {}", path))?;
+            return fs::rename(&part, dest).context("Failed to finish download");
+        }
+
+        let offset = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+        let mut request = Self::agent().get(url);
+        if offset > 0 {
+            request = request.set("Range", &format!("bytes={}-", offset));
+        }
+
+        let response = match request.call() {
+            Ok(response) => response,
+            // The partial file is already complete
+            Err(ureq::Error::Status(416, _)) if offset > 0 => {
+                return fs::rename(&part, dest).context("Failed to finish download");
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to download {}", url)),
+        };
+
+        // Servers without range support answer 200 with the whole file
+        let resumed = response.status() == 206;
+        let start = if resumed { offset } else { 0 };
+        let total = response
+            .header("Content-Length")
+            .and_then(|length| length.parse::<u64>().ok())
+            .map(|length| length + start);
+
+        let mut file = if resumed {
+            OpenOptions::new().append(true).open(&part)
+        } else {
+            File::create(&part)
+        }
+        .with_context(|| format!("Failed to write {}", part.display()))?;
+
+        if resumed {
+            self.status(&format!("Resuming {} at {}", label, format_bytes(start)));
+        }
+        let mut progress = Progress::new(&label, start, total, !self.config.is_quiet());
+        let mut reader = response.into_reader();
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = reader
+                .read(&mut buffer)
+                .with_context(|| format!("Download of {} interrupted; re-run to resume", label))?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read])
+                .with_context(|| format!("Failed to write {}", part.display()))?;
+            progress.advance(read as u64);
+        }
+        progress.finish();
+
+        fs::rename(&part, dest).context("Failed to finish download")
+    }
+
+    fn verify_shasums_signature(&self, base_url: &str, shasums: &str) -> Result<()> {
+        which::which("gpg").map_err(|_| anyhow!("gpg is required to verify signatures"))?;
+
+        // Removed on drop, also when the download or gpg fails
+        let dir = scratch_dir(&self.download_dir)?;
+        let shasums_path = dir.path().join("SHASUMS256.txt");
+        let signature_path = dir.path().join("SHASUMS256.txt.sig");
+        fs::write(&shasums_path, shasums)?;
+        self.download(&format!("{}/SHASUMS256.txt.sig", base_url), &signature_path)?;
+
+        let output = Command::new("gpg")
+            .arg("--verify")
+            .arg(&signature_path)
+            .arg(&shasums_path)
+            .output()
+            .context("Failed to run gpg")?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "GPG signature verification of SHASUMS256.txt failed (import the Node.js release keys first):\n{}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        self.status("Verified GPG signature of SHASUMS256.txt");
+
+        Ok(())
+    }
+}
+
+/// Create a fresh scratch directory inside `parent`, removed when dropped
+fn scratch_dir(parent: &Path) -> Result<TempDir> {
+    fs::create_dir_all(parent)
+        .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    tempfile::Builder::new()
+        .prefix(".verify-")
+        .tempdir_in(parent)
+        .with_context(|| format!("Failed to create a directory in {}", parent.display()))
+}
+
+/// Hex-encoded SHA-256 of a file
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Unpack a release archive into `dest`, dropping its top-level directory
+///
+/// The archive is unpacked next to `dest` first and renamed into place, so an
+/// interrupted extraction never leaves a half-installed version behind.
+pub fn extract(archive: &Path, dest: &Path) -> Result<()> {
+    let parent = dest
+        .parent()
+        .ok_or_else(|| anyhow!("Invalid install directory {}", dest.display()))?;
+    fs::create_dir_all(parent)
+        .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+
+    let staging = parent.join(format!(
+        ".{}.partial",
+        dest.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)
+            .with_context(|| format!("Failed to remove {}", staging.display()))?;
+    }
+
+    let file =
+        File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?;
+    let mut tar = tar::Archive::new(xz2::read::XzDecoder::new(file));
+    tar.set_preserve_permissions(true);
+
+    for entry in tar.entries().context("Failed to read archive")? {
+        let mut entry = entry.context("Failed to read archive")?;
+        let path = entry
+            .path()
+            .context("Invalid path in archive")?
+            .into_owned();
+
+        let mut components = path.components();
+        components.next();
+        let relative: PathBuf = components.collect();
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        if relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+        {
+            return Err(anyhow!("Unsafe path in archive: {}", path.display()));
+        }
+
+        let target = staging.join(&relative);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        entry
+            .unpack(&target)
+            .with_context(|| format!("Failed to extract {}", relative.display()))?;
+    }
+
+    fs::rename(&staging, dest)
+        .with_context(|| format!("Failed to move {} into place", dest.display()))
+}
+
+/// Download progress on stderr: a single updating line on a terminal, start
+/// and end messages otherwise
+struct Progress {
+    label: String,
+    done: u64,
+    total: Option<u64>,
+    enabled: bool,
+    interactive: bool,
+    last_percent: Option<u64>,
+}
+
+impl Progress {
+    fn new(label: &str, done: u64, total: Option<u64>, enabled: bool) -> Self {
+        let interactive = io::stderr().is_terminal();
+        if enabled && !interactive {
+            eprintln!("Downloading {}...", label);
+        }
+        Self {
+            label: label.to_string(),
+            done,
+            total,
+            enabled,
+            interactive,
+            last_percent: None,
+        }
+    }
+
+    fn advance(&mut self, bytes: u64) {
+        self.done += bytes;
+        if !self.enabled || !self.interactive {
+            return;
+        }
+
+        let percent = self.total.map(|total| self.done * 100 / total.max(1));
+        if percent.is_some() && percent == self.last_percent {
+            return;
+        }
+        self.last_percent = percent;
+
+        let line = match (percent, self.total) {
+            (Some(percent), Some(total)) => format!(
+                "Downloading {} {:>3}% ({} / {})",
+                self.label,
+                percent,
+                format_bytes(self.done),
+                format_bytes(total)
+            ),
+            _ => format!("Downloading {} {}", self.label, format_bytes(self.done)),
+        };
+        eprint!("\r{}", line);
+    }
+
+    fn finish(&self) {
+        if !self.enabled {
+            return;
+        }
+        if self.interactive {
+            eprintln!();
+        } else {
+            eprintln!("Downloaded {} ({})", self.label, format_bytes(self.done));
+        }
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// Build a release-style .tar.xz with a top-level `node-vX-os-arch/` directory
+    fn build_archive(path: &Path) {
+        let file = File::create(path).unwrap();
+        let mut builder = tar::Builder::new(xz2::write::XzEncoder::new(file, 1));
+        let content = b"#!/bin/sh\necho v20.12.2\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder
+            .append_data(
+                &mut header,
+                "node-v20.12.2-linux-x64/bin/node",
+                &content[..],
+            )
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_parse_shasums() {
+        let shasums =
+            "abc123  node-v20.12.2-darwin-arm64.tar.xz\nDEF456  node-v20.12.2-linux-x64.tar.xz\n";
+        assert_eq!(
+            parse_shasums(shasums, "node-v20.12.2-linux-x64.tar.xz"),
+            Some("def456".to_string())
+        );
+        assert_eq!(
+            parse_shasums(shasums, "node-v20.12.2-linux-x64.tar.gz"),
+            None
+        );
+        assert_eq!(
+            archive_name("v20.12.2", "linux", "x64"),
+            "node-v20.12.2-linux-x64.tar.xz"
+        );
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("data");
+        fs::write(&path, "hello\n").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_strips_top_level_directory() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().unwrap();
+        let archive = dir.path().join("node.tar.xz");
+        build_archive(&archive);
+
+        let dest = dir.path().join("versions/node/v20.12.2");
+        extract(&archive, &dest).unwrap();
+
+        let node = dest.join("bin/node");
+        assert!(node.is_file());
+        assert_eq!(
+            fs::metadata(&node).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+        assert!(!dir.path().join("versions/node/.v20.12.2.partial").exists());
+    }
+}
@@ -0,0 +1,255 @@
+use anyhow::{anyhow, Context, Result};
+use auto_nvm::nvmrc::version_file::VersionFile;
+use auto_nvm::{nvm, resolve, VersionSpec};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An installed Node.js version and everything that refers to it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstalledVersion {
+    pub version: String,
+    pub active: bool,
+    pub default: bool,
+    /// nvm aliases resolving to this version, other than `default`
+    pub aliases: Vec<String>,
+    /// Version files resolving to this version; `None` when projects were not scanned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub used_by: Option<Vec<PathBuf>>,
+}
+
+impl InstalledVersion {
+    /// Whether nothing refers to this version: no scanned project, alias,
+    /// default or active shell (always false when projects were not scanned)
+    pub fn is_unused(&self) -> bool {
+        !self.active
+            && !self.default
+            && self.aliases.is_empty()
+            && self.used_by.as_ref().is_some_and(Vec::is_empty)
+    }
+}
+
+/// A project version file whose version could not be read or parsed, so the
+/// installed version it needs is unknown
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvalidFile {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Installed versions and the project files that could not be matched to one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inventory {
+    pub versions: Vec<InstalledVersion>,
+    pub invalid: Vec<InvalidFile>,
+}
+
+/// Collect the installed versions (newest first) with their aliases and, when
+/// `projects` is given, the version files that resolve to each of them
+pub fn collect(
+    nvm_dir: &Path,
+    active: Option<&str>,
+    projects: Option<&[VersionFile]>,
+) -> Result<Inventory> {
+    let installed = nvm::list_installed_versions_in(nvm_dir)?;
+
+    let mut versions: Vec<InstalledVersion> = installed
+        .iter()
+        .map(|version| InstalledVersion {
+            version: version.clone(),
+            active: active.is_some_and(|active| same_version(active, version)),
+            default: false,
+            aliases: Vec::new(),
+            used_by: projects.map(|_| Vec::new()),
+        })
+        .collect();
+
+    for alias in nvm::list_aliases_in(nvm_dir)? {
+        let target = nvm::read_alias_in(nvm_dir, &alias)
+            .and_then(|target| VersionSpec::parse(&target).ok())
+            .and_then(|target| resolve::resolve_version(&target, &installed, Some(nvm_dir)));
+        let entry = target.and_then(|target| versions.iter_mut().find(|v| v.version == target));
+
+        if let Some(entry) = entry {
+            if alias == "default" {
+                entry.default = true;
+            } else {
+                entry.aliases.push(alias);
+            }
+        }
+    }
+
+    let mut invalid = Vec::new();
+    for file in projects.unwrap_or_default() {
+        let spec = match file.version_spec() {
+            Ok(spec) => spec,
+            Err(e) => {
+                invalid.push(InvalidFile {
+                    path: file.path.clone(),
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let target = resolve::resolve_version(&spec, &installed, Some(nvm_dir));
+        let entry = target.and_then(|target| versions.iter_mut().find(|v| v.version == target));
+        if let Some(used_by) = entry.and_then(|entry| entry.used_by.as_mut()) {
+            used_by.push(file.path.clone());
+        }
+    }
+
+    Ok(Inventory { versions, invalid })
+}
+
+/// Unused versions that `prune` may remove, newest first
+///
+/// With `keep_latest_per_major`, the newest installed version of every major
+/// is kept even when it is unused.
+pub fn prune_candidates(
+    versions: &[InstalledVersion],
+    keep_latest_per_major: bool,
+) -> Vec<&InstalledVersion> {
+    let mut seen_majors = HashSet::new();
+
+    versions
+        .iter()
+        .filter(|version| {
+            // `versions` is sorted newest first, so the first of a major is its latest
+            let major = nvm::parse_version(&version.version).map(|(major, _, _)| major);
+            let latest = major.is_some_and(|major| seen_majors.insert(major));
+            version.is_unused() && !(keep_latest_per_major && latest)
+        })
+        .collect()
+}
+
+/// Delete an installed version from the nvm directory, refusing to touch the
+/// active or default version
+pub fn remove(nvm_dir: &Path, version: &InstalledVersion) -> Result<PathBuf> {
+    if version.active {
+        return Err(anyhow!(
+            "Refusing to remove {}: it is the active version",
+            version.version
+        ));
+    }
+    if version.default {
+        return Err(anyhow!(
+            "Refusing to remove {}: it is the default version",
+            version.version
+        ));
+    }
+
+    let dir = nvm::version_dir(nvm_dir, &version.version);
+    fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+    Ok(dir)
+}
+
+fn same_version(a: &str, b: &str) -> bool {
+    a.trim_start_matches('v') == b.trim_start_matches('v')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use auto_nvm::nvmrc::VersionSource;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_collect() -> Result<()> {
+        let nvm_dir = tempdir()?;
+        for version in ["v16.20.2", "v18.17.1", "v20.10.0"] {
+            fs::create_dir_all(nvm_dir.path().join("versions/node").join(version))?;
+        }
+        fs::create_dir_all(nvm_dir.path().join("alias/lts"))?;
+        fs::write(nvm_dir.path().join("alias/default"), "18")?;
+        fs::write(nvm_dir.path().join("alias/lts/*"), "lts/iron")?;
+        fs::write(nvm_dir.path().join("alias/lts/iron"), "v20.10.0")?;
+
+        let projects = vec![
+            VersionFile {
+                path: PathBuf::from("/work/api/.nvmrc"),
+                source: VersionSource::Nvmrc,
+                spec: "20".to_string(),
+            },
+            VersionFile {
+                path: PathBuf::from("/work/web/package.json"),
+                source: VersionSource::PackageJsonEngines,
+                spec: ">=16".to_string(),
+            },
+            VersionFile {
+                path: PathBuf::from("/work/old/.node-version"),
+                source: VersionSource::NodeVersion,
+                spec: "eighteen".to_string(),
+            },
+        ];
+
+        let inventory = collect(nvm_dir.path(), Some("v18.17.1"), Some(&projects))?;
+        let versions = inventory.versions;
+        let summary: Vec<(&str, bool, bool, Vec<&str>, usize)> = versions
+            .iter()
+            .map(|v| {
+                (
+                    v.version.as_str(),
+                    v.active,
+                    v.default,
+                    v.aliases.iter().map(String::as_str).collect(),
+                    v.used_by.as_ref().map(Vec::len).unwrap_or_default(),
+                )
+            })
+            .collect();
+        // The engines range resolves to the newest installed version
+        assert_eq!(
+            summary,
+            vec![
+                ("v20.10.0", false, false, vec!["lts/*", "lts/iron"], 2),
+                ("v18.17.1", true, true, vec![], 0),
+                ("v16.20.2", false, false, vec![], 0),
+            ]
+        );
+        assert!(versions[2].is_unused());
+        assert_eq!(inventory.invalid.len(), 1);
+        assert_eq!(
+            inventory.invalid[0].path,
+            PathBuf::from("/work/old/.node-version")
+        );
+
+        let versions = collect(nvm_dir.path(), None, None)?.versions;
+        assert!(versions.iter().all(|v| v.used_by.is_none()));
+        assert!(!versions.iter().any(InstalledVersion::is_unused));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune() -> Result<()> {
+        let nvm_dir = tempdir()?;
+        for version in ["v16.20.2", "v18.17.1", "v18.16.0", "v20.10.0"] {
+            fs::create_dir_all(nvm_dir.path().join("versions/node").join(version))?;
+        }
+        fs::create_dir_all(nvm_dir.path().join("alias"))?;
+        fs::write(nvm_dir.path().join("alias/default"), "20")?;
+
+        let versions = collect(nvm_dir.path(), None, Some(&[]))?.versions;
+        let names = |candidates: Vec<&InstalledVersion>| -> Vec<String> {
+            candidates.iter().map(|v| v.version.clone()).collect()
+        };
+        assert_eq!(
+            names(prune_candidates(&versions, false)),
+            vec!["v18.17.1", "v18.16.0", "v16.20.2"]
+        );
+        assert_eq!(names(prune_candidates(&versions, true)), vec!["v18.16.0"]);
+
+        let candidate = prune_candidates(&versions, true)[0];
+        let removed = remove(nvm_dir.path(), candidate)?;
+        assert!(!removed.exists());
+        assert!(nvm_dir.path().join("versions/node/v18.17.1").is_dir());
+
+        let default = versions.iter().find(|v| v.default).unwrap();
+        assert!(remove(nvm_dir.path(), default).is_err());
+        assert!(nvm_dir.path().join("versions/node/v20.10.0").is_dir());
+
+        Ok(())
+    }
+}
@@ -1,3 +1,49 @@
+//! Resolve the Node.js version a project asks for and switch to it
+//!
+//! The library behind the `auto-nvm` command. It finds version files
+//! ([`VersionFile`]), parses and validates the specs in them
+//! ([`VersionSpec`]), resolves them against the versions installed in a
+//! version manager ([`VersionManager`]) and describes the outcome as a
+//! [`Resolution`].
+//!
+//! ```no_run
+//! use auto_nvm::{backend, resolve, VersionManager};
+//!
+//! let backend = backend::detect();
+//! let resolution = resolve::resolve_dir(
+//!     std::path::Path::new("."),
+//!     backend.as_ref(),
+//!     backend.active_version(),
+//!     None,
+//! )?;
+//! if let Some(spec) = &resolution.spec {
+//!     println!("{} -> {:?}", spec, resolution.resolved_version);
+//! }
+//! # Ok::<(), auto_nvm::Error>(())
+//! ```
+//!
+//! Errors are reported as [`Error`], whose variants callers can match on.
+
+pub mod backend;
+// Fixtures shared by `auto-nvm bench` and benches/; not a stable API
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
 pub mod config;
+pub mod diagnostic;
+pub mod error;
+pub mod index;
+pub mod log;
 pub mod nvm;
 pub mod nvmrc;
+pub mod release;
+pub mod resolve;
+pub mod spec;
+
+pub use backend::VersionManager;
+pub use diagnostic::Diagnostic;
+pub use error::{Error, Result};
+pub use nvm::ShellType;
+pub use nvmrc::version_file::VersionFile;
+pub use resolve::Resolution;
+pub use spec::VersionSpec;
@@ -0,0 +1,266 @@
+use crate::release;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Size at which the log is rotated
+const MAX_SIZE: u64 = 1024 * 1024;
+
+/// Rotated logs kept next to the current one (`auto-nvm.log.1` ...)
+const KEEP: usize = 3;
+
+/// How much a log line says, from least to most verbose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level.trim().to_ascii_lowercase().as_str() {
+            "error" => Ok(Level::Error),
+            "warn" | "warning" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            _ => Err(format!("Unknown log level: {}", level)),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+struct Logger {
+    level: Option<Level>,
+    started: Instant,
+    file: Mutex<Option<File>>,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+fn logger() -> &'static Logger {
+    LOGGER.get_or_init(|| Logger {
+        // Unset, `off` or an unknown level disables logging
+        level: std::env::var("AUTO_NVM_LOG")
+            .ok()
+            .and_then(|level| level.parse().ok()),
+        started: Instant::now(),
+        file: Mutex::new(None),
+    })
+}
+
+/// Read `AUTO_NVM_LOG` and start the invocation clock; call first thing in
+/// `main` so [`elapsed_ms`] covers the whole invocation
+pub fn init() {
+    logger();
+}
+
+/// Whether messages of this level are written
+pub fn enabled(level: Level) -> bool {
+    logger().level.is_some_and(|max| level <= max)
+}
+
+/// Milliseconds since [`init`]
+pub fn elapsed_ms() -> f64 {
+    logger().started.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Append a line to the log if `level` is enabled
+///
+/// Failing to write is ignored: logging must never break a shell hook.
+pub fn log(level: Level, message: impl AsRef<str>) {
+    if !enabled(level) {
+        return;
+    }
+    let logger = logger();
+    let Ok(mut file) = logger.file.lock() else {
+        return;
+    };
+    if file.is_none() {
+        *file = log_path().and_then(|path| open(&path, MAX_SIZE).ok());
+    }
+    if let Some(file) = file.as_mut() {
+        let _ = file.write_all(format_line(level, message.as_ref()).as_bytes());
+    }
+}
+
+pub fn error(message: impl AsRef<str>) {
+    log(Level::Error, message);
+}
+
+pub fn warn(message: impl AsRef<str>) {
+    log(Level::Warn, message);
+}
+
+pub fn info(message: impl AsRef<str>) {
+    log(Level::Info, message);
+}
+
+pub fn debug(message: impl AsRef<str>) {
+    log(Level::Debug, message);
+}
+
+pub fn trace(message: impl AsRef<str>) {
+    log(Level::Trace, message);
+}
+
+/// Log file (`AUTO_NVM_LOG_FILE`, else `auto-nvm.log` in the XDG state dir)
+pub fn log_path() -> Option<PathBuf> {
+    if let Ok(file) = std::env::var("AUTO_NVM_LOG_FILE") {
+        if !file.is_empty() {
+            return Some(PathBuf::from(file));
+        }
+    }
+
+    // macOS and Windows have no state dir; fall back to local data
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("auto-nvm").join("auto-nvm.log"))
+}
+
+/// Open the log for appending, rotating it first once it reaches `max_size`
+fn open(path: &Path, max_size: u64) -> io::Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() >= max_size) {
+        rotate(path)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Shift `log.1` to `log.2` and so on, dropping the oldest, and move the
+/// current log to `log.1`
+fn rotate(path: &Path) -> io::Result<()> {
+    let rotated = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+    for n in (1..KEEP).rev() {
+        if rotated(n).exists() {
+            fs::rename(rotated(n), rotated(n + 1))?;
+        }
+    }
+    fs::rename(path, rotated(1))
+}
+
+/// `2026-10-18T09:12:03.412Z DEBUG 4242 message`; the pid groups the lines
+/// of one invocation
+fn format_line(level: Level, message: &str) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs();
+    format!(
+        "{}T{:02}:{:02}:{:02}.{:03}Z {:<5} {} {}\n",
+        release::format_date((secs / 86_400) as i64),
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        now.subsec_millis(),
+        level,
+        std::process::id(),
+        message.replace('\n', "\\n")
+    )
+}
+
+/// The last `count` lines of a log file and the offset its end is at
+pub fn tail(path: &Path, count: usize) -> io::Result<(Vec<String>, u64)> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    // Read backwards in blocks until enough lines are in hand
+    let mut start = len;
+    let mut buffer = Vec::new();
+    while start > 0 && buffer.iter().filter(|&&byte| byte == b'\n').count() <= count {
+        let block = start.min(8192);
+        start -= block;
+        let mut chunk = vec![0; block as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend(buffer);
+        buffer = chunk;
+    }
+
+    let text = String::from_utf8_lossy(&buffer);
+    let lines: Vec<&str> = text.lines().collect();
+    let lines = lines[lines.len().saturating_sub(count)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+    Ok((lines, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_level() {
+        assert_eq!("debug".parse(), Ok(Level::Debug));
+        assert_eq!(" WARN ".parse(), Ok(Level::Warn));
+        assert!("off".parse::<Level>().is_err());
+        assert!(Level::Error < Level::Debug);
+
+        let line = format_line(Level::Debug, "probe /tmp/.nvmrc\nmissing");
+        assert!(line.contains("Z DEBUG "));
+        assert!(line.ends_with(" probe /tmp/.nvmrc\\nmissing\n"));
+    }
+
+    #[test]
+    fn test_rotate_and_tail() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let path = dir.path().join("state").join("auto-nvm.log");
+
+        for round in 0..5 {
+            let mut file = open(&path, 8)?;
+            writeln!(file, "round {}", round)?;
+        }
+        assert_eq!(fs::read_to_string(&path)?, "round 4\n");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("state/auto-nvm.log.1"))?,
+            "round 3\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("state/auto-nvm.log.3"))?,
+            "round 1\n"
+        );
+        assert!(!dir.path().join("state/auto-nvm.log.4").exists());
+
+        let content: String = (1..=5000).map(|n| format!("line {}\n", n)).collect();
+        fs::write(&path, &content)?;
+        let (lines, end) = tail(&path, 3)?;
+        assert_eq!(lines, ["line 4998", "line 4999", "line 5000"]);
+        assert_eq!(end, content.len() as u64);
+        assert_eq!(tail(&path, 10_000)?.0.len(), 5000);
+        Ok(())
+    }
+}
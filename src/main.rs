@@ -1,16 +1,33 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use clap::{Parser, Subcommand};
+use std::ffi::OsString;
+use std::io::{IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
 
-mod config;
-mod nvm;
-mod nvmrc;
+use auto_nvm::{
+    backend, config, diagnostic, index, log, nvm, nvmrc, release, resolve, Diagnostic, VersionSpec,
+};
+
+mod completions;
+mod doctor;
+mod exec;
+mod globals;
+mod help;
+mod install;
+mod inventory;
+mod pin;
+mod project;
+mod scan;
 mod shell;
+mod shims;
 mod uninstall;
+mod update;
 
 #[derive(Parser)]
 #[command(name = "auto-nvm")]
 #[command(about = "A cross-platform Node.js version auto-switcher")]
 #[command(version)]
+#[command(disable_help_subcommand = true, after_help = help::topics_help())]
 struct Cli {
     /// Enable quiet mode (suppress non-error output)
     #[arg(short, long)]
@@ -20,96 +37,654 @@ struct Cli {
     command: Commands,
 }
 
+/// Exit codes used by `check --strict` and `--format`
+const CHECK_EXIT_CODES: &str = "\
+Exit codes (with --strict or --format):
+  0  Active version satisfies the version file
+  1  Unexpected error (unreadable file, failing --node binary, ...)
+  2  Invalid command-line usage
+  3  Active version does not match the version file
+  4  Required version is not installed
+  5  No version file in the current directory
+  6  Version file contains an invalid version spec";
+
 #[derive(Subcommand)]
 enum Commands {
-    /// Check current directory for .nvmrc and show version info
-    Check,
+    /// Check the current directory's version file and show version info
+    #[command(after_help = CHECK_EXIT_CODES)]
+    Check {
+        /// Machine-readable output format (exits with a status-specific code)
+        #[arg(long, value_enum)]
+        format: Option<resolve::OutputFormat>,
+
+        /// Exit non-zero unless the active version satisfies the version file (for CI)
+        #[arg(long)]
+        strict: bool,
+
+        /// Compare against `process.version` of this node binary instead of PATH
+        #[arg(long, value_name = "PATH")]
+        node: Option<PathBuf>,
+
+        /// Fetch the mirror's index.json to report newer releases (cached)
+        #[arg(long)]
+        remote: bool,
+
+        /// Report .nvmrc content that older nvm versions and other tools misread
+        #[arg(long, conflicts_with_all = ["format", "strict", "node", "remote"])]
+        lint: bool,
+    },
     /// Setup shell integration
-    Setup,
+    Setup {
+        /// Replace outdated integration blocks in every shell config file
+        /// instead of adding one for the current shell
+        #[arg(long)]
+        refresh: bool,
+
+        /// Also install completions for the current shell (with --refresh,
+        /// completions are updated wherever they are installed)
+        #[arg(long)]
+        completions: bool,
+    },
     /// Uninstall/remove shell integration
     Uninstall,
-    /// Execute version switching based on .nvmrc
+    /// Execute version switching based on the version file
     Switch {
         /// Print nvm command instead of executing (for use with eval)
         #[arg(short, long, default_value_t = true)]
         print: bool,
+
+        /// Machine-readable output format (exits with a status-specific code)
+        #[arg(long, value_enum)]
+        format: Option<resolve::OutputFormat>,
+
+        /// Shell to print commands for (defaults to the one in $SHELL)
+        #[arg(long, value_enum)]
+        shell: Option<nvm::ShellType>,
+    },
+    /// Run a command under the Node.js version required by the directory
+    #[command(visible_alias = "run")]
+    Exec {
+        /// Directory whose version file is used (defaults to the current directory)
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+
+        /// Version to use instead of the one in the version file
+        #[arg(long, value_name = "VERSION")]
+        version: Option<VersionSpec>,
+
+        /// Command and arguments to run (after --)
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<OsString>,
+    },
+    /// Write a Node.js version to the project's version file
+    #[command(visible_alias = "use")]
+    Pin {
+        /// Version to pin (defaults to the active version)
+        spec: Option<VersionSpec>,
+
+        /// Version file to write (defaults to $AUTO_NVM_VERSION_FILE, an existing file, or .nvmrc)
+        #[arg(long, value_enum, value_name = "TYPE")]
+        file: Option<nvmrc::VersionSource>,
+
+        /// Pin the full installed version (e.g. 18.17.1)
+        #[arg(long, conflicts_with = "major")]
+        exact: bool,
+
+        /// Pin only the major version (e.g. 18)
+        #[arg(long)]
+        major: bool,
+
+        /// Pin even if the version is not installed
+        #[arg(long)]
+        force: bool,
+    },
+    /// Download and install a Node.js version into the nvm directory
+    Install {
+        /// Version, alias or range to install (defaults to the version file's)
+        spec: Option<VersionSpec>,
+
+        /// Also verify the GPG signature of SHASUMS256.txt (requires gpg and
+        /// the Node.js release keys)
+        #[arg(long)]
+        gpg: bool,
+
+        /// Reinstall the global npm packages of this installed version into
+        /// the new one
+        #[arg(long, value_name = "VERSION")]
+        reinstall_packages_from: Option<VersionSpec>,
+    },
+    /// Reinstall the global npm packages of one Node.js version under another
+    MigrateGlobals {
+        /// Installed version to copy the packages from
+        #[arg(long, value_name = "VERSION")]
+        from: VersionSpec,
+
+        /// Installed version to install the packages into
+        #[arg(long, value_name = "VERSION")]
+        to: VersionSpec,
+
+        /// Install the latest release of each package instead of the same version
+        #[arg(long)]
+        latest: bool,
+
+        /// Only list the packages that would be installed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List installed Node.js versions with their aliases and projects
+    #[command(visible_alias = "ls")]
+    List {
+        /// Scan this directory for projects using each version (repeatable;
+        /// defaults to $AUTO_NVM_PROJECT_ROOTS)
+        #[arg(long, value_name = "DIR")]
+        scan: Vec<PathBuf>,
+
+        /// Output results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove installed Node.js versions that no project, alias or default uses
+    Prune {
+        /// Scan this directory for projects using each version (repeatable;
+        /// defaults to $AUTO_NVM_PROJECT_ROOTS)
+        #[arg(long, value_name = "DIR")]
+        scan: Vec<PathBuf>,
+
+        /// Keep the newest installed version of every major
+        #[arg(long)]
+        keep_latest_per_major: bool,
+
+        /// Only list the versions that would be removed
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Remove versions even when some version files could not be read
+        #[arg(long)]
+        force: bool,
+    },
+    /// Audit every version file below a directory
+    Scan {
+        /// Directory to scan (defaults to the current directory)
+        #[arg(default_value = ".")]
+        root: PathBuf,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "table")]
+        format: scan::ScanFormat,
+
+        /// Glob pattern to skip, on top of .gitignore and node_modules (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+    },
+    /// Show or update the Node.js release schedule used for EOL warnings
+    Schedule {
+        #[command(subcommand)]
+        action: Option<ScheduleAction>,
+    },
+    /// Let `switch` apply the nearest auto-nvm.toml (until its content changes)
+    Allow {
+        /// Directory to look for auto-nvm.toml from (defaults to the current directory)
+        dir: Option<PathBuf>,
+
+        /// Stop applying it instead
+        #[arg(long)]
+        revoke: bool,
+    },
+    /// Manage node/npm/npx/corepack shims for non-interactive contexts
+    Shims {
+        #[command(subcommand)]
+        action: ShimsAction,
+    },
+    /// Diagnose why automatic switching might not work
+    Doctor {
+        /// Output results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Time version resolution and `switch` on a synthetic workspace
+    #[cfg(feature = "bench")]
+    Bench {
+        /// Timed runs per benchmark
+        #[arg(long, default_value_t = 100)]
+        iterations: usize,
+
+        /// Output results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Replace this binary with the latest release (from $AUTO_NVM_RELEASE_URL
+    /// or the public release feed)
+    SelfUpdate {
+        /// Only report whether a newer release is available
+        #[arg(long)]
+        check: bool,
+
+        /// Consider prereleases (e.g. 0.3.0-beta.1)
+        #[arg(long)]
+        pre: bool,
+
+        /// Do not verify the GPG signature of checksums.txt (the archive
+        /// checksum is still verified)
+        #[arg(long)]
+        skip_signature: bool,
+    },
+    /// Print a shell completion script
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Print help for a command or a help topic
+    Help {
+        /// Command (e.g. `pin` or `shims install`) or topic (version-files,
+        /// config, hooks)
+        #[arg(value_name = "COMMAND|TOPIC")]
+        name: Vec<String>,
+    },
+    /// Write man pages for every command and help topic
+    Man {
+        /// Man directory to write man1/ and man7/ into (defaults to
+        /// $XDG_DATA_HOME/man)
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+    },
+    /// Print installed versions and aliases for shell completion
+    #[command(name = "complete-versions", hide = true)]
+    CompleteVersions,
+    /// Read the debug log written when AUTO_NVM_LOG is set
+    Log {
+        #[command(subcommand)]
+        action: LogAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum LogAction {
+    /// Print the end of the log
+    Tail {
+        /// Number of lines to print
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: usize,
+
+        /// Keep printing lines as they are written
+        #[arg(short, long)]
+        follow: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleAction {
+    /// List release lines with their codename, start and end-of-life dates
+    Show,
+    /// Replace the local schedule with a schedule.json file (offline update)
+    Update {
+        /// File in the format of github.com/nodejs/Release schedule.json
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ShimsAction {
+    /// Create or refresh shims (re-run after installing global packages)
+    Install {
+        /// Shims directory (defaults to $AUTO_NVM_SHIMS_DIR or ~/.local/share/auto-nvm/shims)
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+    /// Remove the shims created by `shims install`
+    Remove {
+        /// Shims directory (defaults to $AUTO_NVM_SHIMS_DIR or ~/.local/share/auto-nvm/shims)
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
     },
 }
 
 fn main() -> Result<()> {
+    // Invoked through a node/npm/npx/... shim: run the real binary directly
+    let mut args = std::env::args_os();
+    if let Some(name) = args.next().as_ref().and_then(shims::shim_name) {
+        let args: Vec<OsString> = args.collect();
+        return shims::run_shim(&name, &args);
+    }
+
+    log::init();
     let cli = Cli::parse();
     let config = config::Config::from_cli(cli.quiet);
+    if log::enabled(log::Level::Info) {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let cwd = std::env::current_dir().unwrap_or_default();
+        log::info(format!("{} (cwd {})", args.join(" "), cwd.display()));
+    }
 
     match cli.command {
-        Commands::Check => {
-            handle_check(&config)?;
+        Commands::Check {
+            format,
+            strict,
+            node,
+            remote,
+            lint,
+        } => {
+            if lint {
+                handle_lint(&config)?;
+            } else {
+                handle_check(&config, format, strict, node, remote)?;
+            }
         }
-        Commands::Setup => {
-            handle_setup(&config)?;
+        Commands::Setup {
+            refresh,
+            completions,
+        } => {
+            // Completions first: the integration block loads them
+            if completions && !refresh {
+                handle_install_completions(&config)?;
+            }
+            if refresh {
+                handle_setup_refresh(&config)?;
+            } else {
+                handle_setup(&config)?;
+            }
+            if !refresh {
+                handle_install_man(&config);
+            }
         }
         Commands::Uninstall => {
             handle_uninstall(&config)?;
         }
-        Commands::Switch { print } => {
-            handle_switch(&config, print)?;
+        Commands::Switch {
+            print,
+            format,
+            shell,
+        } => {
+            handle_switch(&config, print, format, shell)?;
+        }
+        Commands::Exec {
+            dir,
+            version,
+            command,
+        } => {
+            handle_exec(&config, dir, version, command)?;
+        }
+        Commands::Pin {
+            spec,
+            file,
+            exact,
+            major,
+            force,
+        } => {
+            let request = pin::PinRequest {
+                spec,
+                source: file,
+                exact,
+                major,
+                force,
+            };
+            handle_pin(&config, &request)?;
+        }
+        Commands::Install {
+            spec,
+            gpg,
+            reinstall_packages_from,
+        } => {
+            handle_install(&config, spec, gpg, reinstall_packages_from)?;
+        }
+        Commands::MigrateGlobals {
+            from,
+            to,
+            latest,
+            dry_run,
+        } => {
+            let from = exec::find_installation(&from)?;
+            let to = exec::find_installation(&to)?;
+            migrate_globals(&config, &from, &to, latest, dry_run)?;
+        }
+        Commands::List { scan, json } => {
+            handle_list(&config, scan, json)?;
+        }
+        Commands::Prune {
+            scan,
+            keep_latest_per_major,
+            dry_run,
+            force,
+        } => {
+            handle_prune(&config, scan, keep_latest_per_major, dry_run, force)?;
+        }
+        Commands::Scan {
+            root,
+            format,
+            exclude,
+        } => {
+            handle_scan(&config, &root, format, exclude)?;
+        }
+        Commands::Schedule { action } => {
+            handle_schedule(&config, action.unwrap_or(ScheduleAction::Show))?;
         }
+        Commands::Allow { dir, revoke } => {
+            handle_allow(&config, dir, revoke)?;
+        }
+        Commands::Shims { action } => {
+            handle_shims(&config, action)?;
+        }
+        Commands::Doctor { json } => {
+            handle_doctor(&config, json)?;
+        }
+        #[cfg(feature = "bench")]
+        Commands::Bench { iterations, json } => {
+            if !handle_bench(iterations, json)? {
+                std::process::exit(1);
+            }
+        }
+        Commands::SelfUpdate {
+            check,
+            pre,
+            skip_signature,
+        } => {
+            handle_self_update(&config, check, pre, skip_signature)?;
+        }
+        Commands::Completions { shell } => {
+            print!("{}", completions::script(shell));
+        }
+        Commands::Help { name } => {
+            print!("{}", help::help_text(&name)?);
+        }
+        Commands::Man { out_dir } => {
+            let dir = out_dir
+                .or_else(help::man::default_dir)
+                .ok_or_else(|| anyhow!("Could not determine the man directory"))?;
+            let written = help::man::install(&dir)?;
+            if !config.is_quiet() {
+                println!("Wrote {} man pages to {}", written.len(), dir.display());
+            }
+        }
+        Commands::CompleteVersions => {
+            for candidate in completions::version_candidates() {
+                println!("{}", candidate);
+            }
+        }
+        Commands::Log { action } => match action {
+            LogAction::Tail { lines, follow } => handle_log_tail(lines, follow)?,
+        },
     }
 
     Ok(())
 }
 
-fn handle_check(config: &config::Config) -> Result<()> {
+/// Print parse errors and portability warnings for the .nvmrc in the current
+/// directory, exiting 1 if there are any
+fn handle_lint(config: &config::Config) -> Result<()> {
+    let nvmrc_path = nvmrc::find_nvmrc_current_dir()?
+        .ok_or_else(|| anyhow!("No .nvmrc file found in current directory"))?;
+    let content = std::fs::read_to_string(&nvmrc_path)
+        .with_context(|| format!("Failed to read {}", nvmrc_path.display()))?;
+    let path = nvmrc_path.display();
+
+    let mut diagnostics = Vec::new();
+    match nvmrc::read_nvmrc(&nvmrc_path) {
+        Ok(_) => {}
+        Err(auto_nvm::Error::EmptyVersionFile { .. }) => {
+            diagnostics.push(Diagnostic::error("No Node.js version given").with_file(&nvmrc_path))
+        }
+        Err(auto_nvm::Error::NvmrcSyntax(diagnostic)) => diagnostics.push(*diagnostic),
+        Err(e) => return Err(e.into()),
+    }
+    diagnostics.extend(
+        nvmrc::parser::lint(&content)
+            .into_iter()
+            .map(|warning| warning.with_file(&nvmrc_path)),
+    );
+
+    let color = diagnostic::use_color(std::io::stdout().is_terminal());
+    for diagnostic in &diagnostics {
+        println!("{}\n", diagnostic.render(color));
+    }
+    let problems = diagnostics.len();
+    if problems > 0 {
+        std::process::exit(1);
+    }
     if !config.is_quiet() {
-        println!("Checking for .nvmrc in current directory...");
+        println!("✓ {} is portable", path);
     }
+    Ok(())
+}
 
-    match nvmrc::find_nvmrc_current_dir()? {
-        Some(nvmrc_path) => {
-            let required_version = nvmrc::parse_nvmrc(&nvmrc_path)?;
-            nvmrc::validate_version(&required_version)?;
+/// Write diagnostics to stderr, in colour when it is a terminal
+fn print_diagnostics(diagnostics: &[Diagnostic]) {
+    let color = diagnostic::use_color(std::io::stderr().is_terminal());
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render(color));
+    }
+}
 
-            if !config.is_quiet() {
-                println!("Found .nvmrc with version: {}", required_version);
-                println!("Path: {}", nvmrc_path.display());
+fn handle_check(
+    config: &config::Config,
+    format: Option<resolve::OutputFormat>,
+    strict: bool,
+    node: Option<PathBuf>,
+    remote: bool,
+) -> Result<()> {
+    // Without --remote only an already cached index is used, never the network
+    let client = index::IndexClient::new(config);
+    let remote_index = if remote {
+        Some(client.fetch()?)
+    } else {
+        client.cached()
+    };
+    let mut resolution = resolve::resolve_current_dir(node.as_deref(), remote_index.as_ref())?;
+    resolution.package_manager_version = resolution
+        .package_manager
+        .as_deref()
+        .and_then(|value| nvmrc::package_manager::PackageManager::parse(value).ok())
+        .and_then(|package_manager| package_manager.active_version());
 
-                // Try to get current Node.js version
-                match nvm::get_current_version() {
-                    Ok(current_version) => {
-                        println!("Current Node.js version: {}", current_version);
+    if let Some(format) = format {
+        println!("{}", resolution.render(format, None)?);
+        exit_with_status(resolution.status);
+        return Ok(());
+    }
 
-                        // Simple version comparison (just string comparison for now)
-                        let current_clean = current_version.trim_start_matches('v');
-                        let required_clean = required_version.trim_start_matches('v');
-
-                        if current_clean == required_clean
-                            || (required_version == "lts"
-                                || required_version == "stable"
-                                || required_version == "latest")
-                        {
-                            println!("✓ Version matches requirement");
-                        } else {
-                            println!(
-                                "✗ Version mismatch! Required: {}, Current: {}",
-                                required_version, current_version
-                            );
-                        }
-                    }
-                    Err(_) => {
-                        println!("⚠ No Node.js version currently active");
-                    }
+    if !config.is_quiet() {
+        println!("Checking for .nvmrc in current directory...");
+    }
+
+    match resolution.status {
+        resolve::MatchStatus::InvalidSpec => {
+            if resolution.diagnostics.is_empty() {
+                let error = resolution.error.clone().unwrap_or_default();
+                if !strict {
+                    return Err(anyhow!(error));
+                }
+                eprintln!("Error: {}", error);
+            } else {
+                print_diagnostics(&resolution.diagnostics);
+                if !strict {
+                    std::process::exit(1);
                 }
             }
         }
-        None => {
+        resolve::MatchStatus::NoVersionFile => {
             if !config.is_quiet() {
-                println!("No .nvmrc file found in current directory");
+                println!("No version file found in current directory");
 
                 // Still show current version if available
-                if let Ok(current_version) = nvm::get_current_version() {
+                if let Some(current_version) = &resolution.active_version {
                     println!("Current Node.js version: {}", current_version);
                 }
             }
         }
+        status => {
+            if !config.is_quiet() {
+                let required_version = resolution
+                    .spec
+                    .as_ref()
+                    .map(VersionSpec::to_string)
+                    .unwrap_or_default();
+                let file_name = resolution
+                    .source
+                    .map(|source| source.file_name())
+                    .unwrap_or(".nvmrc");
+                println!("Found {} with version: {}", file_name, required_version);
+                if let Some(path) = &resolution.version_file {
+                    println!("Path: {}", path.display());
+                }
+
+                match &resolution.active_version {
+                    Some(current_version) => {
+                        println!("Current Node.js version: {}", current_version);
+                    }
+                    None => {
+                        println!("⚠ No Node.js version currently active");
+                    }
+                }
+
+                match status {
+                    resolve::MatchStatus::Match => println!("✓ Version matches requirement"),
+                    resolve::MatchStatus::NotInstalled => {
+                        println!("✗ Version {} is not installed", required_version);
+                        print_diagnostics(&resolution.diagnostics);
+                    }
+                    _ => println!(
+                        "✗ Version mismatch! Required: {} ({}), Current: {}",
+                        required_version,
+                        resolution.resolved_version.as_deref().unwrap_or_default(),
+                        resolution.active_version.as_deref().unwrap_or("none")
+                    ),
+                }
+
+                if let (false, Some(available)) =
+                    (resolution.installed, &resolution.available_version)
+                {
+                    println!(
+                        "  Latest matching release: {} (run: nvm install {})",
+                        available, required_version
+                    );
+                }
+                if let Some(newer) = &resolution.newer_version {
+                    let major = release::major_of(newer).unwrap_or_default();
+                    println!("ℹ Newer {}.x available: {}", major, newer);
+                }
+                if let Some(warning) = &resolution.eol_warning {
+                    println!("⚠ {}", warning);
+                }
+            }
+        }
+    }
+
+    if let (Some(required), false) = (&resolution.package_manager, config.is_quiet()) {
+        let name = required.split('@').next().unwrap_or_default();
+        match (&resolution.package_manager_version, resolution.package_manager_mismatch()) {
+            (Some(active), false) => println!("✓ Package manager {} {} is active", name, active),
+            (Some(active), true) => println!(
+                "✗ Package manager mismatch! Required: {}, Current: {} {} (run: corepack enable {})",
+                required, name, active, name
+            ),
+            (None, _) => println!(
+                "✗ Package manager {} is not on PATH (run: corepack enable {})",
+                required, name
+            ),
+        }
+    }
+
+    if strict {
+        exit_with_status(resolution.status);
     }
 
     Ok(())
@@ -183,28 +758,907 @@ fn handle_setup(config: &config::Config) -> Result<()> {
     Ok(())
 }
 
+/// Rewrite auto-nvm blocks that differ from what this version generates, in
+/// the config file of every shell
+fn handle_setup_refresh(config: &config::Config) -> Result<()> {
+    let mut refreshed = 0;
+    for shell in [
+        nvm::ShellType::Bash,
+        nvm::ShellType::Zsh,
+        nvm::ShellType::Fish,
+        nvm::ShellType::PowerShell,
+    ] {
+        let Ok(config_path) = shell::get_config_file_path(shell) else {
+            continue;
+        };
+        if shell::refresh_integration(&config_path, shell)
+            .with_context(|| format!("Failed to refresh {}", config_path.display()))?
+        {
+            refreshed += 1;
+            if !config.is_quiet() {
+                println!("Updated auto-nvm integration in {}", config_path.display());
+            }
+        }
+    }
+
+    if refreshed == 0 && !config.is_quiet() {
+        println!("Shell integration is up to date");
+    }
+
+    for path in completions::refresh_installed()? {
+        if !config.is_quiet() {
+            println!("Updated completions in {}", path.display());
+        }
+    }
+
+    if let Some(dir) = help::man::default_dir().filter(|dir| help::man::is_installed(dir)) {
+        help::man::install(&dir)?;
+        if !config.is_quiet() {
+            println!("Updated man pages in {}", dir.display());
+        }
+    }
+    Ok(())
+}
+
+/// Install the man pages into the user's man directory; a failure only warns,
+/// since shell integration works without them
+fn handle_install_man(config: &config::Config) {
+    let Some(dir) = help::man::default_dir() else {
+        return;
+    };
+    match help::man::install(&dir) {
+        Ok(_) if !config.is_quiet() => {
+            println!("Man pages installed: {} (try: man auto-nvm)", dir.display())
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Warning: {:#}", e),
+    }
+}
+
+/// Install completions for the current shell; zsh and PowerShell load them
+/// from the integration block, so an existing block is updated to do so
+fn handle_install_completions(config: &config::Config) -> Result<()> {
+    let shell = nvm::detect_shell();
+    let path = completions::install(shell)?;
+    let config_path = shell::get_config_file_path(shell)?;
+    shell::refresh_integration(&config_path, shell)
+        .with_context(|| format!("Failed to refresh {}", config_path.display()))?;
+    if config.is_quiet() {
+        return Ok(());
+    }
+
+    println!("Completions installed: {}", path.display());
+    if completions::loader(shell).is_some() {
+        println!("Loaded by the auto-nvm block in {}", config_path.display());
+    }
+    Ok(())
+}
+
+/// Install the latest release over the running binary, then refresh the
+/// shell integration with the new binary
+fn handle_self_update(
+    config: &config::Config,
+    check: bool,
+    pre: bool,
+    skip_signature: bool,
+) -> Result<()> {
+    let mut updater = update::Updater::new(config.is_quiet());
+    updater.verify_signature = !skip_signature;
+    let current = env!("CARGO_PKG_VERSION");
+
+    let (release, checksums) = updater.check(pre)?;
+    let release = match release {
+        Some(release) if update::compare_versions(&release.version, current).is_gt() => release,
+        _ => {
+            if !config.is_quiet() {
+                println!("auto-nvm {} is up to date", current);
+            }
+            return Ok(());
+        }
+    };
+
+    if check {
+        println!(
+            "auto-nvm {} is available (installed: {})",
+            release.version, current
+        );
+        println!("Run: auto-nvm self-update");
+        return Ok(());
+    }
+
+    let exe = update::current_exe()?;
+    updater.install(&release, &checksums, &exe)?;
+    if !config.is_quiet() {
+        println!(
+            "Updated auto-nvm {} -> {} ({})",
+            current,
+            release.version,
+            exe.display()
+        );
+    }
+
+    // The new binary generates the shell integration it expects
+    let mut refresh = std::process::Command::new(&exe);
+    refresh.args(["setup", "--refresh"]);
+    if config.is_quiet() {
+        refresh.arg("--quiet");
+    }
+    let status = refresh
+        .status()
+        .with_context(|| format!("Failed to run {}", exe.display()))?;
+    if !status.success() {
+        return Err(anyhow!(
+            "Updated, but refreshing the shell integration failed; run: auto-nvm setup --refresh"
+        ));
+    }
+    Ok(())
+}
+
 fn handle_uninstall(config: &config::Config) -> Result<()> {
     let uninstall_manager = uninstall::UninstallManager::new(config.clone());
     uninstall_manager.execute()
 }
 
-fn handle_switch(_config: &config::Config, _print: bool) -> Result<()> {
-    // Find .nvmrc in current directory
-    match nvmrc::find_nvmrc_current_dir()? {
-        Some(nvmrc_path) => {
-            let required_version = nvmrc::parse_nvmrc(&nvmrc_path)?;
-            nvmrc::validate_version(&required_version)?;
+fn handle_exec(
+    _config: &config::Config,
+    dir: Option<PathBuf>,
+    version: Option<VersionSpec>,
+    command: Vec<OsString>,
+) -> Result<()> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => std::env::current_dir()?,
+    };
+
+    let spec = match version {
+        Some(version) => version,
+        None => exec::read_version_spec(&dir)?
+            .ok_or_else(|| anyhow!("No version file found in {}", dir.display()))?,
+    };
+
+    let installation = exec::find_installation(&spec)?;
+    let envs = exec::build_env(&installation, std::env::var_os("PATH").as_deref())?;
+
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("No command given"))?;
+    exec::exec_command(program, args, &envs)
+}
+
+fn handle_pin(config: &config::Config, request: &pin::PinRequest) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    let outcome = pin::pin(config, &current_dir, request)?;
+
+    if !config.is_quiet() {
+        println!(
+            "Pinned Node.js {} in {} ({})",
+            outcome.value,
+            outcome.path.display(),
+            outcome.source.as_str()
+        );
+        if !nvmrc::VersionSource::lookup().contains(&outcome.source) {
+            println!(
+                "Note: switch and the hooks only read package.json engines when AUTO_NVM_READ_ENGINES=1"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_install(
+    config: &config::Config,
+    spec: Option<VersionSpec>,
+    gpg: bool,
+    reinstall_packages_from: Option<VersionSpec>,
+) -> Result<()> {
+    let spec = match spec {
+        Some(spec) => spec,
+        None => {
+            let current_dir = std::env::current_dir()?;
+            exec::read_version_spec(&current_dir)?
+                .ok_or_else(|| anyhow!("No version given and no version file found"))?
+        }
+    };
+
+    // Fail before downloading if the package source is not installed
+    let packages_from = reinstall_packages_from
+        .as_ref()
+        .map(exec::find_installation)
+        .transpose()?;
+
+    let mut installer = install::Installer::new(config.clone())?;
+    installer.verify_signature = gpg;
+    let outcome = installer.install(&spec)?;
+
+    if !config.is_quiet() {
+        if outcome.already_installed {
+            println!(
+                "Node.js {} is already installed in {}",
+                outcome.version,
+                outcome.dir.display()
+            );
+        } else {
+            println!(
+                "Installed Node.js {} in {}",
+                outcome.version,
+                outcome.dir.display()
+            );
+        }
+    }
+
+    if let Some(from) = packages_from {
+        let to = exec::NodeInstallation {
+            version: outcome.version,
+            dir: outcome.dir,
+        };
+        migrate_globals(config, &from, &to, false, false)?;
+    }
+
+    Ok(())
+}
+
+/// Reinstall the global packages of `from` that `to` is missing
+fn migrate_globals(
+    config: &config::Config,
+    from: &exec::NodeInstallation,
+    to: &exec::NodeInstallation,
+    latest: bool,
+    dry_run: bool,
+) -> Result<()> {
+    if from.dir == to.dir {
+        return Err(anyhow!(
+            "Source and target are the same version ({})",
+            from.version
+        ));
+    }
+
+    let (linked, packages): (Vec<_>, Vec<_>) = globals::list_global_packages(from)?
+        .into_iter()
+        .partition(|package| package.linked);
+    let existing = globals::list_global_packages(to)?;
+    let packages = globals::packages_to_migrate(&packages, &existing, latest);
+
+    for package in &linked {
+        eprintln!(
+            "Skipping {}: installed with `npm link`; link it again under {}",
+            package.name, to.version
+        );
+    }
+
+    if packages.is_empty() {
+        if !config.is_quiet() {
+            println!(
+                "No global packages to migrate from {} to {}",
+                from.version, to.version
+            );
+        }
+        return Ok(());
+    }
+
+    let names: Vec<String> = packages
+        .iter()
+        .map(|package| package.install_arg(latest))
+        .collect();
+    if dry_run {
+        println!("Would install into {}: {}", to.version, names.join(" "));
+        return Ok(());
+    }
+
+    if !config.is_quiet() {
+        println!(
+            "Installing {} global package(s) from {} into {}: {}",
+            packages.len(),
+            from.version,
+            to.version,
+            names.join(" ")
+        );
+    }
+    globals::install_packages(to, &packages, latest)
+}
+
+fn handle_list(config: &config::Config, scan: Vec<PathBuf>, json: bool) -> Result<()> {
+    let nvm_dir =
+        nvm::nvm_dir().ok_or_else(|| anyhow!("Could not find the nvm directory; set NVM_DIR"))?;
+    let active = nvm::get_current_version().ok();
+
+    let roots = if scan.is_empty() {
+        config.project_roots.clone()
+    } else {
+        scan
+    };
+    let (projects, mut invalid) = if roots.is_empty() {
+        (None, Vec::new())
+    } else {
+        let (files, invalid) = scan::find_version_files(&roots)?;
+        (Some(files), invalid)
+    };
+
+    let inventory = inventory::collect(&nvm_dir, active.as_deref(), projects.as_deref())?;
+    let versions = inventory.versions;
+    invalid.extend(inventory.invalid);
+    if !config.is_quiet() {
+        for file in &invalid {
+            eprintln!("Warning: {}: {}", file.path.display(), file.error);
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&versions)?);
+        return Ok(());
+    }
+
+    if versions.is_empty() {
+        println!("No Node.js versions installed in {}", nvm_dir.display());
+        return Ok(());
+    }
+
+    if !config.is_quiet() {
+        println!("Installed Node.js versions in {}:", nvm_dir.display());
+    }
+    for version in &versions {
+        let mut labels = Vec::new();
+        if version.default {
+            labels.push("default".to_string());
+        }
+        labels.extend(version.aliases.iter().cloned());
+        if version.is_unused() {
+            labels.push("unused".to_string());
+        }
+
+        let marker = if version.active { "*" } else { " " };
+        if labels.is_empty() {
+            println!("{} {}", marker, version.version);
+        } else {
+            println!("{} {}  ({})", marker, version.version, labels.join(", "));
+        }
+        for path in version.used_by.iter().flatten() {
+            println!("      used by {}", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_prune(
+    config: &config::Config,
+    scan: Vec<PathBuf>,
+    keep_latest_per_major: bool,
+    dry_run: bool,
+    force: bool,
+) -> Result<()> {
+    let nvm_dir =
+        nvm::nvm_dir().ok_or_else(|| anyhow!("Could not find the nvm directory; set NVM_DIR"))?;
+    let active = nvm::get_current_version().ok();
+
+    let roots = if scan.is_empty() {
+        config.project_roots.clone()
+    } else {
+        scan
+    };
+    // Without projects every version would look unused
+    if roots.is_empty() {
+        return Err(anyhow!(
+            "No project directories to check; pass --scan <DIR> or set AUTO_NVM_PROJECT_ROOTS"
+        ));
+    }
+    let (projects, mut invalid) = scan::find_version_files(&roots)?;
+
+    let inventory = inventory::collect(&nvm_dir, active.as_deref(), Some(&projects))?;
+    let versions = inventory.versions;
+    invalid.extend(inventory.invalid);
+
+    // A project whose version is unknown may need any of the candidates
+    if !invalid.is_empty() {
+        let files: Vec<String> = invalid
+            .iter()
+            .map(|file| format!("  {}: {}", file.path.display(), file.error))
+            .collect();
+        if !force && !dry_run {
+            return Err(anyhow!(
+                "Refusing to prune: the versions these files need are unknown\n{}\nFix them, or pass --force to prune anyway",
+                files.join("\n")
+            ));
+        }
+        eprintln!(
+            "Warning: the versions these files need are unknown\n{}",
+            files.join("\n")
+        );
+    }
+
+    let candidates = inventory::prune_candidates(&versions, keep_latest_per_major);
+
+    if candidates.is_empty() {
+        if !config.is_quiet() {
+            println!("No unused Node.js versions to remove");
+        }
+        return Ok(());
+    }
+
+    for version in &candidates {
+        if dry_run {
+            println!(
+                "Would remove {} ({})",
+                version.version,
+                nvm::version_dir(&nvm_dir, &version.version).display()
+            );
+        } else {
+            inventory::remove(&nvm_dir, version)?;
+            if !config.is_quiet() {
+                println!("Removed {}", version.version);
+            }
+        }
+    }
+
+    if !dry_run && !config.is_quiet() {
+        println!("Removed {} unused version(s)", candidates.len());
+    }
+
+    Ok(())
+}
+
+fn handle_scan(
+    _config: &config::Config,
+    root: &std::path::Path,
+    format: scan::ScanFormat,
+    exclude: Vec<String>,
+) -> Result<()> {
+    if !root.is_dir() {
+        return Err(anyhow!("{} is not a directory", root.display()));
+    }
+
+    let scanner = scan::Scanner::new(exclude)?;
+    let entries = scanner.scan(root)?;
+    println!("{}", scan::render(&entries, format)?);
+
+    Ok(())
+}
+
+fn handle_schedule(config: &config::Config, action: ScheduleAction) -> Result<()> {
+    match action {
+        ScheduleAction::Show => {
+            let schedule = release::Schedule::load()?;
+            let today = release::today();
+
+            println!(
+                "{:<6} {:<10} {:<11} {:<11} STATUS",
+                "MAJOR", "CODENAME", "START", "END"
+            );
+            for (major, line) in schedule.lines().rev() {
+                let status = match schedule.eol_status(major, &today) {
+                    release::EolStatus::Ended { .. } => "end-of-life".to_string(),
+                    release::EolStatus::NearEnd { days, .. } => format!("ends in {} days", days),
+                    _ if line.start.as_str() > today.as_str() => "planned".to_string(),
+                    _ => "supported".to_string(),
+                };
+                println!(
+                    "{:<6} {:<10} {:<11} {:<11} {}",
+                    major,
+                    line.codename.as_deref().unwrap_or("-"),
+                    line.start,
+                    line.end,
+                    status
+                );
+            }
+        }
+        ScheduleAction::Update { file } => {
+            let (path, count) = release::update_from(&file)?;
+            if !config.is_quiet() {
+                println!(
+                    "Updated release schedule with {} release lines ({})",
+                    count,
+                    path.display()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_allow(config: &config::Config, dir: Option<PathBuf>, revoke: bool) -> Result<()> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => std::env::current_dir()?,
+    };
+    let list =
+        project::allow_list_path().ok_or_else(|| anyhow!("Could not determine data directory"))?;
+
+    if revoke {
+        let file = dir
+            .ancestors()
+            .map(|ancestor| ancestor.join(project::FILE_NAME))
+            .find(|file| file.is_file())
+            .ok_or_else(|| {
+                anyhow!(
+                    "No {} found in {} or its parents",
+                    project::FILE_NAME,
+                    dir.display()
+                )
+            })?;
+        let revoked = project::revoke(&list, &file)?;
+        if !config.is_quiet() {
+            if revoked {
+                println!("Revoked {}", file.display());
+            } else {
+                println!("{} was not allowed", file.display());
+            }
+        }
+        return Ok(());
+    }
+
+    let env = project::find_project_env(&dir)?.ok_or_else(|| {
+        anyhow!(
+            "No {} found in {} or its parents",
+            project::FILE_NAME,
+            dir.display()
+        )
+    })?;
+    project::allow(&list, &env)?;
+    if !config.is_quiet() {
+        println!("Allowed {}", env.file().display());
+        for (key, value) in &env.vars {
+            println!("  {}={}", key, value);
+        }
+        for entry in &env.path {
+            println!("  PATH += {}", entry.display());
+        }
+    }
+    Ok(())
+}
+
+fn handle_shims(config: &config::Config, action: ShimsAction) -> Result<()> {
+    match action {
+        ShimsAction::Install { dir } => shims::ShimManager::new(config.clone(), dir)?.install(),
+        ShimsAction::Remove { dir } => shims::ShimManager::new(config.clone(), dir)?.remove(),
+    }
+}
+
+fn handle_doctor(config: &config::Config, json: bool) -> Result<()> {
+    let doctor = doctor::Doctor::new(config.clone());
+    if !doctor.execute(json)? {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn handle_switch(
+    config: &config::Config,
+    _print: bool,
+    format: Option<resolve::OutputFormat>,
+    shell: Option<nvm::ShellType>,
+) -> Result<()> {
+    // Project environment first: `nvm use` then puts its bin dir in front of PATH
+    let shell = shell.unwrap_or_else(nvm::detect_shell);
+    let env_commands = project_env_commands(config, shell);
+    let backend = backend::detect();
+
+    if let Some(format) = format {
+        let resolution = resolve::resolve_current_dir(None, None)?;
+        let mut commands = env_commands;
+        if let Some(spec) = &resolution.spec {
+            if let Some(command) = use_command(backend.as_ref(), spec) {
+                commands.push(command);
+                commands.extend(package_manager_command(config, spec));
+            }
+        }
+        let command = (!commands.is_empty()).then(|| commands.join("; "));
+        log_emitted(command.as_deref().unwrap_or_default());
+        // Shown even with --quiet: the hooks run quietly and it is the only
+        // place the warning reaches the user
+        if let Some(warning) = &resolution.eol_warning {
+            eprintln!("Warning: {}", warning);
+        }
+        println!("{}", resolution.render(format, command.as_deref())?);
+        exit_with_status(resolution.status);
+        return Ok(());
+    }
+
+    for command in &env_commands {
+        println!("{}", command);
+    }
+
+    // Find the version file in the current directory
+    match nvmrc::find_version_spec(&std::env::current_dir()?)
+        .inspect_err(|e| log::error(e.to_string()))?
+    {
+        Some((_, spec)) => {
+            // Warnings go to stderr so they don't interfere with eval, and
+            // are shown even with --quiet because the hooks run quietly
+            let resolved = backend.resolve(&spec).unwrap_or_default();
+            log::debug(format!(
+                "resolved {} to {}",
+                spec,
+                resolved.as_deref().unwrap_or("nothing installed")
+            ));
+            let version = resolved.unwrap_or_else(|| spec.to_string());
+            if let Some(warning) = release::eol_warning(&version) {
+                eprintln!("Warning: {}", warning);
+            }
 
             // Output nvm command for eval to execute in current shell
             // This is the only way to affect the parent shell's environment
-            println!("nvm use {}", required_version);
+            let mut commands = Vec::new();
+            if let Some(command) = use_command(backend.as_ref(), &spec) {
+                commands.push(command);
+                commands.extend(package_manager_command(config, &spec));
+            }
+            for command in &commands {
+                println!("{}", command);
+            }
+            log_emitted(&[env_commands, commands].concat().join("; "));
         }
         None => {
-            // Output to stderr so it doesn't interfere with eval
-            eprintln!("No .nvmrc file found in current directory");
+            log_emitted(&env_commands.join("; "));
+            // Output to stderr so it doesn't interfere with eval; the hooks
+            // also run this when leaving a project, where it is not news
+            if !config.is_quiet() {
+                eprintln!("No version file found in current directory");
+            }
             std::process::exit(1);
         }
     }
 
     Ok(())
 }
+
+/// The backend's command for `spec`; a range nothing installed satisfies
+/// gets a warning instead, since nvm cannot resolve ranges itself
+fn use_command(backend: &dyn backend::VersionManager, spec: &VersionSpec) -> Option<String> {
+    let command = backend.use_command(spec);
+    if command.is_none() {
+        eprintln!(
+            "Warning: no installed Node.js version satisfies {}; run: auto-nvm install {}",
+            spec,
+            resolve::shell_quote(&spec.to_string())
+        );
+    }
+    command
+}
+
+/// Record what `switch` hands the shell hook to eval, and how long it took
+fn log_emitted(command: &str) {
+    if command.is_empty() {
+        log::info(format!("emitted nothing ({:.1} ms)", log::elapsed_ms()));
+    } else {
+        log::info(format!(
+            "emitted `{}` ({:.1} ms)",
+            command,
+            log::elapsed_ms()
+        ));
+    }
+}
+
+/// Latency the shell hook must stay under, as promised in the README
+#[cfg(feature = "bench")]
+const SWITCH_BUDGET_MS: f64 = 500.0;
+
+/// Benchmark resolution, the index cache and `switch` on a synthetic
+/// workspace; false if `switch` misses its latency budget
+#[cfg(feature = "bench")]
+fn handle_bench(iterations: usize, json: bool) -> Result<bool> {
+    use auto_nvm::bench::{self, Fixture};
+
+    let fixture = Fixture::create()?;
+    let nvm = backend::Nvm::with_dir(&fixture.nvm_dir);
+    let mut results = Vec::new();
+
+    for (depth, dir) in &fixture.dirs {
+        results.push(bench::measure(
+            format!("resolve (depth {})", depth),
+            iterations,
+            || bench::resolve_from(dir, &nvm),
+        ));
+    }
+
+    let client = fixture.index_client();
+    let spec: VersionSpec = "22".parse()?;
+    results.push(bench::measure("index cache", iterations, || {
+        bench::resolve_cached(&client, &spec)
+    }));
+
+    for backend in fixture.backends() {
+        results.push(bench::measure(
+            format!("emit ({})", backend.kind().as_str()),
+            iterations,
+            || bench::emit(fixture.deepest(), backend.as_ref()),
+        ));
+    }
+
+    // The whole hook: a fresh process running `switch`, as a `cd` does, with
+    // nothing from the user's environment but PATH
+    let exe = std::env::current_exe().context("Failed to locate the auto-nvm binary")?;
+    let project = fixture.dirs.first().map(|(_, dir)| dir.as_path());
+    let mut failure = None;
+    let switch = bench::measure("switch (process)", iterations, || {
+        let output = std::process::Command::new(&exe)
+            .args(["--quiet", "switch"])
+            .current_dir(project.unwrap_or(&fixture.root))
+            .env_clear()
+            .envs(std::env::var_os("PATH").map(|path| ("PATH", path)))
+            .env("HOME", &fixture.home)
+            .env("NVM_DIR", &fixture.nvm_dir)
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => {
+                failure.get_or_insert_with(|| {
+                    anyhow!(
+                        "`switch` failed ({}): {}",
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )
+                });
+            }
+            Err(e) => {
+                failure.get_or_insert_with(|| anyhow!(e).context("Failed to run `switch`"));
+            }
+        }
+    });
+    if let Some(failure) = failure {
+        return Err(failure);
+    }
+    results.push(switch.clone());
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+    } else {
+        let width = results
+            .iter()
+            .map(|result| result.name.len())
+            .max()
+            .unwrap_or(0);
+        println!("{:<width$}  {:>10}  {:>10}", "Benchmark", "p50", "p95");
+        for result in &results {
+            println!(
+                "{:<width$}  {:>7.3} ms  {:>7.3} ms",
+                result.name, result.p50_ms, result.p95_ms
+            );
+        }
+        println!();
+        if switch.p95_ms <= SWITCH_BUDGET_MS {
+            println!(
+                "✓ switch p95 {:.1} ms is within the {} ms budget",
+                switch.p95_ms, SWITCH_BUDGET_MS
+            );
+        } else {
+            println!(
+                "✗ switch p95 {:.1} ms exceeds the {} ms budget",
+                switch.p95_ms, SWITCH_BUDGET_MS
+            );
+        }
+    }
+
+    Ok(switch.p95_ms <= SWITCH_BUDGET_MS)
+}
+
+/// Print the end of the log, then optionally follow it like `tail -f`
+fn handle_log_tail(lines: usize, follow: bool) -> Result<()> {
+    let path = log::log_path().ok_or_else(|| anyhow!("Could not determine the log directory"))?;
+    if !path.exists() {
+        return Err(anyhow!(
+            "No log at {} (set AUTO_NVM_LOG=debug to start logging)",
+            path.display()
+        ));
+    }
+
+    let (last, mut offset) =
+        log::tail(&path, lines).with_context(|| format!("Failed to read {}", path.display()))?;
+    for line in last {
+        println!("{}", line);
+    }
+
+    if !follow {
+        return Ok(());
+    }
+    loop {
+        std::thread::sleep(std::time::Duration::from_millis(250));
+        let len = std::fs::metadata(&path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        if len < offset {
+            // Rotated: start again from the top of the new file
+            offset = 0;
+        }
+        if len > offset {
+            let mut file = std::fs::File::open(&path)?;
+            file.seek(SeekFrom::Start(offset))?;
+            let mut new = String::new();
+            file.read_to_string(&mut new)?;
+            print!("{}", new);
+            std::io::stdout().flush()?;
+            offset = len;
+        }
+    }
+}
+
+/// Commands applying the `[env]` of the nearest auto-nvm.toml, or undoing
+/// those of the project the shell just left
+///
+/// A file is only applied once `auto-nvm allow` recorded its content; until
+/// then it is treated as absent. Problems are reported on stderr and leave
+/// the environment untouched.
+fn project_env_commands(config: &config::Config, shell: nvm::ShellType) -> Vec<String> {
+    let commands = std::env::current_dir()
+        .map_err(anyhow::Error::from)
+        .and_then(|dir| project::find_project_env(&dir))
+        .and_then(|next| {
+            let Some(env) = next else {
+                return Ok(None);
+            };
+            let allowed = match project::allow_list_path() {
+                Some(list) => project::is_allowed(&list, &env)?,
+                None => false,
+            };
+            if allowed {
+                return Ok(Some(env));
+            }
+            // Shown even with --quiet, like the EOL warning: the hooks run
+            // quietly and would otherwise ignore the file silently
+            eprintln!(
+                "Warning: {} is not allowed; review it, then run: auto-nvm allow",
+                env.file().display()
+            );
+            Ok(None)
+        })
+        .and_then(|next| {
+            project::transition(
+                shell,
+                &project::ActiveEnv::from_env(),
+                next.as_ref(),
+                std::env::var_os("PATH").as_deref(),
+            )
+        });
+
+    match commands {
+        Ok(commands) => commands,
+        Err(e) => {
+            if !config.is_quiet() {
+                eprintln!("Warning: {:#}", e);
+            }
+            Vec::new()
+        }
+    }
+}
+
+/// Command activating the project's `packageManager` after switching to `spec`
+///
+/// Problems are reported on stderr; the Node.js switch itself goes ahead.
+fn package_manager_command(config: &config::Config, spec: &VersionSpec) -> Option<String> {
+    use nvmrc::package_manager::{self, Activation};
+
+    let current_dir = std::env::current_dir().ok()?;
+    let package_manager = match package_manager::read_package_manager(&current_dir) {
+        Ok(package_manager) => package_manager?,
+        Err(e) => {
+            if !config.is_quiet() {
+                eprintln!("Warning: {:#}", e);
+            }
+            return None;
+        }
+    };
+    let installation = exec::find_installation(spec).ok()?;
+
+    match package_manager.activation(&installation.bin_dir()) {
+        Activation::Ready => None,
+        Activation::Command(command) => Some(command),
+        Activation::CorepackMissing => {
+            if !config.is_quiet() {
+                eprintln!(
+                    "Warning: Node.js {} does not bundle corepack, needed for {}; run: npm install --global corepack",
+                    installation.version, package_manager
+                );
+            }
+            None
+        }
+    }
+}
+
+/// Exit with the stable code for a non-matching resolution status
+fn exit_with_status(status: resolve::MatchStatus) {
+    let code = status.exit_code();
+    if code != 0 {
+        std::process::exit(code);
+    }
+}
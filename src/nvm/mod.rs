@@ -1,16 +1,47 @@
-use anyhow::{anyhow, Result};
+use crate::error::{io_error, Error, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use which::which;
 
 /// Supported shell types for nvm invocation
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
 pub enum ShellType {
     Bash,
     Zsh,
     Fish,
+    #[value(name = "powershell")]
     PowerShell,
 }
 
+/// Version manager backend that performs the actual switching
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    Nvm,
+    NvmWindows,
+}
+
+impl Backend {
+    /// Name of the backend as shown in command output
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backend::Nvm => "nvm",
+            Backend::NvmWindows => "nvm-windows",
+        }
+    }
+}
+
+/// Detect which version manager backend is used on this platform
+pub fn detect_backend() -> Backend {
+    if cfg!(windows) {
+        Backend::NvmWindows
+    } else {
+        Backend::Nvm
+    }
+}
+
 /// Detect the current shell type based on environment
 pub fn detect_shell() -> ShellType {
     // On Windows, default to PowerShell
@@ -86,6 +117,133 @@ pub fn detect_nvm() -> Result<bool> {
     }
 }
 
+/// Locate the nvm installation directory ($NVM_DIR, falling back to ~/.nvm)
+pub fn nvm_dir() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var("NVM_DIR") {
+        if !dir.is_empty() {
+            return Some(PathBuf::from(dir));
+        }
+    }
+
+    let default_dir = dirs::home_dir()?.join(".nvm");
+    if default_dir.is_dir() {
+        Some(default_dir)
+    } else {
+        None
+    }
+}
+
+/// Parse a version string like `v18.17.0` into its numeric components
+pub fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = parts.next()?.parse().ok()?;
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some((major, minor, patch))
+}
+
+/// List Node.js versions installed under `$NVM_DIR/versions/node`, newest first
+pub fn list_installed_versions() -> Result<Vec<String>> {
+    match nvm_dir() {
+        Some(dir) => list_installed_versions_in(&dir),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// List Node.js versions installed in the given nvm directory, newest first
+pub fn list_installed_versions_in(nvm_dir: &Path) -> Result<Vec<String>> {
+    list_version_dirs(&nvm_dir.join("versions").join("node"))
+}
+
+/// List the `vX.Y.Z` directories in a directory, newest first
+pub(crate) fn list_version_dirs(versions_dir: &Path) -> Result<Vec<String>> {
+    if !versions_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(versions_dir).map_err(io_error("read", versions_dir))?;
+
+    let mut versions: Vec<((u64, u64, u64), String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            parse_version(&name).map(|parsed| (parsed, name))
+        })
+        .collect();
+
+    versions.sort_by_key(|(parsed, _)| std::cmp::Reverse(*parsed));
+
+    Ok(versions.into_iter().map(|(_, name)| name).collect())
+}
+
+/// Installation directory of a Node.js version inside the nvm directory
+pub fn version_dir(nvm_dir: &Path, version: &str) -> PathBuf {
+    let version = format!("v{}", version.trim().trim_start_matches('v'));
+    nvm_dir.join("versions").join("node").join(version)
+}
+
+/// Read an nvm alias such as `default` or `lts/*`, following alias chains
+pub fn read_alias_in(nvm_dir: &Path, name: &str) -> Option<String> {
+    let mut current = name.to_string();
+
+    // nvm aliases may point at other aliases (e.g. lts/* -> lts/iron -> v20.x)
+    for _ in 0..8 {
+        let alias_path = nvm_dir.join("alias").join(&current);
+        let target = fs::read_to_string(alias_path).ok()?.trim().to_string();
+
+        if target.is_empty() {
+            return None;
+        }
+        if !nvm_dir.join("alias").join(&target).is_file() {
+            return Some(target);
+        }
+        current = target;
+    }
+
+    None
+}
+
+/// List the names of all nvm aliases (e.g. `default`, `lts/iron`), sorted
+pub fn list_aliases_in(nvm_dir: &Path) -> Result<Vec<String>> {
+    let alias_dir = nvm_dir.join("alias");
+    let mut aliases = Vec::new();
+
+    if !alias_dir.is_dir() {
+        return Ok(aliases);
+    }
+
+    // Aliases live in alias/ and one level of namespaces such as alias/lts/
+    for entry in fs::read_dir(&alias_dir)
+        .map_err(io_error("read", &alias_dir))?
+        .filter_map(|entry| entry.ok())
+    {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+
+        if path.is_dir() {
+            for nested in fs::read_dir(&path)
+                .map_err(io_error("read", &path))?
+                .filter_map(|entry| entry.ok())
+            {
+                if nested.path().is_file() {
+                    aliases.push(format!("{}/{}", name, nested.file_name().to_string_lossy()));
+                }
+            }
+        } else if path.is_file() {
+            aliases.push(name);
+        }
+    }
+
+    aliases.sort();
+    Ok(aliases)
+}
+
 /// Get the currently active Node.js version
 pub fn get_current_version() -> Result<String> {
     // Try to get version using node directly first
@@ -108,18 +266,42 @@ pub fn get_current_version() -> Result<String> {
                 let version = String::from_utf8_lossy(&result.stdout).trim().to_string();
                 Ok(version)
             } else {
-                Err(anyhow!("No Node.js version currently active"))
+                Err(Error::NoActiveVersion)
             }
         }
-        Err(e) => Err(anyhow!("Failed to get current Node.js version: {}", e)),
+        Err(source) => Err(Error::Spawn {
+            program: "nvm".to_string(),
+            source,
+        }),
+    }
+}
+
+/// Get `process.version` of a specific node binary
+pub fn get_node_version(node_binary: &Path) -> Result<String> {
+    let program = node_binary.display().to_string();
+    let output = Command::new(node_binary)
+        .args(["-p", "process.version"])
+        .output()
+        .map_err(|source| Error::Spawn {
+            program: program.clone(),
+            source,
+        })?;
+
+    if !output.status.success() {
+        return Err(Error::CommandFailed {
+            program,
+            status: output.status,
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
     }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
 /// Switch to the specified Node.js version using nvm
-#[allow(dead_code)]
 pub fn switch_version(version: &str) -> Result<()> {
     if !detect_nvm()? {
-        return Err(anyhow!("nvm is not available on this system"));
+        return Err(Error::NvmNotFound);
     }
 
     let shell = detect_shell();
@@ -137,19 +319,21 @@ pub fn switch_version(version: &str) -> Result<()> {
                 } else {
                     stdout.to_string()
                 };
-                Err(anyhow!(
-                    "Failed to switch to version {}: {}",
-                    version,
-                    error_msg.trim()
-                ))
+                Err(Error::CommandFailed {
+                    program: format!("nvm use {}", version),
+                    status: result.status,
+                    message: error_msg.trim().to_string(),
+                })
             }
         }
-        Err(e) => Err(anyhow!("Failed to execute nvm command: {}", e)),
+        Err(source) => Err(Error::Spawn {
+            program: "nvm".to_string(),
+            source,
+        }),
     }
 }
 
 /// Check if a specific Node.js version is installed
-#[allow(dead_code)]
 pub fn is_version_installed(version: &str) -> Result<bool> {
     if !detect_nvm()? {
         return Ok(false);
@@ -192,6 +376,68 @@ mod tests {
         let _result = detect_nvm();
     }
 
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("v18.17.0"), Some((18, 17, 0)));
+        assert_eq!(parse_version("20.1.2"), Some((20, 1, 2)));
+        assert_eq!(parse_version("v18"), None);
+        assert_eq!(parse_version("v18.17.0.1"), None);
+        assert_eq!(parse_version("lts/iron"), None);
+    }
+
+    #[test]
+    fn test_list_installed_versions_in() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let versions_dir = temp_dir.path().join("versions/node");
+        for version in ["v18.17.0", "v20.10.0", "v18.9.1", "not-a-version"] {
+            fs::create_dir_all(versions_dir.join(version)).unwrap();
+        }
+
+        let versions = list_installed_versions_in(temp_dir.path()).unwrap();
+        assert_eq!(versions, vec!["v20.10.0", "v18.17.0", "v18.9.1"]);
+
+        // Missing versions directory is not an error
+        let empty_dir = tempfile::tempdir().unwrap();
+        assert!(list_installed_versions_in(empty_dir.path())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_read_alias_in() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let alias_dir = temp_dir.path().join("alias");
+        fs::create_dir_all(alias_dir.join("lts")).unwrap();
+        fs::write(alias_dir.join("default"), "v18.17.0\n").unwrap();
+        fs::write(alias_dir.join("lts/*"), "lts/iron\n").unwrap();
+        fs::write(alias_dir.join("lts/iron"), "v20.10.0\n").unwrap();
+
+        assert_eq!(
+            read_alias_in(temp_dir.path(), "default"),
+            Some("v18.17.0".to_string())
+        );
+        assert_eq!(
+            read_alias_in(temp_dir.path(), "lts/*"),
+            Some("v20.10.0".to_string())
+        );
+        assert_eq!(read_alias_in(temp_dir.path(), "missing"), None);
+    }
+
+    #[test]
+    fn test_list_aliases_in() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let alias_dir = temp_dir.path().join("alias");
+        fs::create_dir_all(alias_dir.join("lts")).unwrap();
+        fs::write(alias_dir.join("default"), "v18.17.0").unwrap();
+        fs::write(alias_dir.join("lts/*"), "lts/iron").unwrap();
+        fs::write(alias_dir.join("lts/iron"), "v20.10.0").unwrap();
+
+        assert_eq!(
+            list_aliases_in(temp_dir.path()).unwrap(),
+            vec!["default", "lts/*", "lts/iron"]
+        );
+    }
+
     #[test]
     fn test_get_current_version_format() {
         // Test that if we can get a version, it has the expected format
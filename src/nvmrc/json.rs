@@ -0,0 +1,342 @@
+use crate::error::{Error, Result};
+use std::ops::Range;
+
+/// A key/value pair inside a JSON object
+#[derive(Debug, Clone)]
+struct Member {
+    key: String,
+    key_start: usize,
+    value: Range<usize>,
+}
+
+/// A JSON object with the positions of its braces and members
+#[derive(Debug, Clone)]
+struct Object {
+    open: usize,
+    close: usize,
+    members: Vec<Member>,
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str, pos: usize) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            pos,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    /// Parse a string, returning its span including the quotes
+    fn string(&mut self) -> Option<Range<usize>> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        while let Some(byte) = self.peek() {
+            self.pos += 1;
+            match byte {
+                b'\\' => self.pos += 1,
+                b'"' => return Some(start..self.pos),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Parse any value, returning its span
+    fn value(&mut self) -> Option<Range<usize>> {
+        self.skip_whitespace();
+        let start = self.pos;
+        match self.peek()? {
+            b'"' => self.string(),
+            b'{' => self.object().map(|object| object.open..object.close + 1),
+            b'[' => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.expect(b']').is_none() {
+                    loop {
+                        self.value()?;
+                        self.skip_whitespace();
+                        match self.peek()? {
+                            b',' => self.pos += 1,
+                            b']' => {
+                                self.pos += 1;
+                                break;
+                            }
+                            _ => return None,
+                        }
+                    }
+                }
+                Some(start..self.pos)
+            }
+            _ => {
+                // Numbers, true, false and null
+                while let Some(byte) = self.peek() {
+                    if byte == b',' || byte == b'}' || byte == b']' || byte.is_ascii_whitespace() {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    None
+                } else {
+                    Some(start..self.pos)
+                }
+            }
+        }
+    }
+
+    fn object(&mut self) -> Option<Object> {
+        self.skip_whitespace();
+        let open = self.pos;
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+
+        self.skip_whitespace();
+        if self.expect(b'}').is_some() {
+            return Some(Object {
+                open,
+                close: self.pos - 1,
+                members,
+            });
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key_span = self.string()?;
+            let key: String = serde_json::from_slice(&self.bytes[key_span.clone()]).ok()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            let value = self.value()?;
+            members.push(Member {
+                key,
+                key_start: key_span.start,
+                value,
+            });
+
+            self.skip_whitespace();
+            match self.peek()? {
+                b',' => self.pos += 1,
+                b'}' => {
+                    self.pos += 1;
+                    return Some(Object {
+                        open,
+                        close: self.pos - 1,
+                        members,
+                    });
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+/// Get a string value at a key path, e.g. `["engines", "node"]`
+pub fn get_string(text: &str, path: &[&str]) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    path.iter()
+        .try_fold(&value, |value, key| value.get(key))?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Set a string value at a key path, creating missing objects and keeping the
+/// rest of the document byte-for-byte intact
+///
+/// Re-serializing package.json would reorder keys and reformat the file, so
+/// values are located by byte span and replaced or inserted in place.
+pub fn set_string(text: &str, path: &[&str], value: &str) -> Result<String> {
+    let quoted = encode(value)?;
+    let root = Parser::new(text, 0)
+        .object()
+        .ok_or_else(|| invalid("Expected a JSON object".to_string()))?;
+
+    let (range, replacement) = edit_object(text, &root, path, &quoted)?;
+
+    let mut result = String::with_capacity(text.len() + replacement.len());
+    result.push_str(&text[..range.start]);
+    result.push_str(&replacement);
+    result.push_str(&text[range.end..]);
+    Ok(result)
+}
+
+fn encode(value: &str) -> Result<String> {
+    serde_json::to_string(value).map_err(|source| Error::Json {
+        context: "Failed to encode JSON string".to_string(),
+        source,
+    })
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidJson { message }
+}
+
+/// Compute the single edit that sets `path` inside `object`
+fn edit_object(
+    text: &str,
+    object: &Object,
+    path: &[&str],
+    quoted: &str,
+) -> Result<(Range<usize>, String)> {
+    let (key, rest) = path
+        .split_first()
+        .ok_or_else(|| invalid("Empty JSON path".to_string()))?;
+
+    if let Some(member) = object.members.iter().find(|m| m.key == *key) {
+        if rest.is_empty() {
+            return Ok((member.value.clone(), quoted.to_string()));
+        }
+
+        return match Parser::new(text, member.value.start).object() {
+            Some(child) => edit_object(text, &child, rest, quoted),
+            None => Err(invalid(format!("\"{}\" is not a JSON object", key))),
+        };
+    }
+
+    // Key is missing: insert it after the last member (or right after `{`)
+    let unit = indent_unit(text);
+    let member_indent = match object.members.first() {
+        Some(member) => line_indent(text, member.key_start).to_string(),
+        None => format!("{}{}", line_indent(text, object.open), unit),
+    };
+    let value = nested_value(rest, quoted, &member_indent, &unit);
+    let entry = format!("{}: {}", encode(key)?, value);
+
+    match object.members.last() {
+        Some(last) => Ok((
+            last.value.end..last.value.end,
+            format!(",\n{}{}", member_indent, entry),
+        )),
+        None => Ok((
+            object.open + 1..object.close,
+            format!(
+                "\n{}{}\n{}",
+                member_indent,
+                entry,
+                line_indent(text, object.open)
+            ),
+        )),
+    }
+}
+
+/// Render `{ "a": { "b": value } }` for the remaining path in multi-line style
+fn nested_value(path: &[&str], quoted: &str, indent: &str, unit: &str) -> String {
+    match path.split_first() {
+        None => quoted.to_string(),
+        Some((key, rest)) => {
+            let inner_indent = format!("{}{}", indent, unit);
+            format!(
+                "{{\n{}{}: {}\n{}}}",
+                inner_indent,
+                serde_json::to_string(key).unwrap_or_default(),
+                nested_value(rest, quoted, &inner_indent, unit),
+                indent
+            )
+        }
+    }
+}
+
+/// Leading whitespace of the line containing `pos`
+fn line_indent(text: &str, pos: usize) -> &str {
+    let line_start = text[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line = &text[line_start..];
+    let end = line
+        .find(|c: char| c != ' ' && c != '\t')
+        .unwrap_or(line.len());
+    &line[..end]
+}
+
+/// Indentation unit used by the document (defaults to two spaces)
+fn indent_unit(text: &str) -> String {
+    text.lines()
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGE_JSON: &str = r#"{
+    "name": "app",
+    "engines": {
+        "node": ">=16",
+        "npm": ">=8"
+    },
+    "scripts": {"test": "jest"}
+}
+"#;
+
+    #[test]
+    fn test_get_string() {
+        assert_eq!(
+            get_string(PACKAGE_JSON, &["engines", "node"]),
+            Some(">=16".to_string())
+        );
+        assert_eq!(get_string(PACKAGE_JSON, &["volta", "node"]), None);
+        assert_eq!(get_string("not json", &["engines", "node"]), None);
+    }
+
+    #[test]
+    fn test_set_string_replaces_in_place() {
+        let result = set_string(PACKAGE_JSON, &["engines", "node"], "20").unwrap();
+        assert_eq!(result, PACKAGE_JSON.replace("\">=16\"", "\"20\""));
+    }
+
+    #[test]
+    fn test_set_string_inserts_into_existing_object() {
+        let text = "{\n  \"engines\": {\n    \"npm\": \">=8\"\n  }\n}\n";
+        let result = set_string(text, &["engines", "node"], "18.17.0").unwrap();
+        assert_eq!(
+            result,
+            "{\n  \"engines\": {\n    \"npm\": \">=8\",\n    \"node\": \"18.17.0\"\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_set_string_creates_missing_objects() {
+        let result = set_string(PACKAGE_JSON, &["volta", "node"], "20.10.0").unwrap();
+        assert_eq!(
+            result,
+            PACKAGE_JSON.replace(
+                "\"scripts\": {\"test\": \"jest\"}\n",
+                "\"scripts\": {\"test\": \"jest\"},\n    \"volta\": {\n        \"node\": \"20.10.0\"\n    }\n"
+            )
+        );
+
+        let result = set_string("{}", &["engines", "node"], "18").unwrap();
+        assert_eq!(
+            get_string(&result, &["engines", "node"]),
+            Some("18".to_string())
+        );
+    }
+
+    #[test]
+    fn test_set_string_rejects_non_objects() {
+        assert!(set_string("[]", &["engines", "node"], "18").is_err());
+        assert!(set_string("{\"engines\": \"18\"}", &["engines", "node"], "18").is_err());
+    }
+}
@@ -1,77 +1,167 @@
-use anyhow::{anyhow, Result};
+use crate::error::{io_error, Error, Result};
+use crate::log;
+use crate::spec::VersionSpec;
+use parser::Nvmrc;
+use serde::Serialize;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+pub mod json;
+pub mod package_manager;
+pub mod parser;
+pub mod range;
+pub mod version_file;
+
+/// Kind of file a version specification was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionSource {
+    /// .nvmrc
+    Nvmrc,
+    /// .node-version
+    NodeVersion,
+    /// "engines.node" in package.json
+    PackageJsonEngines,
+    /// "volta.node" in package.json
+    PackageJsonVolta,
+    /// "nodejs" entry in .tool-versions
+    ToolVersions,
+}
+
+impl VersionSource {
+    /// All sources, in lookup priority order
+    pub const ALL: [VersionSource; 5] = [
+        VersionSource::Nvmrc,
+        VersionSource::NodeVersion,
+        VersionSource::ToolVersions,
+        VersionSource::PackageJsonVolta,
+        VersionSource::PackageJsonEngines,
+    ];
+
+    /// Sources that decide a directory's version, in lookup priority order
+    ///
+    /// `engines.node` only counts when `AUTO_NVM_READ_ENGINES` is set: nearly
+    /// every package.json has one, and it states what the package supports
+    /// rather than which version to switch to.
+    pub fn lookup() -> &'static [VersionSource] {
+        let engines = std::env::var("AUTO_NVM_READ_ENGINES")
+            .is_ok_and(|value| matches!(value.trim(), "1" | "true" | "yes"));
+        if engines {
+            &Self::ALL
+        } else {
+            // `engines` is last in priority order
+            &Self::ALL[..Self::ALL.len() - 1]
+        }
+    }
+
+    /// Name of the source as shown in command output
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VersionSource::Nvmrc => "nvmrc",
+            VersionSource::NodeVersion => "node-version",
+            VersionSource::PackageJsonEngines => "package-json-engines",
+            VersionSource::PackageJsonVolta => "package-json-volta",
+            VersionSource::ToolVersions => "tool-versions",
+        }
+    }
+
+    /// Name of the file the version is stored in
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            VersionSource::Nvmrc => ".nvmrc",
+            VersionSource::NodeVersion => ".node-version",
+            VersionSource::PackageJsonEngines | VersionSource::PackageJsonVolta => "package.json",
+            VersionSource::ToolVersions => ".tool-versions",
+        }
+    }
+
+    /// Whether the tools reading this file only accept exact versions
+    pub fn requires_exact(&self) -> bool {
+        matches!(
+            self,
+            VersionSource::PackageJsonVolta | VersionSource::ToolVersions
+        )
+    }
+}
+
 /// Find .nvmrc file in the current working directory
 pub fn find_nvmrc_current_dir() -> Result<Option<PathBuf>> {
-    let current_dir = std::env::current_dir()?;
-    let nvmrc_path = current_dir.join(".nvmrc");
+    let current_dir = std::env::current_dir().map_err(Error::CurrentDir)?;
+    find_nvmrc_in_dir(&current_dir)
+}
+
+/// Find .nvmrc file in the given directory
+pub fn find_nvmrc_in_dir(dir: &Path) -> Result<Option<PathBuf>> {
+    let nvmrc_path = dir.join(".nvmrc");
 
     if nvmrc_path.exists() && nvmrc_path.is_file() {
+        log::debug(format!("probe {}: found", nvmrc_path.display()));
         Ok(Some(nvmrc_path))
     } else {
+        log::debug(format!("probe {}: missing", nvmrc_path.display()));
         Ok(None)
     }
 }
 
-/// Parse version specification from .nvmrc file
-pub fn parse_nvmrc(nvmrc_path: &Path) -> Result<String> {
-    let content =
-        fs::read_to_string(nvmrc_path).map_err(|e| anyhow!("Failed to read .nvmrc file: {}", e))?;
-
-    // Trim whitespace and remove any comments
-    let version = content
-        .lines()
-        .next() // Take first line only
-        .unwrap_or("")
-        .trim()
-        .to_string();
-
-    if version.is_empty() {
-        return Err(anyhow!(".nvmrc file is empty or contains only whitespace"));
+/// Find the nearest .nvmrc file in the given directory or any of its parents
+pub fn find_nvmrc_upwards(dir: &Path) -> Result<Option<PathBuf>> {
+    for ancestor in dir.ancestors() {
+        if let Some(nvmrc_path) = find_nvmrc_in_dir(ancestor)? {
+            return Ok(Some(nvmrc_path));
+        }
     }
 
-    Ok(version)
+    Ok(None)
+}
+
+/// Read and parse an .nvmrc file; syntax errors point into the file
+pub fn read_nvmrc(nvmrc_path: &Path) -> Result<Nvmrc> {
+    let content = fs::read_to_string(nvmrc_path).map_err(io_error("read", nvmrc_path))?;
+    parse_nvmrc_content(nvmrc_path, &content)
 }
 
-/// Validate version specification format
-pub fn validate_version(version: &str) -> Result<()> {
-    if version.is_empty() {
-        return Err(anyhow!("Version cannot be empty"));
+/// Parse the content of an .nvmrc read from `nvmrc_path`
+pub(crate) fn parse_nvmrc_content(nvmrc_path: &Path, content: &str) -> Result<Nvmrc> {
+    match Nvmrc::parse(content) {
+        Ok(Some(nvmrc)) => Ok(nvmrc),
+        Ok(None) => Err(Error::EmptyVersionFile {
+            path: nvmrc_path.to_path_buf(),
+        }),
+        Err(Error::NvmrcSyntax(diagnostic)) => Err(Error::NvmrcSyntax(Box::new(
+            diagnostic.with_file(nvmrc_path),
+        ))),
+        Err(e) => Err(e),
     }
+}
 
-    // Basic validation - allow common formats:
-    // - Exact versions: 18.17.0, v18.17.0
-    // - Major versions: 18, v18
-    // - Special keywords: lts, node, stable
-    let cleaned_version = version.trim_start_matches('v');
+/// Parse the version specification of an .nvmrc file
+pub fn parse_nvmrc(nvmrc_path: &Path) -> Result<VersionSpec> {
+    read_nvmrc(nvmrc_path).map(|nvmrc| nvmrc.spec)
+}
 
-    // Check for special keywords
-    if matches!(cleaned_version, "lts" | "node" | "stable" | "latest") {
-        return Ok(());
-    }
+/// Find the version file that decides a directory's Node.js version (see
+/// [`version_file::find_version_file`]) and parse its version
+pub fn find_version_spec(dir: &Path) -> Result<Option<(PathBuf, VersionSpec)>> {
+    let file = match version_file::find_version_file(dir)? {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+    let spec = match file.source {
+        VersionSource::Nvmrc => parse_nvmrc(&file.path)?,
+        _ => file.version_spec().map_err(|e| e.in_file(&file.path))?,
+    };
+    Ok(Some((file.path, spec)))
+}
 
-    // Check for semantic version format (major.minor.patch or just major)
-    if cleaned_version
-        .chars()
-        .all(|c| c.is_ascii_digit() || c == '.')
-    {
-        let parts: Vec<&str> = cleaned_version.split('.').collect();
-        if !parts.is_empty() && parts.len() <= 3 {
-            // Validate each part is a number
-            for part in parts {
-                if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
-                    return Err(anyhow!("Invalid version format: {}", version));
-                }
-            }
-            return Ok(());
+/// [`find_version_spec`] in the given directory or the nearest of its parents
+/// with a version file
+pub fn find_version_spec_upwards(dir: &Path) -> Result<Option<(PathBuf, VersionSpec)>> {
+    for ancestor in dir.ancestors() {
+        if let Some(found) = find_version_spec(ancestor)? {
+            return Ok(Some(found));
         }
     }
-
-    Err(anyhow!(
-        "Invalid version format: {}. Expected formats: 18.17.0, v18, lts, etc.",
-        version
-    ))
+    Ok(None)
 }
 
 #[cfg(test)]
@@ -82,27 +172,53 @@ mod tests {
     use tempfile::tempdir;
 
     #[test]
-    fn test_validate_version() {
-        // Valid versions
-        assert!(validate_version("18.17.0").is_ok());
-        assert!(validate_version("v18.17.0").is_ok());
-        assert!(validate_version("18").is_ok());
-        assert!(validate_version("v18").is_ok());
-        assert!(validate_version("lts").is_ok());
-        assert!(validate_version("node").is_ok());
-        assert!(validate_version("stable").is_ok());
-        assert!(validate_version("latest").is_ok());
-
-        // Invalid versions
-        assert!(validate_version("").is_err());
-        assert!(validate_version("invalid").is_err());
-        assert!(validate_version("18.17.0.1").is_err());
-        assert!(validate_version("18.").is_err());
-        assert!(validate_version(".17").is_err());
+    fn test_find_nvmrc_upwards() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let nested = dir.path().join("packages/app/src");
+        fs::create_dir_all(&nested)?;
+
+        assert_eq!(find_nvmrc_upwards(&nested)?, None);
+
+        fs::write(dir.path().join(".nvmrc"), "18\n")?;
+        assert_eq!(
+            find_nvmrc_upwards(&nested)?,
+            Some(dir.path().join(".nvmrc"))
+        );
+
+        fs::write(dir.path().join("packages/.nvmrc"), "20\n")?;
+        assert_eq!(
+            find_nvmrc_upwards(&nested)?,
+            Some(dir.path().join("packages/.nvmrc"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_version_spec_upwards() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        let nested = dir.path().join("app/src");
+        fs::create_dir_all(&nested)?;
+        assert_eq!(find_version_spec_upwards(&nested)?, None);
+
+        fs::write(dir.path().join("app/.node-version"), "20.10.0\n")?;
+        let (path, spec) = find_version_spec_upwards(&nested)?.unwrap();
+        assert_eq!(path, dir.path().join("app/.node-version"));
+        assert_eq!(spec.to_string(), "20.10.0");
+
+        // .nvmrc comes first, and is parsed with its own syntax
+        fs::write(dir.path().join("app/.nvmrc"), "node=18 # LTS\n")?;
+        let (path, spec) = find_version_spec_upwards(&nested)?.unwrap();
+        assert_eq!(path, dir.path().join("app/.nvmrc"));
+        assert_eq!(spec.to_string(), "18");
+
+        fs::write(dir.path().join("app/.nvmrc"), "\n")?;
+        assert!(find_version_spec_upwards(&nested).is_err());
+        Ok(())
     }
 
     #[test]
-    fn test_parse_nvmrc() -> Result<()> {
+    fn test_parse_nvmrc() -> Result<(), Box<dyn std::error::Error>> {
         let dir = tempdir()?;
         let nvmrc_path = dir.path().join(".nvmrc");
 
@@ -112,7 +228,7 @@ mod tests {
         drop(file);
 
         let version = parse_nvmrc(&nvmrc_path)?;
-        assert_eq!(version, "18.17.0");
+        assert_eq!(version.to_string(), "18.17.0");
 
         // Test version with whitespace
         let mut file = fs::File::create(&nvmrc_path)?;
@@ -120,11 +236,23 @@ mod tests {
         drop(file);
 
         let version = parse_nvmrc(&nvmrc_path)?;
-        assert_eq!(version, "v18");
+        assert_eq!(version.to_string(), "v18");
+
+        // Test comments, BOM and CRLF
+        fs::write(
+            &nvmrc_path,
+            "\u{feff}# pinned for CI\r\nv20.10.0 # iron\r\n",
+        )?;
+        assert_eq!(parse_nvmrc(&nvmrc_path)?.to_string(), "v20.10.0");
 
         // Test empty file
         fs::write(&nvmrc_path, "")?;
         assert!(parse_nvmrc(&nvmrc_path).is_err());
+        fs::write(&nvmrc_path, "# no version\n")?;
+        assert!(matches!(
+            parse_nvmrc(&nvmrc_path),
+            Err(Error::EmptyVersionFile { .. })
+        ));
 
         Ok(())
     }
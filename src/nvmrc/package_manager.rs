@@ -0,0 +1,192 @@
+use super::json;
+use crate::error::{io_error, Error, Result};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Package managers corepack can provide
+const SUPPORTED: &[&str] = &["npm", "pnpm", "yarn"];
+
+/// What it takes to activate a package manager under a Node.js installation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Activation {
+    /// Nothing to do: npm ships with Node.js, or the shim is already there
+    Ready,
+    /// Run this command after switching
+    Command(String),
+    /// The installation does not bundle corepack (e.g. Node.js 25+)
+    CorepackMissing,
+}
+
+/// The `packageManager` field of package.json, e.g. `pnpm@8.15.1`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageManager {
+    pub name: String,
+    pub version: String,
+}
+
+impl PackageManager {
+    /// Parse a `packageManager` value; a trailing `+sha512.…` hash is ignored
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let (name, version) = value.split_once('@').ok_or_else(|| {
+            invalid(
+                value,
+                format!(
+                    "Invalid packageManager \"{}\": expected name@version",
+                    value
+                ),
+            )
+        })?;
+        let version = version.split('+').next().unwrap_or_default();
+
+        if !SUPPORTED.contains(&name) {
+            return Err(invalid(
+                value,
+                format!(
+                    "Unsupported packageManager \"{}\": expected one of {}",
+                    name,
+                    SUPPORTED.join(", ")
+                ),
+            ));
+        }
+        if crate::nvm::parse_version(version).is_none() {
+            return Err(invalid(
+                value,
+                format!(
+                    "Invalid packageManager \"{}\": the version must be exact (e.g. {}@8.15.1)",
+                    value, name
+                ),
+            ));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            version: version.to_string(),
+        })
+    }
+
+    /// How to make this package manager available after switching to the
+    /// Node.js installation whose binaries are in `bin_dir`
+    ///
+    /// Corepack's shims read `packageManager` themselves, so they only need to
+    /// be enabled once per installation.
+    pub fn activation(&self, bin_dir: &Path) -> Activation {
+        if self.name == "npm" || bin_dir.join(&self.name).exists() {
+            Activation::Ready
+        } else if bin_dir.join("corepack").exists() {
+            Activation::Command(format!("corepack enable {}", self.name))
+        } else {
+            Activation::CorepackMissing
+        }
+    }
+
+    /// Version reported by the package manager on PATH, if any
+    ///
+    /// A corepack shim answers from its cache; it is not allowed to download
+    /// the package manager (or to ask whether it may).
+    pub fn active_version(&self) -> Option<String> {
+        let output = Command::new(&self.name)
+            .arg("--version")
+            .env("COREPACK_ENABLE_NETWORK", "0")
+            .env("COREPACK_ENABLE_DOWNLOAD_PROMPT", "0")
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!version.is_empty()).then_some(version)
+    }
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
+}
+
+/// Read `packageManager` from the package.json in a directory
+pub fn read_package_manager(dir: &Path) -> Result<Option<PackageManager>> {
+    let path = dir.join("package.json");
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path).map_err(io_error("read", &path))?;
+    json::get_string(&content, &["packageManager"])
+        .map(|value| PackageManager::parse(&value).map_err(|e| e.in_file(&path)))
+        .transpose()
+}
+
+fn invalid(value: &str, message: String) -> Error {
+    Error::InvalidPackageManager {
+        value: value.to_string(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let pm = PackageManager::parse("pnpm@8.15.1").unwrap();
+        assert_eq!(pm.name, "pnpm");
+        assert_eq!(pm.version, "8.15.1");
+        assert_eq!(pm.to_string(), "pnpm@8.15.1");
+
+        let pm = PackageManager::parse("yarn@4.1.0+sha512.5b7bc055cad63273").unwrap();
+        assert_eq!(pm.to_string(), "yarn@4.1.0");
+
+        assert!(PackageManager::parse("pnpm").is_err());
+        assert!(PackageManager::parse("pnpm@^8").is_err());
+        assert!(PackageManager::parse("bun@1.0.0").is_err());
+    }
+
+    #[test]
+    fn test_activation() {
+        let dir = tempfile::tempdir().unwrap();
+        let pnpm = PackageManager::parse("pnpm@8.15.1").unwrap();
+        let npm = PackageManager::parse("npm@10.2.4").unwrap();
+
+        assert_eq!(pnpm.activation(dir.path()), Activation::CorepackMissing);
+
+        fs::write(dir.path().join("corepack"), "").unwrap();
+        assert_eq!(
+            pnpm.activation(dir.path()),
+            Activation::Command("corepack enable pnpm".to_string())
+        );
+        assert_eq!(npm.activation(dir.path()), Activation::Ready);
+
+        // Already enabled
+        fs::write(dir.path().join("pnpm"), "").unwrap();
+        assert_eq!(pnpm.activation(dir.path()), Activation::Ready);
+    }
+
+    #[test]
+    fn test_read_package_manager() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(read_package_manager(dir.path()).unwrap(), None);
+
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "app", "packageManager": "pnpm@8.15.1"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            read_package_manager(dir.path()).unwrap(),
+            Some(PackageManager::parse("pnpm@8.15.1").unwrap())
+        );
+
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"packageManager": "pnpm"}"#,
+        )
+        .unwrap();
+        assert!(read_package_manager(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,421 @@
+use crate::diagnostic::{self, Diagnostic, Severity};
+use crate::error::{Error, Result};
+use crate::spec::VersionSpec;
+use std::ops::Range;
+
+/// UTF-8 byte order mark some Windows editors put at the start of files
+const BOM: char = '\u{feff}';
+
+/// Key nvm reads the Node.js version from in `key=value` files
+const NODE_KEY: &str = "node";
+
+/// A parsed .nvmrc
+///
+/// nvm accepts `#` comments (whole-line and trailing), blank lines, CRLF line
+/// endings, a leading BOM, and either a bare version or `node=<version>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Nvmrc {
+    pub spec: VersionSpec,
+    /// 1-based line and column of the version
+    pub line: usize,
+    pub column: usize,
+    /// Other `key=value` entries, kept for tools that read them
+    pub entries: Vec<Entry>,
+    /// Length of the version as written, and the line it is on
+    len: usize,
+    source_line: String,
+}
+
+/// A `key=value` line other than `node=`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    pub value: String,
+    pub line: usize,
+}
+
+/// A meaningful line: text with the comment and surrounding whitespace removed
+struct Line<'a> {
+    number: usize,
+    /// The whole line, without its line ending
+    raw: &'a str,
+    /// 1-based column of `text` in the original line
+    column: usize,
+    /// Byte offset of `text` in the content
+    start: usize,
+    text: &'a str,
+}
+
+/// Split content into its non-blank, comment-free lines
+fn lines(content: &str) -> impl Iterator<Item = Line<'_>> {
+    let mut offset = if content.starts_with(BOM) {
+        BOM.len_utf8()
+    } else {
+        0
+    };
+    content[offset..]
+        .split_inclusive('\n')
+        .enumerate()
+        .filter_map(move |(index, raw)| {
+            let line_start = offset;
+            offset += raw.len();
+
+            let raw = raw.trim_end_matches(['\n', '\r']);
+            let without_comment = raw.split_once('#').map_or(raw, |(text, _)| text);
+            let text = without_comment.trim();
+            if text.is_empty() {
+                return None;
+            }
+            let indent = without_comment.len() - without_comment.trim_start().len();
+            Some(Line {
+                number: index + 1,
+                raw,
+                column: column_of(raw, indent),
+                start: line_start + indent,
+                text,
+            })
+        })
+}
+
+/// Split a `key=value` line; `=` in ranges such as `>=18` is not a separator
+fn split_entry(text: &str) -> Option<(&str, &str)> {
+    let (key, value) = text.split_once('=')?;
+    let key = key.trim_end();
+    let is_key = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    is_key.then_some((key, value))
+}
+
+/// The version value of a line (a bare version or the value of `node=`), with
+/// its byte offset within the line's text, or `None` for other entries
+fn version_value(text: &str) -> Option<(&str, usize)> {
+    match split_entry(text) {
+        Some((key, value)) if key == NODE_KEY => {
+            let value = value.trim();
+            // `text` is trimmed, so the value runs to its end
+            Some((value, text.len() - value.len()))
+        }
+        Some(_) => None,
+        None => Some((text, 0)),
+    }
+}
+
+/// Byte range of the first version in the content, valid or not
+fn version_span(content: &str) -> Option<Range<usize>> {
+    lines(content).find_map(|line| {
+        let (value, offset) = version_value(line.text)?;
+        let start = line.start + offset;
+        Some(start..start + value.len())
+    })
+}
+
+/// The first version as written, without validating it
+pub fn version_text(content: &str) -> Option<&str> {
+    version_span(content)
+        .map(|span| &content[span])
+        .filter(|text| !text.is_empty())
+}
+
+/// Replace the version in .nvmrc content, keeping comments and other entries
+pub fn replace_version(content: &str, spec: &str) -> String {
+    match version_span(content) {
+        Some(span) => format!("{}{}{}", &content[..span.start], spec, &content[span.end..]),
+        None if content.trim().is_empty() => format!("{}\n", spec),
+        None => {
+            let newline = if content.contains("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            };
+            let separator = if content.ends_with('\n') { "" } else { newline };
+            format!("{}{}{}{}", content, separator, spec, newline)
+        }
+    }
+}
+
+/// 1-based character column of a byte offset
+fn column_of(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+/// A diagnostic pointing at `len` characters from `column` of a line
+fn point(
+    severity: Severity,
+    line: &Line,
+    column: usize,
+    len: usize,
+    message: impl Into<String>,
+) -> Diagnostic {
+    Diagnostic::new(severity, message)
+        .at(line.number, column, len)
+        .with_source_line(line.raw)
+}
+
+fn syntax_error(diagnostic: Diagnostic) -> Error {
+    Error::NvmrcSyntax(Box::new(diagnostic))
+}
+
+impl Nvmrc {
+    /// Parse .nvmrc content; `None` if it holds no version at all
+    pub fn parse(content: &str) -> Result<Option<Self>> {
+        let mut version: Option<(Line, &str, usize)> = None;
+        let mut entries: Vec<Entry> = Vec::new();
+
+        for line in lines(content) {
+            let (value, value_column) = match split_entry(line.text) {
+                Some((key, value)) => {
+                    let value_offset = line.text.len() - value.trim_start().len();
+                    let value = value.trim();
+                    if value.is_empty() {
+                        return Err(syntax_error(point(
+                            Severity::Error,
+                            &line,
+                            line.column + line.text.chars().count(),
+                            1,
+                            format!("Missing value for \"{}\"", key),
+                        )));
+                    }
+                    if key != NODE_KEY {
+                        if entries.iter().any(|entry| entry.key == key) {
+                            return Err(syntax_error(point(
+                                Severity::Error,
+                                &line,
+                                line.column,
+                                key.chars().count(),
+                                format!("Duplicate key \"{}\"", key),
+                            )));
+                        }
+                        entries.push(Entry {
+                            key: key.to_string(),
+                            value: value.to_string(),
+                            line: line.number,
+                        });
+                        continue;
+                    }
+                    (value, line.column + column_of(line.text, value_offset) - 1)
+                }
+                None => (line.text, line.column),
+            };
+
+            if let Some((first, _, _)) = &version {
+                return Err(syntax_error(point(
+                    Severity::Error,
+                    &line,
+                    line.column,
+                    line.text.chars().count(),
+                    format!(
+                        "Only one Node.js version may be given (already set on line {})",
+                        first.number
+                    ),
+                )));
+            }
+            version = Some((line, value, value_column));
+        }
+
+        let (line, value, column) = match version {
+            Some(version) => version,
+            None => return Ok(None),
+        };
+        let len = value.chars().count();
+        let spec = VersionSpec::parse(value).map_err(|e| match e {
+            Error::InvalidSpec { message, .. } => syntax_error(
+                point(Severity::Error, &line, column, len, message)
+                    .with_suggestion(diagnostic::suggest_spec(value)),
+            ),
+            e => e,
+        })?;
+
+        Ok(Some(Self {
+            spec,
+            line: line.number,
+            column,
+            entries,
+            len,
+            source_line: line.raw.to_string(),
+        }))
+    }
+
+    /// A diagnostic pointing at the version
+    pub fn diagnostic(&self, severity: Severity, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(severity, message)
+            .at(self.line, self.column, self.len)
+            .with_source_line(&self.source_line)
+    }
+}
+
+/// Flag content that only current nvm understands
+pub fn lint(content: &str) -> Vec<Diagnostic> {
+    let body = content.strip_prefix(BOM).unwrap_or(content);
+    let raw_lines: Vec<&str> = body
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect();
+    let warn = |line: usize, column: usize, len: usize, message: &str| {
+        let source = raw_lines.get(line - 1).copied().unwrap_or_default();
+        Diagnostic::warning(message)
+            .at(line, column, len)
+            .with_source_line(source)
+    };
+    let mut warnings = Vec::new();
+
+    if content.starts_with(BOM) {
+        warnings.push(warn(
+            1,
+            1,
+            1,
+            "UTF-8 byte order mark; save the file without a BOM",
+        ));
+    }
+    if let Some(index) = content.split('\n').position(|line| line.ends_with('\r')) {
+        warnings.push(warn(index + 1, 1, 1, "CRLF line endings; use LF"));
+    }
+
+    for (index, raw) in raw_lines.iter().enumerate() {
+        if let Some(offset) = raw.find('#') {
+            warnings.push(warn(
+                index + 1,
+                column_of(raw, offset),
+                raw[offset..].trim_end().chars().count(),
+                "Comments are only understood by nvm 0.40 and newer",
+            ));
+        }
+    }
+
+    let mut lines = lines(content);
+    let first = match lines.next() {
+        Some(first) => first,
+        None => return warnings,
+    };
+    if first.number != 1 || first.column != 1 {
+        warnings.push(warn(
+            first.number,
+            first.column,
+            first.text.chars().count(),
+            "The version should start the first line",
+        ));
+    }
+    for line in std::iter::once(first).chain(lines) {
+        let warn = |message: &str| {
+            point(
+                Severity::Warning,
+                &line,
+                line.column,
+                line.text.chars().count(),
+                message,
+            )
+        };
+        match split_entry(line.text) {
+            Some((NODE_KEY, _)) => warnings.push(warn(
+                "node= is only understood by nvm 0.40 and newer; write the bare version",
+            )),
+            Some(_) => warnings.push(warn("Entry is ignored by nvm")),
+            None => match VersionSpec::parse(line.text) {
+                Ok(VersionSpec::Range(_)) => warnings.push(warn(
+                    "nvm does not understand ranges; pin a version such as 18 or 18.17.1",
+                )),
+                Ok(_) if line.text.eq_ignore_ascii_case("lts") => warnings.push(
+                    warn("nvm expects lts/* rather than lts")
+                        .with_suggestion(Some("lts/*".to_string())),
+                ),
+                _ => {}
+            },
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(content: &str) -> String {
+        Nvmrc::parse(content).unwrap().unwrap().spec.to_string()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(spec("18.17.0\n"), "18.17.0");
+        assert_eq!(spec("\u{feff}v20\r\n"), "v20");
+        assert_eq!(spec("# project version\n\n  22 # jod\n"), "22");
+        assert_eq!(spec("node=lts/iron\n"), "lts/iron");
+
+        let nvmrc = Nvmrc::parse("# comment\nnode = 22\ncorepack=true\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!((nvmrc.line, nvmrc.column), (2, 8));
+        assert_eq!(
+            nvmrc.entries,
+            vec![Entry {
+                key: "corepack".to_string(),
+                value: "true".to_string(),
+                line: 3,
+            }]
+        );
+
+        assert!(Nvmrc::parse("").unwrap().is_none());
+        assert!(Nvmrc::parse("# only a comment\n\n").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |content: &str| match Nvmrc::parse(content) {
+            Err(Error::NvmrcSyntax(diagnostic)) => {
+                (diagnostic.line.unwrap(), diagnostic.span.unwrap().0)
+            }
+            other => panic!("expected a syntax error, got {:?}", other),
+        };
+        assert_eq!(error("\n  18..1\n"), (2, 3));
+        assert_eq!(error("node=abc"), (1, 6));
+        assert_eq!(error("18\n20\n"), (2, 1));
+        assert_eq!(error("18\nnode=20\n"), (2, 1));
+        assert_eq!(error("node="), (1, 6));
+        assert_eq!(error("a=1\na=2\n"), (2, 1));
+
+        let message = Nvmrc::parse("18\n20\n").unwrap_err().to_string();
+        assert_eq!(
+            message,
+            "Only one Node.js version may be given (already set on line 1) (line 2, column 1)"
+        );
+        let message = Nvmrc::parse("v18.17.\n").unwrap_err().to_string();
+        assert!(message.ends_with("(line 1, column 1); did you mean `v18.17`?"));
+    }
+
+    #[test]
+    fn test_replace_version() {
+        assert_eq!(replace_version("", "20"), "20\n");
+        assert_eq!(replace_version("18\n", "20"), "20\n");
+        assert_eq!(
+            replace_version("\u{feff}# CI\r\n 18 # hydrogen\r\n", "22"),
+            "\u{feff}# CI\r\n 22 # hydrogen\r\n"
+        );
+        assert_eq!(
+            replace_version("corepack=true\nnode = 18\n", "lts/*"),
+            "corepack=true\nnode = lts/*\n"
+        );
+        assert_eq!(replace_version("# none yet", "22"), "# none yet\n22\n");
+        assert_eq!(version_text("# x\n\n18..1 # typo\n"), Some("18..1"));
+    }
+
+    #[test]
+    fn test_lint() {
+        assert!(lint("18.17.0\n").is_empty());
+        assert!(lint("lts/*\n").is_empty());
+
+        let messages = |content: &str| -> Vec<(usize, usize)> {
+            lint(content)
+                .into_iter()
+                .map(|warning| (warning.line.unwrap(), warning.span.unwrap().0))
+                .collect()
+        };
+        assert_eq!(messages("\u{feff}18\r\n"), vec![(1, 1), (1, 1)]);
+        assert_eq!(messages("# version\n18\n"), vec![(1, 1), (2, 1)]);
+        assert_eq!(messages("18 # hydrogen\n"), vec![(1, 4)]);
+        assert_eq!(messages("node=18\n"), vec![(1, 1)]);
+        assert_eq!(messages(">=18 <21\n"), vec![(1, 1)]);
+        assert_eq!(spec(">= 18"), ">= 18");
+        assert_eq!(messages("lts\n"), vec![(1, 1)]);
+    }
+}
@@ -0,0 +1,273 @@
+/// A version as `(major, minor, patch)`
+pub type Version = (u64, u64, u64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+/// A single `<op><version>` comparison
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Comparator {
+    op: Op,
+    version: Version,
+}
+
+impl Comparator {
+    fn matches(&self, version: Version) -> bool {
+        match self.op {
+            Op::Lt => version < self.version,
+            Op::Le => version <= self.version,
+            Op::Gt => version > self.version,
+            Op::Ge => version >= self.version,
+            Op::Eq => version == self.version,
+        }
+    }
+}
+
+/// A possibly incomplete version such as `18`, `18.x` or `18.17`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Partial {
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+}
+
+impl Partial {
+    fn parse(text: &str) -> Option<Self> {
+        // Prerelease and build metadata are ignored
+        let text = text.trim().trim_start_matches(['v', '=']);
+        let text = text.split(['-', '+']).next()?;
+
+        let mut parts = text.split('.');
+        let mut next = || -> Option<Option<u64>> {
+            match parts.next() {
+                None | Some("x") | Some("X") | Some("*") => Some(None),
+                Some(part) => part.parse().ok().map(Some),
+            }
+        };
+        let major = next()?;
+        let minor = next()?;
+        let patch = next()?;
+
+        // `1.2.3.4` and `18.x.5` are not valid versions
+        if parts.next().is_some()
+            || (major.is_none() && minor.is_some())
+            || (minor.is_none() && patch.is_some())
+        {
+            return None;
+        }
+
+        Some(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    /// Lowest version matching the partial
+    fn low(&self) -> Version {
+        (
+            self.major.unwrap_or(0),
+            self.minor.unwrap_or(0),
+            self.patch.unwrap_or(0),
+        )
+    }
+
+    /// First version above everything matching the partial, `None` for `*`
+    fn next(&self) -> Option<Version> {
+        match (self.major, self.minor, self.patch) {
+            (None, _, _) => None,
+            (Some(major), None, _) => Some((major + 1, 0, 0)),
+            (Some(major), Some(minor), None) => Some((major, minor + 1, 0)),
+            (Some(major), Some(minor), Some(patch)) => Some((major, minor, patch + 1)),
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.patch.is_some()
+    }
+}
+
+/// Check whether a version satisfies an npm-style range such as `>=18 <21`,
+/// `^18.17.0`, `18.x || 20.x` or `16 - 20`
+///
+/// Returns `None` if the range cannot be parsed.
+pub fn satisfies(version: Version, range: &str) -> Option<bool> {
+    let mut satisfied = false;
+    for alternative in range.split("||") {
+        let comparators = parse_set(alternative)?;
+        satisfied |= comparators.iter().all(|c| c.matches(version));
+    }
+    Some(satisfied)
+}
+
+/// Check whether a range can be parsed
+pub fn is_valid(range: &str) -> bool {
+    satisfies((0, 0, 0), range).is_some()
+}
+
+/// Parse one space-separated comparator set into plain comparators
+fn parse_set(set: &str) -> Option<Vec<Comparator>> {
+    let tokens: Vec<&str> = set.split_whitespace().collect();
+
+    if let [low, "-", high] = tokens.as_slice() {
+        let low = Partial::parse(low)?;
+        let high = Partial::parse(high)?;
+        let mut comparators = vec![Comparator {
+            op: Op::Ge,
+            version: low.low(),
+        }];
+        if high.is_full() {
+            comparators.push(Comparator {
+                op: Op::Le,
+                version: high.low(),
+            });
+        } else if let Some(next) = high.next() {
+            comparators.push(Comparator {
+                op: Op::Lt,
+                version: next,
+            });
+        }
+        return Some(comparators);
+    }
+
+    // Join operators written apart from their version, e.g. `>= 18`
+    let mut joined: Vec<String> = Vec::new();
+    let mut pending = String::new();
+    for token in tokens {
+        if token.chars().all(|c| "<>=^~".contains(c)) {
+            pending.push_str(token);
+        } else {
+            joined.push(format!("{}{}", pending, token));
+            pending.clear();
+        }
+    }
+    if !pending.is_empty() {
+        return None;
+    }
+
+    let mut comparators = Vec::new();
+    for token in &joined {
+        comparators.extend(parse_comparator(token)?);
+    }
+    Some(comparators)
+}
+
+fn parse_comparator(token: &str) -> Option<Vec<Comparator>> {
+    let operator_len = token
+        .find(|c: char| !"<>=^~".contains(c))
+        .unwrap_or(token.len());
+    let (operator, version) = token.split_at(operator_len);
+    let partial = Partial::parse(version)?;
+    let low = partial.low();
+
+    let between = |high: Option<Version>| {
+        let mut comparators = vec![Comparator {
+            op: Op::Ge,
+            version: low,
+        }];
+        comparators.extend(high.map(|version| Comparator {
+            op: Op::Lt,
+            version,
+        }));
+        comparators
+    };
+
+    let comparators = match operator {
+        "" | "=" if partial.is_full() => vec![Comparator {
+            op: Op::Eq,
+            version: low,
+        }],
+        "" | "=" => between(partial.next()),
+        ">=" => between(None),
+        ">" => match partial.next() {
+            Some(version) if !partial.is_full() => vec![Comparator {
+                op: Op::Ge,
+                version,
+            }],
+            _ => vec![Comparator {
+                op: Op::Gt,
+                version: low,
+            }],
+        },
+        "<" => vec![Comparator {
+            op: Op::Lt,
+            version: low,
+        }],
+        "<=" => match partial.next() {
+            Some(version) if !partial.is_full() => vec![Comparator {
+                op: Op::Lt,
+                version,
+            }],
+            _ => vec![Comparator {
+                op: Op::Le,
+                version: low,
+            }],
+        },
+        "~" | "~>" => match (partial.major, partial.minor) {
+            (Some(major), Some(minor)) => between(Some((major, minor + 1, 0))),
+            _ => between(partial.next()),
+        },
+        "^" => match (partial.major, partial.minor, partial.patch) {
+            (Some(0), Some(0), Some(patch)) => between(Some((0, 0, patch + 1))),
+            (Some(0), Some(minor), _) => between(Some((0, minor + 1, 0))),
+            (Some(major), _, _) => between(Some((major + 1, 0, 0))),
+            (None, _, _) => between(None),
+        },
+        _ => return None,
+    };
+
+    Some(comparators)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_satisfies() {
+        let cases = [
+            ((18, 17, 1), ">=18", true),
+            ((16, 20, 2), ">=18", false),
+            ((20, 10, 0), ">= 18 < 21", true),
+            ((21, 0, 0), ">=18 <21", false),
+            ((18, 17, 1), "^18.17.0", true),
+            ((19, 0, 0), "^18.17.0", false),
+            ((18, 18, 0), "~18.17.0", false),
+            ((20, 1, 0), "18.x || 20.x", true),
+            ((19, 1, 0), "18.x || 20.x", false),
+            ((20, 99, 0), "16 - 20", true),
+            ((21, 0, 0), "16 - 20", false),
+            ((18, 0, 0), ">17", true),
+            ((17, 9, 0), ">17", false),
+            ((17, 9, 0), "<=17", true),
+            ((22, 0, 0), "*", true),
+            ((22, 0, 0), "", true),
+            ((18, 17, 1), "v18.17.1", true),
+        ];
+
+        for (version, range, expected) in cases {
+            assert_eq!(
+                satisfies(version, range),
+                Some(expected),
+                "{:?} in {}",
+                version,
+                range
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_ranges() {
+        assert!(!is_valid("node"));
+        assert!(!is_valid(">="));
+        assert!(!is_valid("18.x.1"));
+        assert!(!is_valid("1.2.3.4"));
+        assert!(is_valid(">=18.0.0-beta"));
+    }
+}
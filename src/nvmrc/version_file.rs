@@ -0,0 +1,312 @@
+use super::{json, parser, VersionSource};
+use crate::error::{io_error, Error, Result};
+use crate::log;
+use crate::spec::VersionSpec;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A version specification read from one of the supported version files
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionFile {
+    pub path: PathBuf,
+    pub source: VersionSource,
+    pub spec: String,
+}
+
+impl VersionFile {
+    /// The specification as written, parsed
+    pub fn version_spec(&self) -> Result<VersionSpec> {
+        VersionSpec::parse(&self.spec)
+    }
+}
+
+/// Read the version specification of the given source kind from a directory
+pub fn read_version_file(dir: &Path, source: VersionSource) -> Result<Option<VersionFile>> {
+    let path = dir.join(source.file_name());
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path).map_err(io_error("read", &path))?;
+
+    let spec = match source {
+        // Checked with nvm's syntax (one version, valid), but kept as written
+        VersionSource::Nvmrc => {
+            super::parse_nvmrc_content(&path, &content)?;
+            parser::version_text(&content).map(str::to_string)
+        }
+        VersionSource::NodeVersion => {
+            let spec = content.lines().next().unwrap_or("").trim();
+            if spec.is_empty() {
+                return Err(Error::EmptyVersionFile { path });
+            }
+            Some(spec.to_string())
+        }
+        VersionSource::ToolVersions => tool_versions_spec(&content),
+        VersionSource::PackageJsonEngines => json::get_string(&content, &["engines", "node"]),
+        VersionSource::PackageJsonVolta => json::get_string(&content, &["volta", "node"]),
+    };
+
+    Ok(spec.map(|spec| VersionFile { path, source, spec }))
+}
+
+/// Find every version specification in a directory, in lookup priority order
+pub fn find_version_files(dir: &Path) -> Result<Vec<VersionFile>> {
+    let mut files = Vec::new();
+    for source in VersionSource::ALL {
+        if let Some(file) = read_version_file(dir, source)? {
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+/// Find the version file that decides a directory's Node.js version: the
+/// first one in lookup priority order that names a version (see
+/// [`VersionSource::lookup`])
+pub fn find_version_file(dir: &Path) -> Result<Option<VersionFile>> {
+    for &source in VersionSource::lookup() {
+        if let Some(file) = read_version_file(dir, source)? {
+            log::debug(format!("probe {}: found", file.path.display()));
+            return Ok(Some(file));
+        }
+    }
+    log::debug(format!("probe {}: no version file", dir.display()));
+    Ok(None)
+}
+
+/// Write a version specification into the given kind of version file,
+/// keeping the rest of the file's content and formatting intact
+pub fn write_version_file(
+    dir: &Path,
+    source: VersionSource,
+    spec: &VersionSpec,
+) -> Result<PathBuf> {
+    let spec = &spec.to_string();
+    let path = dir.join(source.file_name());
+    let content = if path.exists() {
+        fs::read_to_string(&path).map_err(io_error("read", &path))?
+    } else {
+        String::new()
+    };
+
+    let updated = match source {
+        VersionSource::Nvmrc => parser::replace_version(&content, spec),
+        VersionSource::NodeVersion => replace_first_line(&content, spec),
+        VersionSource::ToolVersions => set_tool_versions_spec(&content, spec),
+        VersionSource::PackageJsonEngines | VersionSource::PackageJsonVolta => {
+            if content.trim().is_empty() {
+                return Err(Error::MissingFile { path });
+            }
+            let key = if source == VersionSource::PackageJsonVolta {
+                "volta"
+            } else {
+                "engines"
+            };
+            json::set_string(&content, &[key, "node"], spec).map_err(|e| e.in_file(&path))?
+        }
+    };
+
+    fs::write(&path, updated).map_err(io_error("write", &path))?;
+
+    Ok(path)
+}
+
+/// Line ending used by existing content (LF for new files)
+fn line_ending(content: &str) -> &'static str {
+    if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+/// Replace the first line, which is the only one nvm reads
+fn replace_first_line(content: &str, spec: &str) -> String {
+    let newline = line_ending(content);
+    match content.find('\n') {
+        Some(end) => format!("{}{}{}", spec, newline, &content[end + 1..]),
+        None => format!("{}{}", spec, newline),
+    }
+}
+
+/// Extract the first nodejs version from .tool-versions content
+fn tool_versions_spec(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let mut tokens = line.split('#').next()?.split_whitespace();
+        match tokens.next() {
+            Some("nodejs") | Some("node") => tokens.next().map(str::to_string),
+            _ => None,
+        }
+    })
+}
+
+/// Replace the first nodejs version in .tool-versions content, keeping any
+/// fallback versions and comments, or append a nodejs line
+fn set_tool_versions_spec(content: &str, spec: &str) -> String {
+    let mut result = String::with_capacity(content.len() + spec.len());
+    let mut found = false;
+
+    for line in content.split_inclusive('\n') {
+        if !found {
+            if let Some(updated) = replace_tool_versions_line(line, spec) {
+                result.push_str(&updated);
+                found = true;
+                continue;
+            }
+        }
+        result.push_str(line);
+    }
+
+    if !found {
+        let newline = line_ending(content);
+        if !result.is_empty() && !result.ends_with('\n') {
+            result.push_str(newline);
+        }
+        result.push_str(&format!("nodejs {}{}", spec, newline));
+    }
+
+    result
+}
+
+fn replace_tool_versions_line(line: &str, spec: &str) -> Option<String> {
+    let indent_len = line.len() - line.trim_start().len();
+    let rest = &line[indent_len..];
+    let tool = ["nodejs", "node"]
+        .into_iter()
+        .find(|tool| rest.starts_with(tool) && rest[tool.len()..].starts_with([' ', '\t']))?;
+
+    let after_tool = &rest[tool.len()..];
+    let version_start = after_tool.len() - after_tool.trim_start().len();
+    let after_separator = &after_tool[version_start..];
+    let version_len = after_separator
+        .find(char::is_whitespace)
+        .unwrap_or(after_separator.len());
+
+    Some(format!(
+        "{}{}{}",
+        &line[..indent_len + tool.len() + version_start],
+        spec,
+        &after_separator[version_len..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_version_files() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join(".nvmrc"), "18\n")?;
+        fs::write(
+            dir.path().join(".tool-versions"),
+            "python 3.12.0\nnodejs 18.17.0 # pinned\n",
+        )?;
+        fs::write(
+            dir.path().join("package.json"),
+            "{\"name\": \"app\", \"engines\": {\"node\": \">=18\"}}",
+        )?;
+
+        let files = find_version_files(dir.path())?;
+        let found: Vec<(VersionSource, &str)> =
+            files.iter().map(|f| (f.source, f.spec.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                (VersionSource::Nvmrc, "18"),
+                (VersionSource::ToolVersions, "18.17.0"),
+                (VersionSource::PackageJsonEngines, ">=18"),
+            ]
+        );
+        assert_eq!(
+            find_version_file(dir.path())?.map(|f| f.source),
+            Some(VersionSource::Nvmrc)
+        );
+
+        fs::remove_file(dir.path().join(".nvmrc"))?;
+        let file = find_version_file(dir.path())?.unwrap();
+        assert_eq!(file.source, VersionSource::ToolVersions);
+        assert_eq!(file.path, dir.path().join(".tool-versions"));
+
+        // engines.node alone does not pick a version by default
+        fs::remove_file(dir.path().join(".tool-versions"))?;
+        assert!(find_version_file(dir.path())?.is_none());
+
+        // .nvmrc is read with nvm's syntax, not just its first line
+        fs::write(
+            dir.path().join(".nvmrc"),
+            "# pinned\nnode=v18.17.1 # hydrogen\n",
+        )?;
+        assert_eq!(find_version_file(dir.path())?.unwrap().spec, "v18.17.1");
+        fs::write(dir.path().join(".nvmrc"), "18\n20\n")?;
+        assert!(matches!(
+            find_version_file(dir.path()),
+            Err(Error::NvmrcSyntax(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_plain_version_files() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+
+        let path = write_version_file(dir.path(), VersionSource::NodeVersion, &"20.10.0".parse()?)?;
+        assert_eq!(fs::read_to_string(&path)?, "20.10.0\n");
+
+        fs::write(dir.path().join(".nvmrc"), "v18\r\n")?;
+        write_version_file(dir.path(), VersionSource::Nvmrc, &"20".parse()?)?;
+        assert_eq!(fs::read_to_string(dir.path().join(".nvmrc"))?, "20\r\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_tool_versions_spec() {
+        assert_eq!(
+            set_tool_versions_spec("python 3.12.0\nnodejs  18.17.0 16.20.2 # x\n", "20.10.0"),
+            "python 3.12.0\nnodejs  20.10.0 16.20.2 # x\n"
+        );
+        assert_eq!(
+            set_tool_versions_spec("python 3.12.0", "20.10.0"),
+            "python 3.12.0\nnodejs 20.10.0\n"
+        );
+        assert_eq!(set_tool_versions_spec("", "20.10.0"), "nodejs 20.10.0\n");
+    }
+
+    #[test]
+    fn test_write_package_json() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempdir()?;
+        assert!(write_version_file(
+            dir.path(),
+            VersionSource::PackageJsonEngines,
+            &"20".parse()?
+        )
+        .is_err());
+
+        let original =
+            "{\n  \"name\": \"app\",\n  \"engines\": {\n    \"node\": \">=16\"\n  }\n}\n";
+        fs::write(dir.path().join("package.json"), original)?;
+
+        write_version_file(
+            dir.path(),
+            VersionSource::PackageJsonEngines,
+            &"20".parse()?,
+        )?;
+        write_version_file(
+            dir.path(),
+            VersionSource::PackageJsonVolta,
+            &"20.10.0".parse()?,
+        )?;
+
+        let content = fs::read_to_string(dir.path().join("package.json"))?;
+        assert_eq!(
+            content,
+            "{\n  \"name\": \"app\",\n  \"engines\": {\n    \"node\": \"20\"\n  },\n  \"volta\": {\n    \"node\": \"20.10.0\"\n  }\n}\n"
+        );
+
+        Ok(())
+    }
+}
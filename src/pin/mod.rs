@@ -0,0 +1,239 @@
+use anyhow::{anyhow, Result};
+use auto_nvm::config::Config;
+use auto_nvm::nvmrc::version_file;
+use auto_nvm::nvmrc::VersionSource;
+use auto_nvm::{nvm, resolve, VersionSpec};
+use std::path::{Path, PathBuf};
+
+/// How precisely a version is written to the version file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PinMode {
+    /// Write the spec exactly as given (e.g. `lts`, `18`)
+    AsGiven,
+    /// Write the full installed version (e.g. `18.17.1`)
+    Exact,
+    /// Write only the major version (e.g. `18`)
+    Major,
+}
+
+/// Options for `auto-nvm pin`
+#[derive(Debug, Clone)]
+pub struct PinRequest {
+    /// Version to pin; defaults to the active version
+    pub spec: Option<VersionSpec>,
+    /// Version file type to write; defaults to the configured or existing one
+    pub source: Option<VersionSource>,
+    pub exact: bool,
+    pub major: bool,
+    /// Pin even if the version is not installed
+    pub force: bool,
+}
+
+/// Result of a successful pin
+#[derive(Debug, Clone, PartialEq)]
+pub struct PinOutcome {
+    pub path: PathBuf,
+    pub source: VersionSource,
+    pub value: VersionSpec,
+}
+
+/// Pin a Node.js version in the version file of the given directory
+pub fn pin(config: &Config, dir: &Path, request: &PinRequest) -> Result<PinOutcome> {
+    let active = nvm::get_current_version().ok();
+    let spec = match (&request.spec, &active) {
+        (Some(spec), _) => spec.clone(),
+        (None, Some(active)) => VersionSpec::parse(active)?,
+        (None, None) => {
+            return Err(anyhow!(
+                "No Node.js version is active; pass the version to pin"
+            ))
+        }
+    };
+
+    // The active version counts as installed even if nvm did not install it
+    let nvm_dir = nvm::nvm_dir();
+    let mut installed = nvm::list_installed_versions()?;
+    if let Some(active) = &active {
+        insert_sorted(&mut installed, active);
+    }
+    let resolved = resolve::resolve_version(&spec, &installed, nvm_dir.as_deref());
+
+    if resolved.is_none() && !request.force {
+        return Err(anyhow!(
+            "Node.js {} is not installed. Run: nvm install {} (or pass --force to pin it anyway)",
+            spec,
+            spec
+        ));
+    }
+
+    let source = match request.source {
+        Some(source) => source,
+        None => match config.version_file()? {
+            Some(source) => source,
+            None => detect_source(dir)?,
+        },
+    };
+
+    let mode = if request.major {
+        PinMode::Major
+    } else if request.exact || request.spec.is_none() || source.requires_exact() {
+        PinMode::Exact
+    } else {
+        PinMode::AsGiven
+    };
+
+    let value = pin_value(&spec, resolved.as_deref(), mode)?;
+    if source.requires_exact() && !value.is_exact() {
+        return Err(anyhow!(
+            "{} only supports exact versions; cannot pin {}",
+            source.as_str(),
+            value
+        ));
+    }
+
+    let path = version_file::write_version_file(dir, source, &value)?;
+
+    Ok(PinOutcome {
+        path,
+        source,
+        value,
+    })
+}
+
+/// Add `version` to a newest-first list of versions, unless it is already there
+fn insert_sorted(versions: &mut Vec<String>, version: &str) {
+    let parsed = nvm::parse_version(version);
+    if versions
+        .iter()
+        .any(|existing| nvm::parse_version(existing) == parsed)
+    {
+        return;
+    }
+    let index = versions
+        .iter()
+        .position(|existing| nvm::parse_version(existing) < parsed)
+        .unwrap_or(versions.len());
+    versions.insert(index, version.to_string());
+}
+
+/// Pick the version file to write when none is configured: the first existing
+/// one (ignoring `engines`, which usually describes a range), else `.nvmrc`
+fn detect_source(dir: &Path) -> Result<VersionSource> {
+    let existing = version_file::find_version_files(dir)?
+        .into_iter()
+        .map(|file| file.source)
+        .find(|source| *source != VersionSource::PackageJsonEngines);
+
+    Ok(existing.unwrap_or(VersionSource::Nvmrc))
+}
+
+/// Compute the value written to the version file
+fn pin_value(spec: &VersionSpec, resolved: Option<&str>, mode: PinMode) -> Result<VersionSpec> {
+    let resolved = resolved.and_then(nvm::parse_version);
+    match mode {
+        PinMode::AsGiven => Ok(spec.clone()),
+        PinMode::Exact => match (resolved, spec) {
+            (Some(version), _) | (None, &VersionSpec::Exact { version, .. }) => {
+                Ok(VersionSpec::Exact {
+                    version,
+                    prefix: false,
+                })
+            }
+            _ => Err(anyhow!("Cannot pin {} exactly: it is not installed", spec)),
+        },
+        PinMode::Major => resolved
+            .map(|(major, _, _)| major)
+            .or_else(|| spec.major())
+            .map(|major| VersionSpec::Partial {
+                major,
+                minor: None,
+                prefix: false,
+            })
+            .ok_or_else(|| anyhow!("Cannot determine the major version of {}", spec)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_sorted() {
+        let mut versions = vec!["v20.10.0".to_string(), "v18.17.1".to_string()];
+        insert_sorted(&mut versions, "v22.11.0");
+        insert_sorted(&mut versions, "v18.19.0");
+        insert_sorted(&mut versions, "v16.20.2");
+        insert_sorted(&mut versions, "v20.10.0");
+        assert_eq!(
+            versions,
+            vec!["v22.11.0", "v20.10.0", "v18.19.0", "v18.17.1", "v16.20.2"]
+        );
+
+        // The newest installed match wins, not the active version
+        let spec = VersionSpec::parse("18").unwrap();
+        assert_eq!(
+            resolve::resolve_version(&spec, &versions, None).as_deref(),
+            Some("v18.19.0")
+        );
+    }
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn spec(spec: &str) -> VersionSpec {
+        VersionSpec::parse(spec).unwrap()
+    }
+
+    #[test]
+    fn test_pin_value() {
+        assert_eq!(
+            pin_value(&spec("lts"), Some("v20.10.0"), PinMode::AsGiven)
+                .unwrap()
+                .to_string(),
+            "lts/*"
+        );
+        assert_eq!(
+            pin_value(&spec("18"), Some("v18.17.1"), PinMode::Exact)
+                .unwrap()
+                .to_string(),
+            "18.17.1"
+        );
+        assert_eq!(
+            pin_value(&spec("v18.17.1"), Some("v18.17.1"), PinMode::Major)
+                .unwrap()
+                .to_string(),
+            "18"
+        );
+        // Forced pins of uninstalled versions
+        assert_eq!(
+            pin_value(&spec("v16.20.2"), None, PinMode::Exact)
+                .unwrap()
+                .to_string(),
+            "16.20.2"
+        );
+        assert_eq!(
+            pin_value(&spec("16.20"), None, PinMode::Major)
+                .unwrap()
+                .to_string(),
+            "16"
+        );
+        assert!(pin_value(&spec("16"), None, PinMode::Exact).is_err());
+        assert!(pin_value(&spec("lts"), None, PinMode::Major).is_err());
+    }
+
+    #[test]
+    fn test_detect_source() -> Result<()> {
+        let dir = tempdir()?;
+        assert_eq!(detect_source(dir.path())?, VersionSource::Nvmrc);
+
+        fs::write(
+            dir.path().join("package.json"),
+            "{\"engines\": {\"node\": \">=18\"}}",
+        )?;
+        assert_eq!(detect_source(dir.path())?, VersionSource::Nvmrc);
+
+        fs::write(dir.path().join(".node-version"), "18\n")?;
+        assert_eq!(detect_source(dir.path())?, VersionSource::NodeVersion);
+
+        Ok(())
+    }
+}
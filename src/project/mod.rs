@@ -0,0 +1,495 @@
+use anyhow::{anyhow, Context, Result};
+use auto_nvm::log;
+use auto_nvm::nvm::ShellType;
+use auto_nvm::resolve;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Name of the project-level configuration file
+pub const FILE_NAME: &str = "auto-nvm.toml";
+
+/// Variables `switch` exports to remember what it set, so it can undo it
+const DIR_VAR: &str = "AUTO_NVM_ENV_DIR";
+const KEYS_VAR: &str = "AUTO_NVM_ENV_KEYS";
+const PATH_VAR: &str = "AUTO_NVM_ENV_PATH";
+
+/// Variables a project may not set: they change how the shell itself runs
+/// commands, or belong to auto-nvm. PATH is extended through `path` instead.
+const RESERVED: &[&str] = &[
+    "PATH",
+    "BASH_ENV",
+    "ENV",
+    "IFS",
+    "PROMPT_COMMAND",
+    "PS1",
+    "SHELL",
+    "HOME",
+    "LD_PRELOAD",
+    "LD_LIBRARY_PATH",
+    "LD_AUDIT",
+    "DYLD_INSERT_LIBRARIES",
+    "DYLD_LIBRARY_PATH",
+];
+
+#[derive(Debug, Default, Deserialize)]
+struct RawProjectFile {
+    /// Directories, relative to the file, to put in front of PATH
+    #[serde(default)]
+    path: Vec<String>,
+    #[serde(default)]
+    env: BTreeMap<String, toml::Value>,
+}
+
+/// The environment a project's `auto-nvm.toml` asks for
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectEnv {
+    /// Directory containing the `auto-nvm.toml`
+    pub dir: PathBuf,
+    pub vars: Vec<(String, String)>,
+    /// Absolute directories to prepend to PATH
+    pub path: Vec<PathBuf>,
+    /// Hex-encoded SHA-256 of the file, which `allow` records
+    pub hash: String,
+}
+
+impl ProjectEnv {
+    /// Parse the content of an `auto-nvm.toml` located in `dir`
+    pub fn parse(dir: &Path, content: &str) -> Result<Self> {
+        let raw: RawProjectFile = toml::from_str(content).context("Invalid auto-nvm.toml")?;
+
+        let mut vars = Vec::new();
+        for (key, value) in raw.env {
+            if !is_identifier(&key) {
+                return Err(anyhow!("Invalid environment variable name \"{}\"", key));
+            }
+            if RESERVED.contains(&key.as_str()) || key.starts_with("AUTO_NVM_") {
+                return Err(anyhow!(
+                    "{} cannot be set from {} (use `path` to extend PATH)",
+                    key,
+                    FILE_NAME
+                ));
+            }
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Float(value) => value.to_string(),
+                toml::Value::Boolean(value) => value.to_string(),
+                _ => return Err(anyhow!("{} must be a string, number or boolean", key)),
+            };
+            vars.push((key, value));
+        }
+
+        // Only directories inside the project: `.` would put whatever the
+        // repository ships in front of every command
+        let path = raw
+            .path
+            .iter()
+            .map(|entry| {
+                let relative = Path::new(entry);
+                let inside = relative
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)));
+                if entry.is_empty() || !inside {
+                    return Err(anyhow!(
+                        "path entry \"{}\" must be a directory inside the project (no absolute paths, `.` or `..`)",
+                        entry
+                    ));
+                }
+                Ok(dir.join(relative))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            vars,
+            path,
+            hash: hex_sha256(content.as_bytes()),
+        })
+    }
+
+    /// The `auto-nvm.toml` this environment was read from
+    pub fn file(&self) -> PathBuf {
+        self.dir.join(FILE_NAME)
+    }
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The list of `auto-nvm.toml` files the user allowed, as
+/// `<sha256>  <path>` lines
+pub fn allow_list_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("auto-nvm").join("allowed"))
+}
+
+fn read_allow_list(list: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let content = match fs::read_to_string(list) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", list.display())),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| line.split_once("  "))
+        .map(|(hash, path)| (hash.to_string(), PathBuf::from(path)))
+        .collect())
+}
+
+fn write_allow_list(list: &Path, entries: &[(String, PathBuf)]) -> Result<()> {
+    if let Some(parent) = list.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    let content: String = entries
+        .iter()
+        .map(|(hash, path)| format!("{}  {}\n", hash, path.display()))
+        .collect();
+    fs::write(list, content).with_context(|| format!("Failed to write {}", list.display()))
+}
+
+/// Whether this exact content of the file was allowed in `list`
+pub fn is_allowed(list: &Path, env: &ProjectEnv) -> Result<bool> {
+    let file = canonical(&env.file());
+    Ok(read_allow_list(list)?
+        .iter()
+        .any(|(hash, path)| *hash == env.hash && *path == file))
+}
+
+/// Record the file's current content in `list`, replacing an older entry
+pub fn allow(list: &Path, env: &ProjectEnv) -> Result<()> {
+    let file = canonical(&env.file());
+    let mut entries = read_allow_list(list)?;
+    entries.retain(|(_, path)| *path != file);
+    entries.push((env.hash.clone(), file));
+    write_allow_list(list, &entries)
+}
+
+/// Remove `file` from `list`; returns whether it was there
+pub fn revoke(list: &Path, file: &Path) -> Result<bool> {
+    let file = canonical(file);
+    let mut entries = read_allow_list(list)?;
+    let before = entries.len();
+    entries.retain(|(_, path)| *path != file);
+    if entries.len() == before {
+        return Ok(false);
+    }
+    write_allow_list(list, &entries)?;
+    Ok(true)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Find the nearest `auto-nvm.toml` in `dir` or its parents and load it
+pub fn find_project_env(dir: &Path) -> Result<Option<ProjectEnv>> {
+    for ancestor in dir.ancestors() {
+        let file = ancestor.join(FILE_NAME);
+        let found = file.is_file();
+        log::debug(format!(
+            "probe {}: {}",
+            file.display(),
+            if found { "found" } else { "missing" }
+        ));
+        if found {
+            let content = fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            return ProjectEnv::parse(ancestor, &content)
+                .with_context(|| format!("In {}", file.display()))
+                .map(Some);
+        }
+    }
+
+    Ok(None)
+}
+
+/// What a previous `switch` set in the current shell
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActiveEnv {
+    pub dir: Option<PathBuf>,
+    pub keys: Vec<String>,
+    pub path: Vec<PathBuf>,
+}
+
+impl ActiveEnv {
+    /// Read the bookkeeping variables from the process environment
+    pub fn from_env() -> Self {
+        Self {
+            dir: std::env::var_os(DIR_VAR)
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+            keys: std::env::var(KEYS_VAR)
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            path: std::env::var_os(PATH_VAR)
+                .map(|path| std::env::split_paths(&path).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Shell commands that move the shell from the `active` project environment
+/// to `next`: variables of the project being left are unset, the new
+/// project's variables exported and PATH adjusted
+///
+/// Nothing is emitted while staying within the same project.
+pub fn transition(
+    shell: ShellType,
+    active: &ActiveEnv,
+    next: Option<&ProjectEnv>,
+    current_path: Option<&OsStr>,
+) -> Result<Vec<String>> {
+    if active.dir.as_deref() == next.map(|next| next.dir.as_path()) {
+        return Ok(Vec::new());
+    }
+
+    let mut commands = Vec::new();
+    let next_keys: Vec<&str> = next
+        .map(|next| next.vars.iter().map(|(key, _)| key.as_str()).collect())
+        .unwrap_or_default();
+
+    for key in &active.keys {
+        // Only ever unset what auto-nvm set itself
+        if is_identifier(key) && !next_keys.contains(&key.as_str()) {
+            commands.push(unset_var(shell, key));
+        }
+    }
+
+    let next_path: &[PathBuf] = next.map(|next| next.path.as_slice()).unwrap_or_default();
+    if !active.path.is_empty() || !next_path.is_empty() {
+        let mut entries: Vec<PathBuf> = next_path.to_vec();
+        entries.extend(
+            current_path
+                .map(|path| std::env::split_paths(path).collect::<Vec<_>>())
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|entry| !active.path.contains(entry) && !next_path.contains(entry)),
+        );
+        commands.push(set_path(shell, &entries)?);
+    }
+
+    match next {
+        Some(next) => {
+            for (key, value) in &next.vars {
+                commands.push(set_var(shell, key, value));
+            }
+            commands.push(set_var(shell, DIR_VAR, &next.dir.to_string_lossy()));
+            commands.push(set_var(shell, KEYS_VAR, &next_keys.join(" ")));
+            let path = std::env::join_paths(&next.path).context("Invalid path entry")?;
+            commands.push(set_var(shell, PATH_VAR, &path.to_string_lossy()));
+        }
+        None => {
+            for key in [DIR_VAR, KEYS_VAR, PATH_VAR] {
+                commands.push(unset_var(shell, key));
+            }
+        }
+    }
+
+    Ok(commands)
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Quote a value for the given shell
+fn quote(shell: ShellType, value: &str) -> String {
+    match shell {
+        ShellType::Bash | ShellType::Zsh => resolve::shell_quote(value),
+        ShellType::Fish => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+        ShellType::PowerShell => format!("'{}'", value.replace('\'', "''")),
+    }
+}
+
+fn set_var(shell: ShellType, key: &str, value: &str) -> String {
+    match shell {
+        ShellType::Bash | ShellType::Zsh => format!("export {}={}", key, quote(shell, value)),
+        ShellType::Fish => format!("set -gx {} {}", key, quote(shell, value)),
+        ShellType::PowerShell => format!("$env:{} = {}", key, quote(shell, value)),
+    }
+}
+
+fn unset_var(shell: ShellType, key: &str) -> String {
+    match shell {
+        ShellType::Bash | ShellType::Zsh => format!("unset {}", key),
+        ShellType::Fish => format!("set -e {}", key),
+        ShellType::PowerShell => format!("Remove-Item Env:{} -ErrorAction SilentlyContinue", key),
+    }
+}
+
+fn set_path(shell: ShellType, entries: &[PathBuf]) -> Result<String> {
+    // fish keeps PATH as a list
+    if shell == ShellType::Fish {
+        let entries: Vec<String> = entries
+            .iter()
+            .map(|entry| quote(shell, &entry.to_string_lossy()))
+            .collect();
+        return Ok(format!("set -gx PATH {}", entries.join(" ")));
+    }
+
+    let path = std::env::join_paths(entries).context("Invalid path entry")?;
+    Ok(set_var(shell, "PATH", &path.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> ProjectEnv {
+        ProjectEnv::parse(
+            Path::new("/work/app"),
+            r#"
+path = ["node_modules/.bin"]
+
+[env]
+NODE_OPTIONS = "--max-old-space-size=4096"
+NPM_CONFIG_REGISTRY = "https://npm.example.com/it's"
+PORT = 3000
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let env = project();
+        assert_eq!(env.path, vec![PathBuf::from("/work/app/node_modules/.bin")]);
+        assert_eq!(
+            env.vars,
+            vec![
+                (
+                    "NODE_OPTIONS".to_string(),
+                    "--max-old-space-size=4096".to_string()
+                ),
+                (
+                    "NPM_CONFIG_REGISTRY".to_string(),
+                    "https://npm.example.com/it's".to_string()
+                ),
+                ("PORT".to_string(), "3000".to_string()),
+            ]
+        );
+
+        let dir = Path::new("/work/app");
+        assert!(ProjectEnv::parse(dir, "[env]\nPATH = \"/tmp\"").is_err());
+        assert!(ProjectEnv::parse(dir, "[env]\nDYLD_INSERT_LIBRARIES = \"x\"").is_err());
+        for entry in [".", "..", "/tmp", "bin/../..", "./bin", ""] {
+            let content = format!("path = [{:?}]", entry);
+            assert!(ProjectEnv::parse(dir, &content).is_err(), "{}", entry);
+        }
+        assert!(ProjectEnv::parse(dir, "[env]\n\"FOO BAR\" = \"x\"").is_err());
+        assert!(ProjectEnv::parse(dir, "[env]\nAUTO_NVM_QUIET = \"1\"").is_err());
+        assert!(ProjectEnv::parse(dir, "[env]\nLIST = [1, 2]").is_err());
+        assert!(ProjectEnv::parse(dir, "").unwrap().vars.is_empty());
+    }
+
+    #[test]
+    fn test_allow_list() {
+        let home = tempfile::tempdir().unwrap();
+        let list = home.path().join("allowed");
+        let dir = home.path().join("app");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(FILE_NAME), "[env]\nPORT = 3000\n").unwrap();
+        let env = find_project_env(&dir).unwrap().unwrap();
+        assert!(!is_allowed(&list, &env).unwrap());
+
+        allow(&list, &env).unwrap();
+        allow(&list, &env).unwrap();
+        assert!(is_allowed(&list, &env).unwrap());
+        assert_eq!(fs::read_to_string(&list).unwrap().lines().count(), 1);
+
+        // Any change needs a new `allow`
+        fs::write(dir.join(FILE_NAME), "[env]\nPORT = 4000\n").unwrap();
+        let changed = find_project_env(&dir).unwrap().unwrap();
+        assert!(!is_allowed(&list, &changed).unwrap());
+
+        assert!(revoke(&list, &env.file()).unwrap());
+        assert!(!revoke(&list, &env.file()).unwrap());
+        assert!(!is_allowed(&list, &env).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_transition() {
+        let env = project();
+        let path = OsStr::new("/usr/local/bin:/usr/bin");
+
+        let entering = transition(
+            ShellType::Bash,
+            &ActiveEnv::default(),
+            Some(&env),
+            Some(path),
+        )
+        .unwrap();
+        assert_eq!(
+            entering,
+            vec![
+                "export PATH='/work/app/node_modules/.bin:/usr/local/bin:/usr/bin'",
+                "export NODE_OPTIONS='--max-old-space-size=4096'",
+                "export NPM_CONFIG_REGISTRY='https://npm.example.com/it'\\''s'",
+                "export PORT='3000'",
+                "export AUTO_NVM_ENV_DIR='/work/app'",
+                "export AUTO_NVM_ENV_KEYS='NODE_OPTIONS NPM_CONFIG_REGISTRY PORT'",
+                "export AUTO_NVM_ENV_PATH='/work/app/node_modules/.bin'",
+            ]
+        );
+
+        let active = ActiveEnv {
+            dir: Some(env.dir.clone()),
+            keys: vec!["NODE_OPTIONS".to_string(), "PORT".to_string()],
+            path: env.path.clone(),
+        };
+        assert!(transition(ShellType::Bash, &active, Some(&env), Some(path))
+            .unwrap()
+            .is_empty());
+
+        let leaving = transition(
+            ShellType::Fish,
+            &active,
+            None,
+            Some(OsStr::new("/work/app/node_modules/.bin:/usr/bin")),
+        )
+        .unwrap();
+        assert_eq!(
+            leaving,
+            vec![
+                "set -e NODE_OPTIONS",
+                "set -e PORT",
+                "set -gx PATH '/usr/bin'",
+                "set -e AUTO_NVM_ENV_DIR",
+                "set -e AUTO_NVM_ENV_KEYS",
+                "set -e AUTO_NVM_ENV_PATH",
+            ]
+        );
+
+        // Outside any project with nothing set, nothing happens
+        assert!(
+            transition(ShellType::Bash, &ActiveEnv::default(), None, Some(path))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote(ShellType::Fish, r"a\b'c"), r"'a\\b\'c'");
+        assert_eq!(quote(ShellType::PowerShell, "it's"), "'it''s'");
+        assert_eq!(
+            set_var(ShellType::PowerShell, "PORT", "3000"),
+            "$env:PORT = '3000'"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use crate::error::{io_error, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Release schedule bundled at build time, in the format of
+/// https://github.com/nodejs/Release/blob/main/schedule.json
+const BUNDLED_SCHEDULE: &str = include_str!("schedule.json");
+
+/// Warn this many days before a release line reaches end-of-life
+pub const EOL_WARNING_DAYS: i64 = 90;
+
+/// A Node.js release line (major version)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseLine {
+    pub start: String,
+    /// Date the line entered Active LTS (even majors only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lts: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<String>,
+    pub end: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub codename: Option<String>,
+}
+
+/// Support status of a release line on a given day
+#[derive(Debug, Clone, PartialEq)]
+pub enum EolStatus {
+    Supported,
+    /// End-of-life is less than `EOL_WARNING_DAYS` away
+    NearEnd {
+        end: String,
+        days: i64,
+    },
+    Ended {
+        end: String,
+    },
+    /// The major is not in the schedule
+    Unknown,
+}
+
+/// Majors mapped to their release line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    lines: BTreeMap<u64, ReleaseLine>,
+}
+
+impl Schedule {
+    /// The schedule compiled into the binary
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED_SCHEDULE).expect("bundled release schedule is valid")
+    }
+
+    /// Parse a schedule.json document
+    ///
+    /// Pre-1.0 lines such as `v0.12` are skipped; they are all long past EOL.
+    pub fn parse(json: &str) -> Result<Self> {
+        let raw: BTreeMap<String, ReleaseLine> =
+            serde_json::from_str(json).map_err(|source| Error::Json {
+                context: "Invalid release schedule".to_string(),
+                source,
+            })?;
+
+        let mut lines = BTreeMap::new();
+        for (key, line) in raw {
+            let key = key.trim_start_matches('v');
+            if key.contains('.') {
+                continue;
+            }
+            let major = key
+                .parse()
+                .map_err(|_| invalid(format!("Invalid release line \"{}\"", key)))?;
+            let dates = [Some(&line.start), Some(&line.end), line.lts.as_ref()];
+            for date in dates.into_iter().flatten() {
+                parse_date(date).ok_or_else(|| invalid(format!("Invalid date \"{}\"", date)))?;
+            }
+            lines.insert(major, line);
+        }
+
+        if lines.is_empty() {
+            return Err(invalid(
+                "Release schedule contains no release lines".to_string(),
+            ));
+        }
+
+        Ok(Self { lines })
+    }
+
+    /// The bundled schedule, updated with the lines of the local override file
+    pub fn load() -> Result<Self> {
+        let mut schedule = Self::bundled();
+
+        if let Some(path) = override_path().filter(|path| path.is_file()) {
+            let content = fs::read_to_string(&path).map_err(io_error("read", &path))?;
+            let update = Self::parse(&content).map_err(|e| e.in_file(&path))?;
+            schedule.lines.extend(update.lines);
+        }
+
+        Ok(schedule)
+    }
+
+    pub fn line(&self, major: u64) -> Option<&ReleaseLine> {
+        self.lines.get(&major)
+    }
+
+    /// All release lines, oldest first
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = (u64, &ReleaseLine)> {
+        self.lines.iter().map(|(major, line)| (*major, line))
+    }
+
+    /// Support status of a major on `today` (`YYYY-MM-DD`)
+    pub fn eol_status(&self, major: u64, today: &str) -> EolStatus {
+        let line = match self.line(major) {
+            Some(line) => line,
+            // 0.x and io.js lines are not in the schedule
+            None if major < 4 => {
+                return EolStatus::Ended {
+                    end: "2016-12-31".to_string(),
+                }
+            }
+            None => return EolStatus::Unknown,
+        };
+
+        match (parse_date(&line.end), parse_date(today)) {
+            (Some(end), Some(today)) if end < today => EolStatus::Ended {
+                end: line.end.clone(),
+            },
+            (Some(end), Some(today)) if end - today <= EOL_WARNING_DAYS => EolStatus::NearEnd {
+                end: line.end.clone(),
+                days: end - today,
+            },
+            _ => EolStatus::Supported,
+        }
+    }
+
+    /// Warning to show for a version or version spec, if its line is past or
+    /// close to end-of-life
+    pub fn eol_warning(&self, version: &str, today: &str) -> Option<String> {
+        let major = major_of(version)?;
+        let name = match self.line(major).and_then(|line| line.codename.as_ref()) {
+            Some(codename) => format!("Node.js {} ({})", major, codename),
+            None => format!("Node.js {}", major),
+        };
+
+        match self.eol_status(major, today) {
+            EolStatus::Ended { end } => Some(format!("{} reached end-of-life on {}", name, end)),
+            EolStatus::NearEnd { end, days } => Some(format!(
+                "{} reaches end-of-life on {} (in {} days)",
+                name, end, days
+            )),
+            EolStatus::Supported | EolStatus::Unknown => None,
+        }
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidSchedule { message }
+}
+
+/// Location of the local schedule override (`AUTO_NVM_SCHEDULE_FILE`, else
+/// the local data dir)
+pub fn override_path() -> Option<PathBuf> {
+    if let Ok(path) = std::env::var("AUTO_NVM_SCHEDULE_FILE") {
+        if !path.is_empty() {
+            return Some(PathBuf::from(path));
+        }
+    }
+
+    dirs::data_local_dir().map(|dir| dir.join("auto-nvm").join("schedule.json"))
+}
+
+/// Validate a schedule.json file and install it as the local override
+///
+/// Returns the override path and the number of release lines in the file.
+pub fn update_from(file: &Path) -> Result<(PathBuf, usize)> {
+    let content = fs::read_to_string(file).map_err(io_error("read", file))?;
+    let schedule = Schedule::parse(&content).map_err(|e| e.in_file(file))?;
+
+    let path = override_path().ok_or(Error::NoDirectory("data"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error("create directory", parent))?;
+    }
+    fs::write(&path, content).map_err(io_error("write", &path))?;
+
+    Ok((path, schedule.lines.len()))
+}
+
+/// EOL warning for a version or spec using the installed schedule and today's date
+pub fn eol_warning(version: &str) -> Option<String> {
+    // A broken override file must not break `check` or the cd hook
+    let schedule = Schedule::load().unwrap_or_else(|_| Schedule::bundled());
+    schedule.eol_warning(version, &today())
+}
+
+/// Major version of a version or numeric spec (`v18.17.1`, `18`, `18.17`)
+pub fn major_of(version: &str) -> Option<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Today's date in UTC as `YYYY-MM-DD`
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / 86_400)
+        .unwrap_or(0);
+    format_date(days as i64)
+}
+
+/// Convert days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+pub(crate) fn format_date(days: i64) -> String {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Convert a `YYYY-MM-DD` date to days since 1970-01-01
+fn parse_date(date: &str) -> Option<i64> {
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    Some(era * 146_097 + doe - 719_468)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_schedule() {
+        let schedule = Schedule::bundled();
+        let line = schedule.line(20).unwrap();
+        assert_eq!(line.codename.as_deref(), Some("Iron"));
+        assert_eq!(line.end, "2026-04-30");
+        assert!(schedule.line(21).unwrap().codename.is_none());
+    }
+
+    #[test]
+    fn test_eol_status() {
+        let schedule = Schedule::bundled();
+        assert_eq!(
+            schedule.eol_status(16, "2024-01-01"),
+            EolStatus::Ended {
+                end: "2023-09-11".to_string()
+            }
+        );
+        assert_eq!(
+            schedule.eol_status(20, "2026-03-01"),
+            EolStatus::NearEnd {
+                end: "2026-04-30".to_string(),
+                days: 60
+            }
+        );
+        assert_eq!(schedule.eol_status(22, "2024-01-01"), EolStatus::Supported);
+        assert_eq!(schedule.eol_status(99, "2024-01-01"), EolStatus::Unknown);
+        assert!(matches!(
+            schedule.eol_status(0, "2024-01-01"),
+            EolStatus::Ended { .. }
+        ));
+    }
+
+    #[test]
+    fn test_eol_warning() {
+        let schedule = Schedule::bundled();
+        assert_eq!(
+            schedule.eol_warning("v14.21.3", "2024-01-01").as_deref(),
+            Some("Node.js 14 (Fermium) reached end-of-life on 2023-04-30")
+        );
+        assert_eq!(
+            schedule.eol_warning("25", "2026-05-01").as_deref(),
+            Some("Node.js 25 reaches end-of-life on 2026-06-01 (in 31 days)")
+        );
+        assert_eq!(schedule.eol_warning("22", "2024-01-01"), None);
+        assert_eq!(schedule.eol_warning("lts", "2024-01-01"), None);
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let schedule = Schedule::parse(
+            r#"{
+                "v0.12": {"start": "2015-02-06", "end": "2016-12-31"},
+                "v30": {"start": "2028-04-20", "lts": "2028-10-24", "end": "2031-04-30", "codename": "Next"}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(schedule.lines().count(), 1);
+        assert_eq!(schedule.line(30).unwrap().codename.as_deref(), Some("Next"));
+
+        assert!(Schedule::parse("{}").is_err());
+        assert!(Schedule::parse(r#"{"v30": {"start": "soon", "end": "later"}}"#).is_err());
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(19_782), "2024-02-29");
+        assert_eq!(format_date(20_744), "2026-10-18");
+        for days in [0, 19_782, 20_744, -1] {
+            assert_eq!(parse_date(&format_date(days)), Some(days));
+        }
+        assert_eq!(parse_date("2024-13-01"), None);
+    }
+}
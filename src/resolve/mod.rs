@@ -0,0 +1,613 @@
+use crate::backend::{self, VersionManager};
+use crate::diagnostic::{self, Diagnostic, Severity};
+use crate::error::{Error, Result};
+use crate::index::{self, RemoteIndex};
+use crate::log;
+use crate::nvm::{self, Backend};
+use crate::nvmrc::parser::Nvmrc;
+use crate::nvmrc::version_file::{self, VersionFile};
+use crate::nvmrc::{self, package_manager, VersionSource};
+use crate::release;
+use crate::spec::VersionSpec;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Machine-readable output formats for `check` and `switch`
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// A single JSON object
+    Json,
+    /// Shell-quoted AUTO_NVM_* variable assignments
+    Env,
+    /// One `key: value` pair per line
+    Plain,
+}
+
+/// How the active Node.js version relates to the version file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatchStatus {
+    Match,
+    Mismatch,
+    NotInstalled,
+    NoVersionFile,
+    InvalidSpec,
+}
+
+impl MatchStatus {
+    /// Stable process exit code for this status
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MatchStatus::Match => 0,
+            MatchStatus::Mismatch => 3,
+            MatchStatus::NotInstalled => 4,
+            MatchStatus::NoVersionFile => 5,
+            MatchStatus::InvalidSpec => 6,
+        }
+    }
+
+    /// Name of the status as shown in command output
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchStatus::Match => "match",
+            MatchStatus::Mismatch => "mismatch",
+            MatchStatus::NotInstalled => "not-installed",
+            MatchStatus::NoVersionFile => "no-version-file",
+            MatchStatus::InvalidSpec => "invalid-spec",
+        }
+    }
+}
+
+/// Everything auto-nvm knows about the version requirement of a directory
+#[derive(Debug, Clone, Serialize)]
+pub struct Resolution {
+    pub version_file: Option<PathBuf>,
+    pub source: Option<VersionSource>,
+    pub spec: Option<VersionSpec>,
+    pub resolved_version: Option<String>,
+    pub active_version: Option<String>,
+    pub installed: bool,
+    pub status: MatchStatus,
+    pub backend: Backend,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Set when the required release line is past or close to end-of-life
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eol_warning: Option<String>,
+    /// Newest published version satisfying the spec (from the remote index)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available_version: Option<String>,
+    /// Newer published version of the same major as the resolved version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub newer_version: Option<String>,
+    /// `packageManager` from package.json, e.g. `pnpm@8.15.1`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_manager: Option<String>,
+    /// Version of that package manager found on PATH (only filled in by `check`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_manager_version: Option<String>,
+    /// Problems found in the version file, with suggested fixes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolution {
+    fn new(backend: Backend) -> Self {
+        Self {
+            version_file: None,
+            source: None,
+            spec: None,
+            resolved_version: None,
+            active_version: None,
+            installed: false,
+            status: MatchStatus::NoVersionFile,
+            backend,
+            error: None,
+            eol_warning: None,
+            available_version: None,
+            newer_version: None,
+            package_manager: None,
+            package_manager_version: None,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Whether the package manager on PATH differs from `packageManager`
+    pub fn package_manager_mismatch(&self) -> bool {
+        match (&self.package_manager, &self.package_manager_version) {
+            (Some(required), Some(active)) => required
+                .split_once('@')
+                .is_some_and(|(_, version)| version != active.trim_start_matches('v')),
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    /// Render the resolution in the given format, optionally including the
+    /// command `switch` would emit
+    pub fn render(&self, format: OutputFormat, command: Option<&str>) -> Result<String> {
+        match format {
+            OutputFormat::Json => serde_json::to_string_pretty(&RenderedResolution {
+                resolution: self,
+                command,
+            })
+            .map_err(|source| Error::Json {
+                context: "Failed to serialize resolution".to_string(),
+                source,
+            }),
+            OutputFormat::Env => Ok(self
+                .fields(command)
+                .into_iter()
+                .map(|(key, value)| {
+                    format!("AUTO_NVM_{}={}", key.to_uppercase(), shell_quote(&value))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            OutputFormat::Plain => Ok(self
+                .fields(command)
+                .into_iter()
+                .map(|(key, value)| format!("{}: {}", key, value))
+                .collect::<Vec<_>>()
+                .join("\n")),
+        }
+    }
+
+    /// Flat key/value view shared by the env and plain formats
+    fn fields(&self, command: Option<&str>) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            (
+                "version_file",
+                self.version_file
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default(),
+            ),
+            (
+                "source",
+                self.source
+                    .map(|s| s.as_str().to_string())
+                    .unwrap_or_default(),
+            ),
+            (
+                "spec",
+                self.spec
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_default(),
+            ),
+            (
+                "resolved_version",
+                self.resolved_version.clone().unwrap_or_default(),
+            ),
+            (
+                "active_version",
+                self.active_version.clone().unwrap_or_default(),
+            ),
+            ("installed", self.installed.to_string()),
+            ("status", self.status.as_str().to_string()),
+            ("backend", self.backend.as_str().to_string()),
+        ];
+
+        if let Some(error) = &self.error {
+            fields.push(("error", error.clone()));
+        }
+        if let Some(warning) = &self.eol_warning {
+            fields.push(("eol_warning", warning.clone()));
+        }
+        if let Some(available) = &self.available_version {
+            fields.push(("available_version", available.clone()));
+        }
+        if let Some(newer) = &self.newer_version {
+            fields.push(("newer_version", newer.clone()));
+        }
+        if let Some(package_manager) = &self.package_manager {
+            fields.push(("package_manager", package_manager.clone()));
+        }
+        if let Some(version) = &self.package_manager_version {
+            fields.push(("package_manager_version", version.clone()));
+        }
+        if let Some(command) = command {
+            fields.push(("command", command.to_string()));
+        }
+
+        fields
+    }
+}
+
+/// JSON view of a resolution together with the emitted command
+#[derive(Serialize)]
+struct RenderedResolution<'a> {
+    #[serde(flatten)]
+    resolution: &'a Resolution,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<&'a str>,
+}
+
+/// Quote a value for safe use in POSIX shell assignments
+pub fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Resolve the version requirement of the current working directory
+///
+/// The active version is taken from `node_binary` when given, otherwise from
+/// the `node` on PATH. With a remote index, the newest published versions
+/// are looked up as well.
+pub fn resolve_current_dir(
+    node_binary: Option<&Path>,
+    remote: Option<&RemoteIndex>,
+) -> Result<Resolution> {
+    let current_dir = std::env::current_dir().map_err(Error::CurrentDir)?;
+    let backend = backend::detect();
+    let active_version = match node_binary {
+        Some(node) => Some(nvm::get_node_version(node)?),
+        None => backend.active_version(),
+    };
+
+    resolve_dir(&current_dir, backend.as_ref(), active_version, remote)
+}
+
+/// Resolve the version requirement of a directory against a version manager,
+/// given the active Node.js version
+pub fn resolve_dir(
+    dir: &Path,
+    backend: &dyn VersionManager,
+    active_version: Option<String>,
+    remote: Option<&RemoteIndex>,
+) -> Result<Resolution> {
+    let mut resolution = Resolution::new(backend.kind());
+    resolution.active_version = active_version;
+
+    // A broken packageManager field must not hide the Node.js resolution
+    resolution.package_manager = package_manager::read_package_manager(dir)
+        .ok()
+        .flatten()
+        .map(|package_manager| package_manager.to_string());
+
+    let file = match version_file::find_version_file(dir) {
+        Ok(Some(file)) => file,
+        Ok(None) => return Ok(resolution),
+        Err(e) => {
+            resolution.status = MatchStatus::InvalidSpec;
+            resolution.error = Some(e.to_string());
+            match e {
+                Error::EmptyVersionFile { path } => resolution.version_file = Some(path),
+                Error::NvmrcSyntax(diagnostic) => {
+                    resolution.version_file = diagnostic.file.clone();
+                    resolution.source = Some(VersionSource::Nvmrc);
+                    resolution.diagnostics.push(*diagnostic);
+                }
+                _ => {}
+            }
+            return Ok(resolution);
+        }
+    };
+    resolution.version_file = Some(file.path.clone());
+    resolution.source = Some(file.source);
+
+    let (spec, nvmrc) = match read_spec(&file) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            resolution.status = MatchStatus::InvalidSpec;
+            resolution.error = Some(e.to_string());
+            if let Error::NvmrcSyntax(diagnostic) = e {
+                resolution.diagnostics.push(*diagnostic);
+            }
+            return Ok(resolution);
+        }
+    };
+    resolution.spec = Some(spec.clone());
+    resolution.eol_warning = release::eol_warning(&spec.to_string());
+
+    // An active version that already satisfies the spec counts as installed,
+    // even when it was not installed through nvm (e.g. setup-node in CI)
+    let active_match = resolution.active_version.as_ref().and_then(|active| {
+        resolve_version(&spec, std::slice::from_ref(active), backend.alias_dir())
+    });
+    if let Some(active) = active_match {
+        resolution.resolved_version = Some(active);
+        resolution.installed = true;
+        resolution.status = MatchStatus::Match;
+    } else {
+        resolution.resolved_version = backend.resolve(&spec)?;
+        resolution.installed = resolution.resolved_version.is_some();
+        resolution.status = if resolution.installed {
+            MatchStatus::Mismatch
+        } else {
+            MatchStatus::NotInstalled
+        };
+    }
+    if resolution.status == MatchStatus::NotInstalled {
+        let installed = backend.installed_versions().unwrap_or_default();
+        let suggestion = match &spec {
+            VersionSpec::LtsCodename(_) => diagnostic::suggest_spec(&spec.to_string()),
+            spec => spec
+                .major()
+                .and_then(|major| diagnostic::suggest_installed(major, spec.minor(), &installed)),
+        };
+        let message = format!("Node.js {} is not installed", spec);
+        let diagnostic = match &nvmrc {
+            Some(nvmrc) => nvmrc.diagnostic(Severity::Warning, message),
+            None => Diagnostic::warning(message),
+        };
+        resolution
+            .diagnostics
+            .push(diagnostic.with_file(&file.path).with_suggestion(suggestion));
+    }
+
+    log::debug(format!(
+        "resolved {} to {} ({})",
+        spec,
+        resolution
+            .resolved_version
+            .as_deref()
+            .unwrap_or("nothing installed"),
+        resolution.status.as_str()
+    ));
+
+    if let Some(remote) = remote {
+        resolution.apply_remote(&spec, remote);
+    }
+    if let Some(resolved) = resolution
+        .resolved_version
+        .as_ref()
+        .or(resolution.available_version.as_ref())
+    {
+        resolution.eol_warning = release::eol_warning(resolved);
+    }
+
+    Ok(resolution)
+}
+
+/// Parse the version in a version file; an .nvmrc is parsed with its own
+/// syntax, so diagnostics can point into it
+fn read_spec(file: &VersionFile) -> Result<(VersionSpec, Option<Nvmrc>)> {
+    match file.source {
+        VersionSource::Nvmrc => {
+            nvmrc::read_nvmrc(&file.path).map(|nvmrc| (nvmrc.spec.clone(), Some(nvmrc)))
+        }
+        _ => file
+            .version_spec()
+            .map(|spec| (spec, None))
+            .map_err(|e| e.in_file(&file.path)),
+    }
+}
+
+impl Resolution {
+    /// Fill in the newest published versions for the spec
+    fn apply_remote(&mut self, spec: &VersionSpec, remote: &RemoteIndex) {
+        self.available_version = remote.resolve(spec).map(|r| r.version.clone());
+
+        let current = match self
+            .resolved_version
+            .as_ref()
+            .or(self.available_version.as_ref())
+        {
+            Some(current) => current.clone(),
+            None => return,
+        };
+        self.newer_version = release::major_of(&current)
+            .and_then(|major| remote.latest_in_major(major))
+            .map(|release| release.version.clone())
+            .filter(|latest| index::is_newer(latest, &current));
+    }
+}
+
+/// Resolve a version specification against the installed versions (newest first)
+///
+/// Aliases and LTS lines are looked up in the nvm directory when one is given.
+pub fn resolve_version(
+    spec: &VersionSpec,
+    installed: &[String],
+    nvm_dir: Option<&Path>,
+) -> Option<String> {
+    let alias = |name: &str| {
+        let target = nvm::read_alias_in(nvm_dir?, name)?;
+        installed
+            .iter()
+            .find(|version| same_version(version, &target))
+            .cloned()
+    };
+
+    match spec {
+        VersionSpec::Latest => installed.first().cloned(),
+        VersionSpec::System => None,
+        VersionSpec::Alias(name) => alias(name),
+        VersionSpec::LtsCodename(name) => alias(&format!("lts/{}", name)),
+        VersionSpec::LtsOffset(0) => alias("lts/*"),
+        VersionSpec::LtsOffset(offset) => {
+            // nvm keeps one alias per LTS line; order them newest first
+            let mut lines: Vec<String> = nvm::list_aliases_in(nvm_dir?)
+                .ok()?
+                .into_iter()
+                .filter(|name| name.starts_with("lts/") && name != "lts/*")
+                .collect();
+            lines.sort_by_cached_key(|name| {
+                std::cmp::Reverse(
+                    nvm_dir
+                        .and_then(|dir| nvm::read_alias_in(dir, name))
+                        .and_then(|target| nvm::parse_version(&target)),
+                )
+            });
+            alias(lines.get(*offset as usize)?)
+        }
+        VersionSpec::Exact { .. } | VersionSpec::Partial { .. } | VersionSpec::Range(_) => {
+            installed
+                .iter()
+                .find(|version| {
+                    nvm::parse_version(version).is_some_and(|version| spec.matches(&version))
+                })
+                .cloned()
+        }
+    }
+}
+
+/// Compare two versions ignoring the optional `v` prefix
+fn same_version(a: &str, b: &str) -> bool {
+    a.trim().trim_start_matches('v') == b.trim().trim_start_matches('v')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn spec(spec: &str) -> VersionSpec {
+        VersionSpec::parse(spec).unwrap()
+    }
+
+    fn installed() -> Vec<String> {
+        vec![
+            "v20.10.0".to_string(),
+            "v18.17.1".to_string(),
+            "v18.17.0".to_string(),
+            "v16.20.2".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_resolve_version_exact_and_partial() {
+        let installed = installed();
+        assert_eq!(
+            resolve_version(&spec("18.17.0"), &installed, None),
+            Some("v18.17.0".to_string())
+        );
+        assert_eq!(
+            resolve_version(&spec("v18"), &installed, None),
+            Some("v18.17.1".to_string())
+        );
+        assert_eq!(
+            resolve_version(&spec("16.20"), &installed, None),
+            Some("v16.20.2".to_string())
+        );
+        assert_eq!(resolve_version(&spec("14"), &installed, None), None);
+        assert_eq!(resolve_version(&spec("1"), &installed, None), None);
+    }
+
+    #[test]
+    fn test_resolve_version_keywords() {
+        let installed = installed();
+        assert_eq!(
+            resolve_version(&spec("node"), &installed, None),
+            Some("v20.10.0".to_string())
+        );
+        assert_eq!(resolve_version(&spec("latest"), &[], None), None);
+
+        let nvm_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(nvm_dir.path().join("alias/lts")).unwrap();
+        fs::write(nvm_dir.path().join("alias/lts/*"), "lts/hydrogen").unwrap();
+        fs::write(nvm_dir.path().join("alias/lts/hydrogen"), "v18.17.1").unwrap();
+        assert_eq!(
+            resolve_version(&spec("lts"), &installed, Some(nvm_dir.path())),
+            Some("v18.17.1".to_string())
+        );
+        assert_eq!(resolve_version(&spec("lts"), &installed, None), None);
+
+        fs::write(nvm_dir.path().join("alias/lts/iron"), "v20.10.0").unwrap();
+        fs::write(nvm_dir.path().join("alias/default"), "lts/iron").unwrap();
+        assert_eq!(
+            resolve_version(&spec("lts/-1"), &installed, Some(nvm_dir.path())),
+            Some("v18.17.1".to_string())
+        );
+        assert_eq!(
+            resolve_version(&spec("default"), &installed, Some(nvm_dir.path())),
+            Some("v20.10.0".to_string())
+        );
+        assert_eq!(
+            resolve_version(&spec(">=18.17.1 <20"), &installed, None),
+            Some("v18.17.1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_dir_active_alias_uses_backend_dir() {
+        let nvm_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(nvm_dir.path().join("versions/node/v20.10.0")).unwrap();
+        fs::create_dir_all(nvm_dir.path().join("alias")).unwrap();
+        fs::write(nvm_dir.path().join("alias/default"), "v20.10.0").unwrap();
+        let project = tempfile::tempdir().unwrap();
+        fs::write(project.path().join(".nvmrc"), "default\n").unwrap();
+
+        let backend = backend::Nvm::with_dir(nvm_dir.path());
+        let resolution =
+            resolve_dir(project.path(), &backend, Some("v20.10.0".to_string()), None).unwrap();
+        assert_eq!(resolution.status, MatchStatus::Match);
+        assert_eq!(resolution.resolved_version.as_deref(), Some("v20.10.0"));
+
+        let resolution = resolve_dir(
+            project.path(),
+            &backend::NvmWindows::with_home(nvm_dir.path()),
+            Some("v20.10.0".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(resolution.status, MatchStatus::NotInstalled);
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let mut resolution = Resolution::new(Backend::Nvm);
+        resolution.version_file = Some(PathBuf::from("/project/.nvmrc"));
+        resolution.source = Some(VersionSource::Nvmrc);
+        resolution.spec = Some(VersionSpec::parse("18").unwrap());
+        resolution.resolved_version = Some("v18.17.1".to_string());
+        resolution.active_version = Some("v20.10.0".to_string());
+        resolution.installed = true;
+        resolution.status = MatchStatus::Mismatch;
+
+        let json = resolution
+            .render(OutputFormat::Json, Some("nvm use 18"))
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["status"], "mismatch");
+        assert_eq!(value["source"], "nvmrc");
+        assert_eq!(value["backend"], "nvm");
+        assert_eq!(value["command"], "nvm use 18");
+
+        let env = resolution.render(OutputFormat::Env, None).unwrap();
+        assert!(env.contains("AUTO_NVM_RESOLVED_VERSION='v18.17.1'"));
+        assert!(env.contains("AUTO_NVM_INSTALLED='true'"));
+        assert!(!env.contains("AUTO_NVM_COMMAND"));
+
+        let plain = resolution.render(OutputFormat::Plain, None).unwrap();
+        assert!(plain.contains("status: mismatch"));
+    }
+
+    #[test]
+    fn test_package_manager_mismatch() {
+        let mut resolution = Resolution::new(Backend::Nvm);
+        assert!(!resolution.package_manager_mismatch());
+
+        resolution.package_manager = Some("pnpm@8.15.1".to_string());
+        assert!(resolution.package_manager_mismatch());
+
+        resolution.package_manager_version = Some("8.15.1".to_string());
+        assert!(!resolution.package_manager_mismatch());
+
+        resolution.package_manager_version = Some("8.6.0".to_string());
+        assert!(resolution.package_manager_mismatch());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("v18"), "'v18'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let statuses = [
+            MatchStatus::Match,
+            MatchStatus::Mismatch,
+            MatchStatus::NotInstalled,
+            MatchStatus::NoVersionFile,
+            MatchStatus::InvalidSpec,
+        ];
+        let mut codes: Vec<i32> = statuses.iter().map(|s| s.exit_code()).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), statuses.len());
+        assert_eq!(MatchStatus::Match.exit_code(), 0);
+    }
+}
@@ -0,0 +1,564 @@
+use crate::inventory::InvalidFile;
+use anyhow::{Context, Result};
+use auto_nvm::nvmrc::range::{self, Version};
+use auto_nvm::nvmrc::version_file::{self, VersionFile};
+use auto_nvm::nvmrc::VersionSource;
+use auto_nvm::release::{self, EolStatus, Schedule};
+use auto_nvm::{nvm, resolve, VersionSpec};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Directories skipped even when no .gitignore excludes them
+const DEFAULT_EXCLUDES: &[&str] = &["node_modules", "bower_components"];
+
+/// Output formats for `scan`
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ScanFormat {
+    /// Aligned columns followed by a summary
+    Table,
+    /// A JSON array with one object per version file
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+/// A problem found in a version file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Issue {
+    /// The file is empty, unreadable or contains an invalid spec
+    InvalidSpec { message: String },
+    /// The required major version is past its end-of-life date
+    EndOfLife { major: u64, date: String },
+    /// The required major version reaches end-of-life soon
+    NearEndOfLife { major: u64, date: String, days: i64 },
+    /// No installed version satisfies the spec
+    NotInstalled,
+    /// The version does not satisfy package.json `engines.node`
+    EnginesMismatch { engines: String },
+}
+
+impl Issue {
+    /// Short human-readable description
+    pub fn describe(&self) -> String {
+        match self {
+            Issue::InvalidSpec { message } => format!("invalid: {}", message),
+            Issue::EndOfLife { major, date } => {
+                format!("Node.js {} reached EOL on {}", major, date)
+            }
+            Issue::NearEndOfLife { major, date, days } => {
+                format!(
+                    "Node.js {} reaches EOL on {} (in {} days)",
+                    major, date, days
+                )
+            }
+            Issue::NotInstalled => "not installed".to_string(),
+            Issue::EnginesMismatch { engines } => format!("outside engines range {}", engines),
+        }
+    }
+}
+
+/// A version file found by `scan` and the problems found in it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanEntry {
+    pub path: PathBuf,
+    pub source: VersionSource,
+    pub spec: Option<String>,
+    pub resolved_version: Option<String>,
+    pub issues: Vec<Issue>,
+}
+
+/// Walks project trees and checks every version file against the installed
+/// versions and the Node.js release schedule
+pub struct Scanner {
+    /// Extra glob patterns to exclude, on top of .gitignore and node_modules
+    pub exclude: Vec<String>,
+    /// Installed versions, newest first
+    pub installed: Vec<String>,
+    pub nvm_dir: Option<PathBuf>,
+    pub schedule: Schedule,
+    /// Date EOL checks are made against (`YYYY-MM-DD`)
+    pub today: String,
+}
+
+impl Scanner {
+    /// Create a scanner for the versions installed in the nvm directory
+    pub fn new(exclude: Vec<String>) -> Result<Self> {
+        Ok(Self {
+            exclude,
+            installed: nvm::list_installed_versions()?,
+            nvm_dir: nvm::nvm_dir(),
+            schedule: Schedule::load()?,
+            today: release::today(),
+        })
+    }
+
+    /// Check every version file below `root`
+    pub fn scan(&self, root: &Path) -> Result<Vec<ScanEntry>> {
+        let mut entries = Vec::new();
+        for dir in walk_dirs(root, &self.exclude)? {
+            entries.extend(self.scan_dir(&dir));
+        }
+        Ok(entries)
+    }
+
+    /// Check the version files of a single directory
+    fn scan_dir(&self, dir: &Path) -> Vec<ScanEntry> {
+        let mut entries: Vec<ScanEntry> = VersionSource::ALL
+            .into_iter()
+            .filter_map(
+                |source| match version_file::read_version_file(dir, source) {
+                    Ok(file) => file.map(|file| self.check_file(file)),
+                    Err(e) => Some(ScanEntry {
+                        path: dir.join(source.file_name()),
+                        source,
+                        spec: None,
+                        resolved_version: None,
+                        issues: vec![Issue::InvalidSpec {
+                            message: e.to_string(),
+                        }],
+                    }),
+                },
+            )
+            .collect();
+
+        let engines = entries
+            .iter()
+            .find(|entry| entry.source == VersionSource::PackageJsonEngines && entry.is_valid())
+            .and_then(|entry| entry.spec.clone());
+
+        if let Some(engines) = engines {
+            for entry in entries
+                .iter_mut()
+                .filter(|entry| entry.source != VersionSource::PackageJsonEngines)
+            {
+                if satisfies_engines(entry, &engines) == Some(false) {
+                    entry.issues.push(Issue::EnginesMismatch {
+                        engines: engines.clone(),
+                    });
+                }
+            }
+        }
+
+        entries
+    }
+
+    fn check_file(&self, file: VersionFile) -> ScanEntry {
+        let parsed = file.version_spec();
+        let mut entry = ScanEntry {
+            path: file.path,
+            source: file.source,
+            spec: Some(file.spec.clone()),
+            resolved_version: None,
+            issues: Vec::new(),
+        };
+
+        let spec = match parsed {
+            // engines only takes npm ranges, not nvm aliases such as `lts/*`
+            Ok(spec)
+                if file.source != VersionSource::PackageJsonEngines
+                    || range::is_valid(&file.spec) =>
+            {
+                spec
+            }
+            Ok(_) => {
+                entry.issues.push(Issue::InvalidSpec {
+                    message: format!("Invalid engines range: {}", file.spec),
+                });
+                return entry;
+            }
+            Err(e) => {
+                entry.issues.push(Issue::InvalidSpec {
+                    message: if file.source == VersionSource::PackageJsonEngines {
+                        format!("Invalid engines range: {}", file.spec)
+                    } else {
+                        e.to_string()
+                    },
+                });
+                return entry;
+            }
+        };
+        entry.resolved_version =
+            resolve::resolve_version(&spec, &self.installed, self.nvm_dir.as_deref());
+
+        if file.source != VersionSource::PackageJsonEngines {
+            let major = entry
+                .resolved_version
+                .as_deref()
+                .and_then(nvm::parse_version)
+                .map(|(major, _, _)| major)
+                .or_else(|| spec.major());
+            if let Some(major) = major {
+                match self.schedule.eol_status(major, &self.today) {
+                    EolStatus::Ended { end } => {
+                        entry.issues.push(Issue::EndOfLife { major, date: end })
+                    }
+                    EolStatus::NearEnd { end, days } => entry.issues.push(Issue::NearEndOfLife {
+                        major,
+                        date: end,
+                        days,
+                    }),
+                    EolStatus::Supported | EolStatus::Unknown => {}
+                }
+            }
+        }
+
+        if entry.resolved_version.is_none() {
+            entry.issues.push(Issue::NotInstalled);
+        }
+
+        entry
+    }
+}
+
+impl ScanEntry {
+    fn is_valid(&self) -> bool {
+        !self
+            .issues
+            .iter()
+            .any(|issue| matches!(issue, Issue::InvalidSpec { .. }))
+    }
+}
+
+/// Whether the version an entry requires satisfies an engines range, or
+/// `None` when that cannot be told (e.g. an uninstalled `lts`)
+fn satisfies_engines(entry: &ScanEntry, engines: &str) -> Option<bool> {
+    if !entry.is_valid() {
+        return None;
+    }
+
+    let candidates: Vec<Version> = match entry.resolved_version.as_deref() {
+        Some(resolved) => nvm::parse_version(resolved).into_iter().collect(),
+        None => spec_bounds(&VersionSpec::parse(entry.spec.as_deref()?).ok()?),
+    };
+    if candidates.is_empty() {
+        return None;
+    }
+
+    // A partial spec agrees if any version it allows is inside the range
+    Some(
+        candidates
+            .into_iter()
+            .any(|version| range::satisfies(version, engines) == Some(true)),
+    )
+}
+
+/// Lowest and highest versions a numeric spec such as `18` or `18.17` allows
+fn spec_bounds(spec: &VersionSpec) -> Vec<Version> {
+    match *spec {
+        VersionSpec::Exact { version, .. } => vec![version],
+        VersionSpec::Partial {
+            major,
+            minor: Some(minor),
+            ..
+        } => vec![(major, minor, 0), (major, minor, u64::MAX)],
+        VersionSpec::Partial {
+            major, minor: None, ..
+        } => vec![(major, 0, 0), (major, u64::MAX, u64::MAX)],
+        _ => Vec::new(),
+    }
+}
+
+/// Every directory below `root` that is not hidden, git-ignored or excluded
+pub fn walk_dirs(root: &Path, exclude: &[String]) -> Result<Vec<PathBuf>> {
+    let mut overrides = OverrideBuilder::new(root);
+    for pattern in DEFAULT_EXCLUDES
+        .iter()
+        .copied()
+        .chain(exclude.iter().map(String::as_str))
+    {
+        overrides
+            .add(&format!("!{}", pattern))
+            .with_context(|| format!("Invalid exclude pattern: {}", pattern))?;
+    }
+
+    let walker = WalkBuilder::new(root)
+        .overrides(overrides.build()?)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+
+    let mut dirs = Vec::new();
+    for entry in walker {
+        let entry = entry.with_context(|| format!("Failed to scan {}", root.display()))?;
+        if entry.file_type().is_some_and(|t| t.is_dir()) {
+            dirs.push(entry.into_path());
+        }
+    }
+
+    Ok(dirs)
+}
+
+/// Find the version files in every directory below the given roots, and the
+/// ones that exist but could not be read (e.g. an empty .nvmrc)
+pub fn find_version_files(roots: &[PathBuf]) -> Result<(Vec<VersionFile>, Vec<InvalidFile>)> {
+    let mut files = Vec::new();
+    let mut invalid = Vec::new();
+    for root in roots {
+        for dir in walk_dirs(root, &[])? {
+            for source in VersionSource::ALL {
+                match version_file::read_version_file(&dir, source) {
+                    Ok(file) => files.extend(file),
+                    Err(e) => invalid.push(InvalidFile {
+                        path: dir.join(source.file_name()),
+                        error: e.to_string(),
+                    }),
+                }
+            }
+        }
+    }
+    Ok((files, invalid))
+}
+
+/// Render scan results in the given format
+pub fn render(entries: &[ScanEntry], format: ScanFormat) -> Result<String> {
+    match format {
+        ScanFormat::Json => {
+            serde_json::to_string_pretty(entries).context("Failed to serialize scan results")
+        }
+        ScanFormat::Csv => {
+            let mut lines = vec!["path,source,spec,resolved_version,issues".to_string()];
+            for entry in entries {
+                let row = row(entry);
+                lines.push(
+                    row.iter()
+                        .map(|field| csv_field(field))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                );
+            }
+            Ok(lines.join("\n"))
+        }
+        ScanFormat::Table => {
+            let header = ["PATH", "SOURCE", "SPEC", "RESOLVED", "ISSUES"].map(str::to_string);
+            let rows: Vec<[String; 5]> = std::iter::once(header)
+                .chain(entries.iter().map(row))
+                .collect();
+
+            let mut widths = [0; 4];
+            for row in &rows {
+                for (width, field) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(field.chars().count());
+                }
+            }
+
+            let mut lines: Vec<String> = rows
+                .iter()
+                .map(|row| {
+                    let mut line = String::new();
+                    for (field, width) in row.iter().zip(widths) {
+                        line.push_str(&format!("{:<width$}  ", field, width = width));
+                    }
+                    line.push_str(&row[4]);
+                    line
+                })
+                .collect();
+
+            let with_issues = entries.iter().filter(|e| !e.issues.is_empty()).count();
+            lines.push(String::new());
+            lines.push(format!(
+                "{} version files, {} with issues",
+                entries.len(),
+                with_issues
+            ));
+            Ok(lines.join("\n"))
+        }
+    }
+}
+
+/// Flat view of an entry shared by the table and CSV formats
+fn row(entry: &ScanEntry) -> [String; 5] {
+    let issues = if entry.issues.is_empty() {
+        "ok".to_string()
+    } else {
+        entry
+            .issues
+            .iter()
+            .map(Issue::describe)
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+
+    [
+        entry.path.display().to_string(),
+        entry.source.as_str().to_string(),
+        entry.spec.clone().unwrap_or_default(),
+        entry.resolved_version.clone().unwrap_or_default(),
+        issues,
+    ]
+}
+
+/// Quote a CSV field when it contains separators, quotes or newlines
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn scanner() -> Scanner {
+        Scanner {
+            exclude: Vec::new(),
+            installed: vec!["v20.10.0".to_string(), "v16.20.2".to_string()],
+            nvm_dir: None,
+            schedule: Schedule::bundled(),
+            today: "2024-06-01".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_walk_dirs_respects_gitignore_and_excludes() -> Result<()> {
+        let root = tempdir()?;
+        for dir in [
+            "app",
+            "build/out",
+            "legacy",
+            "app/node_modules/pkg",
+            ".cache",
+        ] {
+            fs::create_dir_all(root.path().join(dir))?;
+        }
+        fs::write(root.path().join(".gitignore"), "build/\n")?;
+
+        let dirs = walk_dirs(root.path(), &["legacy".to_string()])?;
+        let dirs: Vec<&Path> = dirs
+            .iter()
+            .map(|dir| dir.strip_prefix(root.path()).unwrap())
+            .collect();
+        assert_eq!(dirs, vec![Path::new(""), Path::new("app")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_version_files() -> Result<()> {
+        let root = tempdir()?;
+        let app = root.path().join("apps/web");
+        fs::create_dir_all(&app)?;
+        fs::write(app.join(".nvmrc"), "18\n")?;
+        fs::write(root.path().join(".node-version"), "20\n")?;
+        fs::create_dir_all(root.path().join("empty"))?;
+        fs::write(root.path().join("empty/.nvmrc"), "\n")?;
+
+        let (files, invalid) = find_version_files(&[root.path().to_path_buf()])?;
+        let paths: Vec<&Path> = files.iter().map(|f| f.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                root.path().join(".node-version").as_path(),
+                app.join(".nvmrc").as_path()
+            ]
+        );
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].path, root.path().join("empty/.nvmrc"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_reports_issues() -> Result<()> {
+        let root = tempdir()?;
+        let dirs = ["ok", "eol", "invalid", "two", "missing", "drift"];
+        for dir in dirs {
+            fs::create_dir_all(root.path().join(dir))?;
+        }
+        fs::write(root.path().join("ok/.nvmrc"), "20\n")?;
+        fs::write(
+            root.path().join("ok/package.json"),
+            r#"{"engines": {"node": ">=18"}}"#,
+        )?;
+        fs::write(root.path().join("eol/.nvmrc"), "16\n")?;
+        fs::write(root.path().join("invalid/.nvmrc"), "eighteen\n")?;
+        fs::write(root.path().join("two/.nvmrc"), "18\n20\n")?;
+        fs::write(root.path().join("missing/.node-version"), "18.17.1\n")?;
+        fs::write(root.path().join("drift/.nvmrc"), "20\n")?;
+        fs::write(
+            root.path().join("drift/package.json"),
+            r#"{"engines": {"node": "^18.17.0"}}"#,
+        )?;
+
+        let entries = scanner().scan(root.path())?;
+        let issues = |dir: &str, source: VersionSource| -> Vec<Issue> {
+            entries
+                .iter()
+                .find(|e| e.source == source && e.path.starts_with(root.path().join(dir)))
+                .map(|e| e.issues.clone())
+                .unwrap()
+        };
+
+        assert_eq!(issues("ok", VersionSource::Nvmrc), vec![]);
+        assert_eq!(issues("ok", VersionSource::PackageJsonEngines), vec![]);
+        assert_eq!(
+            issues("eol", VersionSource::Nvmrc),
+            vec![Issue::EndOfLife {
+                major: 16,
+                date: "2023-09-11".to_string()
+            }]
+        );
+        assert!(matches!(
+            issues("invalid", VersionSource::Nvmrc).as_slice(),
+            [Issue::InvalidSpec { .. }]
+        ));
+        assert!(matches!(
+            issues("two", VersionSource::Nvmrc).as_slice(),
+            [Issue::InvalidSpec { message }] if message.contains("Only one Node.js version may be given")
+        ));
+        assert_eq!(
+            issues("missing", VersionSource::NodeVersion),
+            vec![Issue::NotInstalled]
+        );
+        assert_eq!(
+            issues("drift", VersionSource::Nvmrc),
+            vec![Issue::EnginesMismatch {
+                engines: "^18.17.0".to_string()
+            }]
+        );
+        assert_eq!(
+            issues("drift", VersionSource::PackageJsonEngines),
+            vec![Issue::NotInstalled]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spec_bounds_agree_with_engines() {
+        let entry = ScanEntry {
+            path: PathBuf::from(".nvmrc"),
+            source: VersionSource::Nvmrc,
+            spec: Some("18".to_string()),
+            resolved_version: None,
+            issues: vec![Issue::NotInstalled],
+        };
+        assert_eq!(satisfies_engines(&entry, "^18.17.0"), Some(true));
+        assert_eq!(satisfies_engines(&entry, ">=20"), Some(false));
+    }
+
+    #[test]
+    fn test_render_csv_quotes_fields() -> Result<()> {
+        let entries = vec![ScanEntry {
+            path: PathBuf::from("app/package.json"),
+            source: VersionSource::PackageJsonEngines,
+            spec: Some(">=16, <21".to_string()),
+            resolved_version: None,
+            issues: vec![Issue::NotInstalled],
+        }];
+
+        assert_eq!(
+            render(&entries, ScanFormat::Csv)?,
+            "path,source,spec,resolved_version,issues\n\
+             app/package.json,package-json-engines,\">=16, <21\",,not installed"
+        );
+
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
-use crate::nvm::ShellType;
 use anyhow::{anyhow, Context, Result};
+use auto_nvm::nvm::ShellType;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -65,7 +65,8 @@ fn wrap_with_markers(content: &str) -> String {
     )
 }
 
-/// Generate the shell integration script for the given shell type
+/// Generate the shell integration script for the given shell type, loading
+/// the completions when they are installed somewhere the shell does not look
 pub fn generate_integration_script(shell: ShellType) -> String {
     let content = match shell {
         ShellType::Bash => BASH_INTEGRATION,
@@ -74,7 +75,10 @@ pub fn generate_integration_script(shell: ShellType) -> String {
         ShellType::PowerShell => POWERSHELL_INTEGRATION,
     };
 
-    wrap_with_markers(content)
+    match crate::completions::loader(shell) {
+        Some(loader) => wrap_with_markers(&format!("{}\n\n{}", content.trim(), loader)),
+        None => wrap_with_markers(content),
+    }
 }
 
 /// Check if auto-nvm is already configured in the given config file
@@ -89,6 +93,80 @@ pub fn check_already_configured(config_path: &Path) -> bool {
     }
 }
 
+/// Read the auto-nvm block (markers included) from the given config file
+/// Returns None if the file has no complete auto-nvm block
+pub fn read_integration_block(config_path: &Path) -> Result<Option<String>> {
+    if !config_path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(config_path).context("Failed to read config file")?;
+
+    let start = match content.find(AUTO_NVM_START) {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+    let end = match content[start..].find(AUTO_NVM_END) {
+        Some(offset) => start + offset + AUTO_NVM_END.len(),
+        None => return Ok(None),
+    };
+
+    Ok(Some(content[start..end].replace("\r\n", "\n")))
+}
+
+/// Check whether the auto-nvm block in the config file differs from the
+/// script this version of auto-nvm would generate
+pub fn is_integration_outdated(config_path: &Path, shell: ShellType) -> Result<bool> {
+    match read_integration_block(config_path)? {
+        Some(block) => Ok(block.trim() != generate_integration_script(shell).trim()),
+        None => Ok(false),
+    }
+}
+
+/// Replace an outdated auto-nvm block with the current script, keeping a
+/// backup; returns true if the block was replaced
+pub fn refresh_integration(config_path: &Path, shell: ShellType) -> Result<bool> {
+    if !is_integration_outdated(config_path, shell)? {
+        return Ok(false);
+    }
+
+    backup_config_file(config_path)?;
+    replace_integration_block(config_path, &generate_integration_script(shell))?;
+    Ok(true)
+}
+
+/// Replace the auto-nvm block (markers included) with `script` where it
+/// stands, so whatever the user put after it still runs after it; returns
+/// false if the file has no complete block
+pub fn replace_integration_block(config_path: &Path, script: &str) -> Result<bool> {
+    if !config_path.exists() {
+        return Ok(false);
+    }
+
+    let content = fs::read_to_string(config_path).context("Failed to read config file")?;
+
+    let start = match content.find(AUTO_NVM_START) {
+        Some(start) => start,
+        None => return Ok(false),
+    };
+    let end = match content[start..].find(AUTO_NVM_END) {
+        Some(offset) => start + offset + AUTO_NVM_END.len(),
+        None => return Ok(false),
+    };
+
+    // Keep the file's line endings
+    let script = if content.contains("\r\n") {
+        script.replace("\r\n", "\n").replace('\n', "\r\n")
+    } else {
+        script.to_string()
+    };
+    let updated = format!("{}{}{}", &content[..start], script, &content[end..]);
+    fs::write(config_path, updated)
+        .with_context(|| format!("Failed to write config file {}", config_path.display()))?;
+
+    Ok(true)
+}
+
 /// Backup the configuration file with a .backup extension
 pub fn backup_config_file(config_path: &Path) -> Result<PathBuf> {
     if !config_path.exists() {
@@ -230,6 +308,74 @@ mod tests {
         assert!(check_already_configured(path));
     }
 
+    #[test]
+    fn test_is_integration_outdated() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        // No block at all is not considered outdated
+        fs::write(path, "alias ll='ls -la'\n").unwrap();
+        assert!(!is_integration_outdated(path, ShellType::Bash).unwrap());
+
+        // Current script is up to date
+        let script = generate_integration_script(ShellType::Bash);
+        fs::write(path, format!("# My config\n\n{}\n", script)).unwrap();
+        assert!(!is_integration_outdated(path, ShellType::Bash).unwrap());
+
+        // Script for another shell (or an older release) is outdated
+        let script = generate_integration_script(ShellType::Zsh);
+        fs::write(path, script).unwrap();
+        assert!(is_integration_outdated(path, ShellType::Bash).unwrap());
+    }
+
+    #[test]
+    fn test_refresh_integration() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".bashrc");
+        let old = generate_integration_script(ShellType::Bash).replace("pushd", "cd");
+        fs::write(
+            &path,
+            format!(
+                "alias ll='ls -la'\n\n{}\n\neval \"$(starship init bash)\"\n",
+                old
+            ),
+        )
+        .unwrap();
+
+        assert!(refresh_integration(&path, ShellType::Bash).unwrap());
+        let content = fs::read_to_string(&path).unwrap();
+        // Replaced where it was, not moved to the end
+        assert_eq!(
+            content,
+            format!(
+                "alias ll='ls -la'\n\n{}\n\neval \"$(starship init bash)\"\n",
+                generate_integration_script(ShellType::Bash)
+            )
+        );
+        assert!(path.with_extension("backup").exists());
+
+        // Up to date now
+        assert!(!refresh_integration(&path, ShellType::Bash).unwrap());
+    }
+
+    #[test]
+    fn test_replace_integration_block_keeps_crlf() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let crlf = |text: String| text.replace('\n', "\r\n");
+        let old = crlf(format!("# before\n{}\n# after\n", wrap_with_markers("old")));
+        fs::write(path, old).unwrap();
+
+        assert!(replace_integration_block(path, &wrap_with_markers("new")).unwrap());
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            crlf(format!("# before\n{}\n# after\n", wrap_with_markers("new")))
+        );
+
+        fs::write(path, "# no block\n").unwrap();
+        assert!(!replace_integration_block(path, "x").unwrap());
+    }
+
     #[test]
     fn test_remove_integration_from_config() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -0,0 +1,395 @@
+use crate::exec;
+use anyhow::{anyhow, Context, Result};
+use auto_nvm::config::Config;
+use auto_nvm::{nvm, nvmrc, VersionSpec};
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Binaries that are always shimmed, regardless of installed versions
+const CORE_SHIMS: &[&str] = &["node", "npm", "npx", "corepack"];
+
+/// Name of the auto-nvm binary
+const BINARY_NAME: &str = "auto-nvm";
+
+/// File in the shims directory listing the shims `install` created, one file
+/// name per line
+const MANIFEST: &str = ".auto-nvm-shims";
+
+/// Default directory for shims (`AUTO_NVM_SHIMS_DIR`, else the local data dir)
+pub fn default_shims_dir() -> Result<PathBuf> {
+    if let Ok(dir) = std::env::var("AUTO_NVM_SHIMS_DIR") {
+        if !dir.is_empty() {
+            return Ok(PathBuf::from(dir));
+        }
+    }
+
+    let data_dir =
+        dirs::data_local_dir().ok_or_else(|| anyhow!("Could not determine data directory"))?;
+    Ok(data_dir.join("auto-nvm").join("shims"))
+}
+
+/// Return the shim name if the process was invoked through a shim: as
+/// node, npm, npx or corepack, or as a shim listed in the manifest of the
+/// directory it was run from
+///
+/// Any other name (`auto-nvm-0.2`, an `anvm` symlink) runs auto-nvm itself.
+pub fn shim_name(argv0: &OsString) -> Option<String> {
+    let path = Path::new(argv0);
+    let name = path.file_stem()?.to_string_lossy().to_string();
+
+    if name == BINARY_NAME || name.is_empty() {
+        return None;
+    }
+    if CORE_SHIMS.contains(&name.as_str()) {
+        return Some(name);
+    }
+
+    // A bare name was found on PATH
+    let path = if path.parent().is_some_and(|parent| parent != Path::new("")) {
+        path.to_path_buf()
+    } else {
+        which::which(path).ok()?
+    };
+    read_manifest(path.parent()?)
+        .contains(&shim_file_name(&name))
+        .then_some(name)
+}
+
+/// Shim file names recorded in `shims_dir`'s manifest
+fn read_manifest(shims_dir: &Path) -> BTreeSet<String> {
+    fs::read_to_string(shims_dir.join(MANIFEST))
+        .map(|manifest| {
+            manifest
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Run the real binary behind a shim for the current directory
+///
+/// This is the hot path for every shimmed call, so it only reads the version
+/// file and the nvm directory; no shell or nvm function is started.
+pub fn run_shim(name: &str, args: &[OsString]) -> Result<()> {
+    let nvm_dir =
+        nvm::nvm_dir().ok_or_else(|| anyhow!("Could not find the nvm directory; set NVM_DIR"))?;
+    let current_dir = std::env::current_dir()?;
+
+    let spec = resolve_shim_spec(&nvm_dir, &current_dir)?;
+    let installation = exec::find_installation_in(&nvm_dir, &spec)?;
+
+    let program = installation.bin_dir().join(name);
+    if !program.exists() {
+        return Err(anyhow!(
+            "{} is not installed for Node.js {}",
+            name,
+            installation.version
+        ));
+    }
+
+    let envs = exec::build_env(&installation, std::env::var_os("PATH").as_deref())?;
+    exec::exec_command(program.as_os_str(), args, &envs)
+}
+
+/// Version spec for a shim call: nearest version file, then the nvm default
+/// alias
+fn resolve_shim_spec(nvm_dir: &Path, dir: &Path) -> Result<VersionSpec> {
+    if let Some((_, spec)) = nvmrc::find_version_spec_upwards(dir)? {
+        return Ok(spec);
+    }
+
+    let target = nvm::read_alias_in(nvm_dir, "default").ok_or_else(|| {
+        anyhow!(
+            "No version file found in {} or its parents, and no nvm default alias is set",
+            dir.display()
+        )
+    })?;
+    Ok(VersionSpec::parse(&target)?)
+}
+
+/// Names to shim: the core Node.js binaries plus every globally installed bin
+pub fn collect_shim_names(nvm_dir: &Path) -> Result<BTreeSet<String>> {
+    let mut names: BTreeSet<String> = CORE_SHIMS.iter().map(|s| s.to_string()).collect();
+
+    for version in nvm::list_installed_versions_in(nvm_dir)? {
+        let bin_dir = nvm::version_dir(nvm_dir, &version).join("bin");
+        let entries = match fs::read_dir(&bin_dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name != BINARY_NAME {
+                names.insert(name);
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+/// Create or refresh shims in the given directory, all pointing at `target`
+pub fn install_shims(shims_dir: &Path, target: &Path, names: &BTreeSet<String>) -> Result<()> {
+    fs::create_dir_all(shims_dir)
+        .with_context(|| format!("Failed to create directory {}", shims_dir.display()))?;
+
+    // Keep shims from earlier installs listed so `remove` still finds them
+    let mut manifest = read_manifest(shims_dir);
+    manifest.extend(names.iter().map(|name| shim_file_name(name)));
+    let manifest_path = shims_dir.join(MANIFEST);
+    let content: String = manifest.iter().map(|name| format!("{}\n", name)).collect();
+    fs::write(&manifest_path, content)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+
+    for name in names {
+        let shim_path = shims_dir.join(shim_file_name(name));
+
+        if shim_path.symlink_metadata().is_ok() {
+            fs::remove_file(&shim_path)
+                .with_context(|| format!("Failed to replace {}", shim_path.display()))?;
+        }
+
+        create_shim(target, &shim_path)
+            .with_context(|| format!("Failed to create shim {}", shim_path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Remove the shims listed in the directory's manifest
+///
+/// Other files are left alone, and the directory itself is only removed once
+/// it is empty. Returns the number of shims removed.
+pub fn remove_shims(shims_dir: &Path) -> Result<usize> {
+    if !shims_dir.is_dir() {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+    for name in read_manifest(shims_dir) {
+        let path = shims_dir.join(&name);
+        if is_shim(&path) {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+            removed += 1;
+        }
+    }
+    let manifest_path = shims_dir.join(MANIFEST);
+    if manifest_path.exists() {
+        fs::remove_file(&manifest_path)
+            .with_context(|| format!("Failed to remove {}", manifest_path.display()))?;
+    }
+
+    // Only succeeds if nothing else lives in the directory
+    let _ = fs::remove_dir(shims_dir);
+
+    Ok(removed)
+}
+
+/// Check whether a listed file is still a shim created by auto-nvm
+#[cfg(unix)]
+fn is_shim(path: &Path) -> bool {
+    fs::read_link(path)
+        .ok()
+        .and_then(|target| target.file_stem().map(|stem| stem == BINARY_NAME))
+        .unwrap_or(false)
+}
+
+/// Check whether a listed file is still a shim created by auto-nvm
+#[cfg(not(unix))]
+fn is_shim(path: &Path) -> bool {
+    // Shims are links or copies of the binary
+    path.is_file()
+}
+
+fn shim_file_name(name: &str) -> String {
+    if cfg!(windows) {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(unix)]
+fn create_shim(target: &Path, shim_path: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, shim_path)
+}
+
+#[cfg(not(unix))]
+fn create_shim(target: &Path, shim_path: &Path) -> std::io::Result<()> {
+    fs::hard_link(target, shim_path).or_else(|_| fs::copy(target, shim_path).map(|_| ()))
+}
+
+/// Shim manager for `auto-nvm shims`
+pub struct ShimManager {
+    config: Config,
+    shims_dir: PathBuf,
+}
+
+impl ShimManager {
+    pub fn new(config: Config, shims_dir: Option<PathBuf>) -> Result<Self> {
+        let shims_dir = match shims_dir {
+            Some(dir) => dir,
+            None => default_shims_dir()?,
+        };
+
+        Ok(Self { config, shims_dir })
+    }
+
+    /// Create shims for node, npm, npx, corepack and global package binaries
+    pub fn install(&self) -> Result<()> {
+        let nvm_dir = nvm::nvm_dir()
+            .ok_or_else(|| anyhow!("Could not find the nvm directory; set NVM_DIR"))?;
+        let target = std::env::current_exe().context("Failed to locate the auto-nvm binary")?;
+        let names = collect_shim_names(&nvm_dir)?;
+
+        install_shims(&self.shims_dir, &target, &names)?;
+
+        if !self.config.is_quiet() {
+            println!(
+                "Created {} shims in {}",
+                names.len(),
+                self.shims_dir.display()
+            );
+            println!("  {}", names.iter().cloned().collect::<Vec<_>>().join(" "));
+            println!();
+            println!("Add the shims directory to the front of PATH, e.g.:");
+            println!("  export PATH=\"{}:$PATH\"", self.shims_dir.display());
+            println!();
+            println!("Re-run `auto-nvm shims install` after installing global packages.");
+        }
+
+        Ok(())
+    }
+
+    /// Remove the shims created by `install`
+    pub fn remove(&self) -> Result<()> {
+        let removed = remove_shims(&self.shims_dir)?;
+
+        if !self.config.is_quiet() {
+            if removed > 0 {
+                println!(
+                    "Removed {} shims from {}",
+                    removed,
+                    self.shims_dir.display()
+                );
+            } else {
+                println!("No shims found in {}", self.shims_dir.display());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_shim_name() {
+        assert_eq!(shim_name(&OsString::from("/usr/local/bin/auto-nvm")), None);
+        assert_eq!(shim_name(&OsString::from("auto-nvm.exe")), None);
+        assert_eq!(
+            shim_name(&OsString::from(
+                "/home/user/.local/share/auto-nvm/shims/npx"
+            )),
+            Some("npx".to_string())
+        );
+        // Renamed copies and aliases of the binary are not shims
+        assert_eq!(shim_name(&OsString::from("/opt/bin/auto-nvm-0.2")), None);
+        assert_eq!(shim_name(&OsString::from("/usr/local/bin/anvm")), None);
+
+        // Global package shims are found through the manifest
+        let shims_dir = tempdir().unwrap();
+        let names: BTreeSet<String> = ["tsc".to_string()].into();
+        install_shims(
+            shims_dir.path(),
+            Path::new("/usr/local/bin/auto-nvm"),
+            &names,
+        )
+        .unwrap();
+        let tsc = shims_dir.path().join(shim_file_name("tsc"));
+        assert_eq!(shim_name(&tsc.into_os_string()), Some("tsc".to_string()));
+        let other = shims_dir.path().join("eslint");
+        assert_eq!(shim_name(&other.into_os_string()), None);
+    }
+
+    #[test]
+    fn test_collect_shim_names() {
+        let nvm_dir = tempdir().unwrap();
+        let bin_dir = nvm_dir.path().join("versions/node/v20.10.0/bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        for name in ["node", "npm", "tsc", "pnpm"] {
+            fs::write(bin_dir.join(name), "").unwrap();
+        }
+
+        let names = collect_shim_names(nvm_dir.path()).unwrap();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        assert_eq!(names, vec!["corepack", "node", "npm", "npx", "pnpm", "tsc"]);
+    }
+
+    #[test]
+    fn test_resolve_shim_spec() {
+        let nvm_dir = tempdir().unwrap();
+        let project = tempdir().unwrap();
+        let nested = project.path().join("src");
+        fs::create_dir_all(&nested).unwrap();
+
+        assert!(resolve_shim_spec(nvm_dir.path(), &nested).is_err());
+
+        fs::create_dir_all(nvm_dir.path().join("alias")).unwrap();
+        fs::write(nvm_dir.path().join("alias/default"), "v18.17.0").unwrap();
+        assert_eq!(
+            resolve_shim_spec(nvm_dir.path(), &nested)
+                .unwrap()
+                .to_string(),
+            "v18.17.0"
+        );
+
+        fs::write(project.path().join(".nvmrc"), "20\n").unwrap();
+        assert_eq!(
+            resolve_shim_spec(nvm_dir.path(), &nested)
+                .unwrap()
+                .to_string(),
+            "20"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install_and_remove_shims() {
+        let shims_dir = tempdir().unwrap();
+        let shims_path = shims_dir.path().join("shims");
+        let names: BTreeSet<String> = ["node", "npm"].iter().map(|s| s.to_string()).collect();
+
+        install_shims(&shims_path, Path::new("/usr/local/bin/auto-nvm"), &names).unwrap();
+        // Installing twice refreshes existing shims instead of failing
+        install_shims(&shims_path, Path::new("/usr/local/bin/auto-nvm"), &names).unwrap();
+
+        assert_eq!(
+            fs::read_link(shims_path.join("node")).unwrap(),
+            PathBuf::from("/usr/local/bin/auto-nvm")
+        );
+        assert_eq!(remove_shims(&shims_path).unwrap(), 2);
+        assert!(!shims_path.exists());
+        assert_eq!(remove_shims(&shims_path).unwrap(), 0);
+
+        // Unrelated files in a shared directory are kept, even when they
+        // replaced a shim
+        install_shims(&shims_path, Path::new("/usr/local/bin/auto-nvm"), &names).unwrap();
+        fs::write(shims_path.join("other-tool"), "").unwrap();
+        fs::remove_file(shims_path.join("npm")).unwrap();
+        fs::write(shims_path.join("npm"), "").unwrap();
+        assert_eq!(remove_shims(&shims_path).unwrap(), 1);
+        assert!(shims_path.join("other-tool").exists());
+        assert!(shims_path.join("npm").exists());
+        assert!(!shims_path.join(MANIFEST).exists());
+    }
+}
@@ -0,0 +1,280 @@
+use crate::error::{Error, Result};
+use crate::nvmrc::range::{self, Version};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// A parsed Node.js version specification
+///
+/// Every form nvm and package.json accept is a variant, so commands match on
+/// the kind of spec instead of re-parsing strings. `Display` writes the spec
+/// back in the form nvm understands, keeping an explicit `v` prefix.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum VersionSpec {
+    /// A full version, e.g. `18.17.1` or `v18.17.1`
+    Exact { version: Version, prefix: bool },
+    /// A major or major.minor version, e.g. `18` or `v18.17`
+    Partial {
+        major: u64,
+        minor: Option<u64>,
+        prefix: bool,
+    },
+    /// An npm-style range, e.g. `>=18 <21`, `^18.17.0` or `18.x`
+    Range(String),
+    /// The nvm `default` alias
+    Alias(String),
+    /// A named LTS line, e.g. `lts/iron`
+    LtsCodename(String),
+    /// The newest LTS line (`lts`, `lts/*`) or an older one (`lts/-1`)
+    LtsOffset(u32),
+    /// The Node.js installed outside the version manager
+    System,
+    /// The newest release (`node`, `stable`, `latest`)
+    Latest,
+}
+
+impl VersionSpec {
+    /// Parse and validate a version specification
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            return Err(invalid(spec, "Version cannot be empty".to_string()));
+        }
+
+        let lower = spec.to_ascii_lowercase();
+        match lower.as_str() {
+            "node" | "stable" | "latest" => return Ok(Self::Latest),
+            "lts" | "lts/*" => return Ok(Self::LtsOffset(0)),
+            "system" => return Ok(Self::System),
+            "default" => return Ok(Self::Alias(lower)),
+            _ => {}
+        }
+
+        if let Some(name) = lower.strip_prefix("lts/") {
+            if let Some(offset) = name.strip_prefix('-') {
+                return offset
+                    .parse()
+                    .map(Self::LtsOffset)
+                    .map_err(|_| invalid(spec, format!("Invalid LTS offset: {}", spec)));
+            }
+            if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Ok(Self::LtsCodename(name.to_string()));
+            }
+            return Err(invalid(spec, format!("Invalid LTS codename: {}", spec)));
+        }
+
+        let prefix = spec.starts_with('v');
+        let numeric = spec.trim_start_matches('v');
+        if numeric.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            let parts: Option<Vec<u64>> = numeric
+                .split('.')
+                .map(|part| {
+                    // Reject `18.`, `.17` and `+1`-style parts
+                    if part.is_empty() {
+                        None
+                    } else {
+                        part.parse().ok()
+                    }
+                })
+                .collect();
+            return match parts.as_deref() {
+                Some([major, minor, patch]) => Ok(Self::Exact {
+                    version: (*major, *minor, *patch),
+                    prefix,
+                }),
+                Some([major, minor]) => Ok(Self::Partial {
+                    major: *major,
+                    minor: Some(*minor),
+                    prefix,
+                }),
+                Some([major]) => Ok(Self::Partial {
+                    major: *major,
+                    minor: None,
+                    prefix,
+                }),
+                _ => Err(invalid(spec, format!("Invalid version format: {}", spec))),
+            };
+        }
+
+        if range::is_valid(spec) {
+            return Ok(Self::Range(spec.to_string()));
+        }
+
+        Err(invalid(
+            spec,
+            format!(
+                "Invalid version format: {}. Expected formats: 18.17.0, v18, >=18 <21, lts/*, etc.",
+                spec
+            ),
+        ))
+    }
+
+    /// Whether a concrete version satisfies the spec
+    ///
+    /// Aliases, LTS lines, `system` and `node` depend on what the version
+    /// manager knows and never match here; resolve them through a
+    /// [`VersionManager`](crate::VersionManager) instead.
+    pub fn matches(&self, version: &Version) -> bool {
+        match self {
+            Self::Exact { version: exact, .. } => exact == version,
+            Self::Partial { major, minor, .. } => {
+                *major == version.0 && minor.is_none_or(|minor| minor == version.1)
+            }
+            Self::Range(range) => range::satisfies(*version, range).unwrap_or(false),
+            Self::Alias(_)
+            | Self::LtsCodename(_)
+            | Self::LtsOffset(_)
+            | Self::System
+            | Self::Latest => false,
+        }
+    }
+
+    /// Major version the spec is pinned to, for exact and partial versions
+    pub fn major(&self) -> Option<u64> {
+        match self {
+            Self::Exact { version, .. } => Some(version.0),
+            Self::Partial { major, .. } => Some(*major),
+            _ => None,
+        }
+    }
+
+    /// Minor version the spec is pinned to, for exact and major.minor versions
+    pub fn minor(&self) -> Option<u64> {
+        match self {
+            Self::Exact { version, .. } => Some(version.1),
+            Self::Partial { minor, .. } => *minor,
+            _ => None,
+        }
+    }
+
+    /// Whether the spec names a single version
+    pub fn is_exact(&self) -> bool {
+        matches!(self, Self::Exact { .. })
+    }
+}
+
+fn invalid(spec: &str, message: String) -> Error {
+    Error::InvalidSpec {
+        spec: spec.to_string(),
+        message,
+    }
+}
+
+impl FromStr for VersionSpec {
+    type Err = Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        Self::parse(spec)
+    }
+}
+
+impl TryFrom<String> for VersionSpec {
+    type Error = Error;
+
+    fn try_from(spec: String) -> Result<Self> {
+        Self::parse(&spec)
+    }
+}
+
+impl From<VersionSpec> for String {
+    fn from(spec: VersionSpec) -> Self {
+        spec.to_string()
+    }
+}
+
+impl fmt::Display for VersionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = |prefix: &bool| if *prefix { "v" } else { "" };
+        match self {
+            Self::Exact {
+                version: (major, minor, patch),
+                prefix,
+            } => write!(f, "{}{}.{}.{}", v(prefix), major, minor, patch),
+            Self::Partial {
+                major,
+                minor: Some(minor),
+                prefix,
+            } => write!(f, "{}{}.{}", v(prefix), major, minor),
+            Self::Partial {
+                major,
+                minor: None,
+                prefix,
+            } => write!(f, "{}{}", v(prefix), major),
+            Self::Range(range) => f.write_str(range),
+            Self::Alias(name) => f.write_str(name),
+            Self::LtsCodename(name) => write!(f, "lts/{}", name),
+            Self::LtsOffset(0) => f.write_str("lts/*"),
+            Self::LtsOffset(offset) => write!(f, "lts/-{}", offset),
+            Self::System => f.write_str("system"),
+            Self::Latest => f.write_str("node"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let spec: VersionSpec = " v18.17 ".parse().unwrap();
+        assert_eq!(
+            spec,
+            VersionSpec::Partial {
+                major: 18,
+                minor: Some(17),
+                prefix: true
+            }
+        );
+        assert_eq!(spec.to_string(), "v18.17");
+
+        let cases = [
+            ("18.17.0", "18.17.0"),
+            ("v16", "v16"),
+            ("lts", "lts/*"),
+            ("lts/Iron", "lts/iron"),
+            ("lts/-1", "lts/-1"),
+            ("stable", "node"),
+            ("latest", "node"),
+            ("system", "system"),
+            ("default", "default"),
+            (">=18 <21", ">=18 <21"),
+            ("18.x", "18.x"),
+        ];
+        for (input, display) in cases {
+            assert_eq!(VersionSpec::parse(input).unwrap().to_string(), display);
+        }
+
+        for input in ["", "invalid", "18.17.0.1", "18.", ".17", "lts/-x", "lts/"] {
+            assert!(
+                VersionSpec::parse(input).is_err(),
+                "{} should be invalid",
+                input
+            );
+        }
+        let error = "18..1".parse::<VersionSpec>().unwrap_err();
+        assert!(matches!(error, Error::InvalidSpec { ref spec, .. } if spec == "18..1"));
+    }
+
+    #[test]
+    fn test_matches() {
+        let matches = |spec: &str, version| VersionSpec::parse(spec).unwrap().matches(&version);
+        assert!(matches("18.17.1", (18, 17, 1)));
+        assert!(!matches("18.17.1", (18, 17, 0)));
+        assert!(matches("v18", (18, 20, 4)));
+        assert!(matches("18.17", (18, 17, 9)));
+        assert!(!matches("18.17", (18, 18, 0)));
+        assert!(matches("^18.17.0", (18, 19, 0)));
+        assert!(!matches(">=20", (18, 19, 0)));
+        assert!(!matches("lts/*", (22, 0, 0)));
+    }
+
+    #[test]
+    fn test_serde() {
+        let spec: VersionSpec = serde_json::from_str(r#""lts""#).unwrap();
+        assert_eq!(spec, VersionSpec::LtsOffset(0));
+        assert_eq!(serde_json::to_string(&spec).unwrap(), r#""lts/*""#);
+        assert!(serde_json::from_str::<VersionSpec>(r#""not a version""#).is_err());
+    }
+}
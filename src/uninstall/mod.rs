@@ -1,5 +1,5 @@
-use crate::config::Config;
 use anyhow::{anyhow, Context, Result};
+use auto_nvm::config::Config;
 use std::io::{self, Write};
 
 pub mod binary;
@@ -25,6 +25,7 @@ impl UninstallManager {
             println!("  • Binary file (auto-nvm executable)");
             println!("  • PATH entries from shell configuration files");
             println!("  • Shell integration code");
+            println!("  • Shell completions and man pages");
             println!();
             println!("Note: Backup files (.backup) will be preserved for safety.");
             println!();
@@ -59,6 +60,32 @@ impl UninstallManager {
             }
         }
 
+        // Remove completions
+        total_count += 1;
+        if let Err(e) = self.remove_completions() {
+            if !self.config.is_quiet() {
+                println!("⚠ Failed to remove shell completions: {}", e);
+            }
+        } else {
+            success_count += 1;
+            if !self.config.is_quiet() {
+                println!("✓ Removed shell completions");
+            }
+        }
+
+        // Remove man pages
+        total_count += 1;
+        if let Err(e) = self.remove_man_pages() {
+            if !self.config.is_quiet() {
+                println!("⚠ Failed to remove man pages: {}", e);
+            }
+        } else {
+            success_count += 1;
+            if !self.config.is_quiet() {
+                println!("✓ Removed man pages");
+            }
+        }
+
         // Remove binary
         total_count += 1;
         if let Err(e) = self.remove_binary() {
@@ -130,7 +157,7 @@ impl UninstallManager {
     /// Remove shell integration using existing logic
     fn remove_shell_integration(&self) -> Result<()> {
         // Detect user's current shell
-        let shell = crate::nvm::detect_shell();
+        let shell = auto_nvm::nvm::detect_shell();
 
         // Get the config file path for this shell
         let config_path = crate::shell::get_config_file_path(shell)
@@ -152,6 +179,19 @@ impl UninstallManager {
         Ok(())
     }
 
+    /// Remove completion files installed by `setup --completions`
+    fn remove_completions(&self) -> Result<()> {
+        crate::completions::remove_installed()
+    }
+
+    /// Remove man pages installed by `setup`
+    fn remove_man_pages(&self) -> Result<()> {
+        match crate::help::man::default_dir() {
+            Some(dir) => crate::help::man::remove(&dir),
+            None => Ok(()),
+        }
+    }
+
     /// Remove binary file
     fn remove_binary(&self) -> Result<()> {
         binary::remove_auto_nvm_binary()
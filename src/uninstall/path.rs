@@ -91,7 +91,7 @@ fn get_possible_install_dirs() -> Result<Vec<String>> {
 }
 
 /// Get shell configuration files to check for PATH entries
-fn get_shell_config_files() -> Result<Vec<PathBuf>> {
+pub fn get_shell_config_files() -> Result<Vec<PathBuf>> {
     let mut files = Vec::new();
 
     let home_dir =
@@ -184,7 +184,7 @@ fn remove_path_entries_from_file(file_path: &Path, entries: &[PathEntry]) -> Res
 
     // Sort entries by line number in descending order to avoid index shifting
     let mut sorted_entries = entries.to_vec();
-    sorted_entries.sort_by(|a, b| b.line_number.cmp(&a.line_number));
+    sorted_entries.sort_by_key(|entry| std::cmp::Reverse(entry.line_number));
 
     // Remove lines (in reverse order to maintain correct indices)
     for entry in sorted_entries {
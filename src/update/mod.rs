@@ -0,0 +1,412 @@
+use crate::install;
+use anyhow::{anyhow, Context, Result};
+use auto_nvm::{index, log};
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// Where release archives are published (the `releases/` directory of the
+/// repository, served by a CDN)
+pub const DEFAULT_FEED: &str = "https://cdn.jsdelivr.net/gh/zerosrat/auto-nvm@main/releases";
+
+/// `sha256sum` output for every published archive; the release feed
+const CHECKSUMS: &str = "checksums.txt";
+
+/// Detached, ASCII-armoured GPG signature of the checksums
+const SIGNATURE: &str = "checksums.txt.asc";
+
+/// ASCII-armoured public key the release feed is signed with, embedded by the
+/// release build
+const RELEASE_KEY: Option<&str> = option_env!("AUTO_NVM_RELEASE_PUBLIC_KEY");
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Rust target triple the release archives are built for, e.g.
+/// `x86_64-unknown-linux-gnu`
+pub fn target() -> Result<String> {
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x86_64",
+        "aarch64" => "aarch64",
+        other => return Err(anyhow!("No auto-nvm releases are built for {}", other)),
+    };
+    let vendor_os = match std::env::consts::OS {
+        "linux" => "unknown-linux-gnu",
+        "macos" => "apple-darwin",
+        other => return Err(anyhow!("No auto-nvm releases are built for {}", other)),
+    };
+    Ok(format!("{}-{}", arch, vendor_os))
+}
+
+/// An archive listed in the release feed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    /// Version without the `v`, e.g. `0.2.0` or `0.2.0-beta.1`
+    pub version: String,
+    /// File name, e.g. `auto-nvm-v0.2.0-x86_64-apple-darwin.tar.gz`
+    pub archive: String,
+    pub sha256: String,
+}
+
+impl Release {
+    pub fn is_prerelease(&self) -> bool {
+        self.version.contains('-')
+    }
+}
+
+/// The archives for `target` listed in checksums.txt content
+pub fn parse_feed(checksums: &str, target: &str) -> Vec<Release> {
+    let suffix = format!("-{}.tar.gz", target);
+    checksums
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let sha256 = parts.next()?.to_ascii_lowercase();
+            // sha256sum marks binary mode with a leading `*`
+            let archive = parts.next()?.trim_start_matches('*');
+            let version = archive
+                .strip_prefix("auto-nvm-v")?
+                .strip_suffix(suffix.as_str())?;
+            Some(Release {
+                version: version.to_string(),
+                archive: archive.to_string(),
+                sha256,
+            })
+        })
+        .collect()
+}
+
+/// The newest release, skipping prereleases unless `pre` is set
+pub fn latest(releases: &[Release], pre: bool) -> Option<&Release> {
+    releases
+        .iter()
+        .filter(|release| pre || !release.is_prerelease())
+        .max_by(|a, b| compare_versions(&a.version, &b.version))
+}
+
+/// Compare semver versions; a prerelease sorts before its release
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let split = |version: &str| -> (Vec<u64>, Option<String>) {
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre.to_string())),
+            None => (version, None),
+        };
+        let core = core
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect();
+        (core, pre)
+    };
+    let (a_core, a_pre) = split(a);
+    let (b_core, b_pre) = split(b);
+
+    a_core.cmp(&b_core).then_with(|| match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => compare_prerelease(&a, &b),
+    })
+}
+
+/// Compare dot-separated prerelease identifiers, numbers numerically
+fn compare_prerelease(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => {
+                let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    (Ok(_), Err(_)) => Ordering::Less,
+                    (Err(_), Ok(_)) => Ordering::Greater,
+                    (Err(_), Err(_)) => a.cmp(b),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+/// Checks the release feed and replaces the running binary
+pub struct Updater {
+    feed: String,
+    /// Verify the GPG signature of checksums.txt
+    pub verify_signature: bool,
+    /// Public key file to verify it with instead of the embedded release key
+    pub release_key: Option<PathBuf>,
+    pub quiet: bool,
+}
+
+impl Updater {
+    /// Updater for `AUTO_NVM_RELEASE_URL`, else the public feed, signed by
+    /// `AUTO_NVM_RELEASE_KEY`, else the embedded release key
+    pub fn new(quiet: bool) -> Self {
+        let feed = std::env::var("AUTO_NVM_RELEASE_URL")
+            .ok()
+            .filter(|feed| !feed.is_empty())
+            .unwrap_or_else(|| DEFAULT_FEED.to_string());
+        Self {
+            feed: feed.trim_end_matches('/').to_string(),
+            verify_signature: true,
+            release_key: std::env::var_os("AUTO_NVM_RELEASE_KEY")
+                .filter(|key| !key.is_empty())
+                .map(PathBuf::from),
+            quiet,
+        }
+    }
+
+    /// The newest release for this platform and the checksums it came from
+    pub fn check(&self, pre: bool) -> Result<(Option<Release>, Vec<u8>)> {
+        log::info(format!("checking release feed {}", self.feed));
+        let checksums = self.fetch(CHECKSUMS)?;
+        let text = String::from_utf8_lossy(&checksums).to_string();
+        let release = latest(&parse_feed(&text, &target()?), pre).cloned();
+        Ok((release, checksums))
+    }
+
+    /// Download, verify and install `release` over the binary at `exe`
+    pub fn install(&self, release: &Release, checksums: &[u8], exe: &Path) -> Result<()> {
+        let dir = exe
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid binary path {}", exe.display()))?;
+        let staging = dir.join(format!(".auto-nvm-update-{}", std::process::id()));
+        fs::create_dir_all(&staging).with_context(|| {
+            format!(
+                "Cannot write to {} (re-run with the permissions used to install auto-nvm)",
+                dir.display()
+            )
+        })?;
+        let result = self.install_from(release, checksums, exe, &staging);
+        let _ = fs::remove_dir_all(&staging);
+        result
+    }
+
+    fn install_from(
+        &self,
+        release: &Release,
+        checksums: &[u8],
+        exe: &Path,
+        staging: &Path,
+    ) -> Result<()> {
+        if self.verify_signature {
+            self.verify(checksums, staging)?;
+        }
+
+        self.status(&format!("Downloading {}...", release.archive));
+        let archive = staging.join(&release.archive);
+        fs::write(&archive, self.fetch(&release.archive)?)
+            .with_context(|| format!("Failed to write {}", archive.display()))?;
+
+        let actual = install::sha256_file(&archive)?;
+        if actual != release.sha256 {
+            return Err(anyhow!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                release.archive,
+                release.sha256,
+                actual
+            ));
+        }
+        self.status(&format!("Verified SHA-256 of {}", release.archive));
+
+        let binary = staging.join("auto-nvm");
+        extract_binary(&archive, &binary)?;
+        replace(&binary, exe)
+    }
+
+    /// Check checksums.txt against its detached signature with gpg, trusting
+    /// only the release key: it is imported into an empty keyring, so keys in
+    /// the user's keyring are never considered
+    fn verify(&self, checksums: &[u8], staging: &Path) -> Result<()> {
+        which::which("gpg").map_err(|_| {
+            anyhow!("gpg is required to verify the release signature (or pass --skip-signature)")
+        })?;
+
+        let key = match &self.release_key {
+            Some(path) => fs::read(path)
+                .with_context(|| format!("Failed to read release key {}", path.display()))?,
+            None => RELEASE_KEY
+                .ok_or_else(|| {
+                    anyhow!(
+                        "This build has no release key; set AUTO_NVM_RELEASE_KEY to the release public key (or pass --skip-signature)"
+                    )
+                })?
+                .as_bytes()
+                .to_vec(),
+        };
+        let home = staging.join("gnupg");
+        fs::create_dir_all(&home)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&home, fs::Permissions::from_mode(0o700))?;
+        }
+        let key_path = staging.join("release-key.asc");
+        let checksums_path = staging.join(CHECKSUMS);
+        let signature_path = staging.join(SIGNATURE);
+        fs::write(&key_path, key)?;
+        fs::write(&checksums_path, checksums)?;
+        fs::write(&signature_path, self.fetch(SIGNATURE)?)?;
+
+        let import = gpg(&home)
+            .arg("--import")
+            .arg(&key_path)
+            .output()
+            .context("Failed to run gpg")?;
+        if !import.status.success() {
+            return Err(anyhow!(
+                "Failed to import the release key:\n{}",
+                String::from_utf8_lossy(&import.stderr).trim()
+            ));
+        }
+
+        let output = gpg(&home)
+            .args(["--status-fd", "1", "--verify"])
+            .arg(&signature_path)
+            .arg(&checksums_path)
+            .output()
+            .context("Failed to run gpg")?;
+        let valid = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| line.starts_with("[GNUPG:] VALIDSIG "));
+        if !output.status.success() || !valid {
+            return Err(anyhow!(
+                "GPG signature verification of {} failed (it is not signed by the auto-nvm release key):\n{}",
+                CHECKSUMS,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        self.status(&format!("Verified GPG signature of {}", CHECKSUMS));
+        Ok(())
+    }
+
+    /// A file from the feed; `file://` feeds are read from disk
+    fn fetch(&self, name: &str) -> Result<Vec<u8>> {
+        let url = format!("{}/{}", self.feed, name);
+        if let Some(path) = url.strip_prefix("file://") {
+            return fs::read(path).with_context(|| format!("Failed to read {}", path));
+        }
+
+        let response = ureq::AgentBuilder::new()
+            .timeout(TIMEOUT)
+            .user_agent(index::USER_AGENT)
+            .build()
+            .get(&url)
+            .call()
+            .with_context(|| format!("Failed to fetch {}", url))?;
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut body)
+            .with_context(|| format!("Failed to read {}", url))?;
+        Ok(body)
+    }
+
+    fn status(&self, message: &str) {
+        if !self.quiet {
+            eprintln!("{}", message);
+        }
+    }
+}
+
+/// gpg using the keyring in `home` only
+fn gpg(home: &Path) -> Command {
+    let mut command = Command::new("gpg");
+    command.arg("--homedir").arg(home).arg("--batch");
+    command
+}
+
+/// Write the `auto-nvm` binary from a release archive to `dest`
+fn extract_binary(archive: &Path, dest: &Path) -> Result<()> {
+    let file =
+        File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?;
+    let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(file));
+
+    for entry in tar.entries().context("Failed to read archive")? {
+        let mut entry = entry.context("Failed to read archive")?;
+        let is_binary = entry
+            .path()
+            .ok()
+            .and_then(|path| path.file_name().map(|name| name == "auto-nvm"))
+            .unwrap_or(false);
+        if is_binary && entry.header().entry_type().is_file() {
+            let mut out = File::create(dest)
+                .with_context(|| format!("Failed to write {}", dest.display()))?;
+            std::io::copy(&mut entry, &mut out)
+                .with_context(|| format!("Failed to extract {}", dest.display()))?;
+            return Ok(());
+        }
+    }
+
+    Err(anyhow!("{} does not contain auto-nvm", archive.display()))
+}
+
+/// Move `new` over `exe` in one rename, so the binary is never missing or
+/// half-written; `new` must be on the same filesystem
+fn replace(new: &Path, exe: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(new, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {} executable", new.display()))?;
+    }
+    fs::rename(new, exe).with_context(|| format!("Failed to replace {}", exe.display()))
+}
+
+/// The running binary, with symlinks resolved so the real file is replaced
+pub fn current_exe() -> Result<PathBuf> {
+    let exe = std::env::current_exe().context("Failed to locate the auto-nvm binary")?;
+    Ok(fs::canonicalize(&exe).unwrap_or(exe))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_feed() {
+        let checksums = "\
+aa11  auto-nvm-v0.1.0-x86_64-unknown-linux-gnu.tar.gz
+BB22 *auto-nvm-v0.2.0-x86_64-unknown-linux-gnu.tar.gz
+cc33  auto-nvm-v0.2.0-aarch64-apple-darwin.tar.gz
+dd44  auto-nvm-v0.3.0-beta.1-x86_64-unknown-linux-gnu.tar.gz
+ee55  install.sh
+";
+        let releases = parse_feed(checksums, "x86_64-unknown-linux-gnu");
+        assert_eq!(releases.len(), 3);
+        assert_eq!(releases[1].version, "0.2.0");
+        assert_eq!(releases[1].sha256, "bb22");
+        assert_eq!(
+            releases[1].archive,
+            "auto-nvm-v0.2.0-x86_64-unknown-linux-gnu.tar.gz"
+        );
+
+        assert_eq!(latest(&releases, false).unwrap().version, "0.2.0");
+        assert_eq!(latest(&releases, true).unwrap().version, "0.3.0-beta.1");
+        assert!(latest(&[], false).is_none());
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("0.10.0", "0.9.1"), Ordering::Greater);
+        assert_eq!(
+            compare_versions("0.1.0", "0.1.0-alpha.5"),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_versions("0.1.0-alpha.10", "0.1.0-alpha.9"),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_versions("0.1.0-alpha", "0.1.0-beta"),
+            Ordering::Less
+        );
+        assert_eq!(compare_versions("1.2.3", "1.2.3"), Ordering::Equal);
+    }
+}
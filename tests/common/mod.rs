@@ -0,0 +1,199 @@
+//! Test fixtures shared by the integration tests
+//!
+//! [`FakeNvm`] builds an `$NVM_DIR` with fake `node` binaries, aliases and an
+//! `nvm` shell function, so tests do not depend on the Node.js versions (or
+//! the nvm) installed on the machine running them.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// `nvm` for bash and zsh: `nvm use` resolves aliases and version prefixes
+/// against `versions/node` and puts that version's bin dir first on PATH
+const NVM_SH: &str = r#"# Fake nvm for auto-nvm tests
+__FAKE_NVM_PATH="$PATH"
+
+__fake_nvm_resolve() {
+    __fake_nvm_v="$1"
+    while [ -f "$NVM_DIR/alias/$__fake_nvm_v" ]; do
+        __fake_nvm_v=$(cat "$NVM_DIR/alias/$__fake_nvm_v")
+    done
+    __fake_nvm_v=${__fake_nvm_v#v}
+    for __fake_nvm_dir in $(ls "$NVM_DIR/versions/node" | sed 's/^v//' | sort -t. -k1,1nr -k2,2nr -k3,3nr); do
+        case "$__fake_nvm_dir" in
+            "$__fake_nvm_v" | "$__fake_nvm_v".*)
+                echo "v$__fake_nvm_dir"
+                return 0
+                ;;
+        esac
+    done
+    return 1
+}
+
+nvm() {
+    case "$1" in
+        use)
+            shift
+            [ "$1" = "--silent" ] && shift
+            __fake_nvm_version=$(__fake_nvm_resolve "$1") || {
+                echo "N/A: version \"$1\" is not yet installed." >&2
+                return 3
+            }
+            export NVM_BIN="$NVM_DIR/versions/node/$__fake_nvm_version/bin"
+            export PATH="$NVM_BIN:$__FAKE_NVM_PATH"
+            echo "Now using node $__fake_nvm_version"
+            ;;
+        current)
+            node --version
+            ;;
+        *)
+            echo "fake nvm: unsupported command: $*" >&2
+            return 1
+            ;;
+    esac
+}
+"#;
+
+/// The same `nvm` for fish
+const NVM_FISH: &str = r#"# Fake nvm for auto-nvm tests
+set -g __fake_nvm_path $PATH
+
+function __fake_nvm_resolve
+    set -l v $argv[1]
+    while test -f "$NVM_DIR/alias/$v"
+        set v (cat "$NVM_DIR/alias/$v")
+    end
+    set v (string replace -r '^v' '' -- $v)
+    for dir in (ls "$NVM_DIR/versions/node" | sed 's/^v//' | sort -t. -k1,1nr -k2,2nr -k3,3nr)
+        if test "$dir" = "$v"; or string match -q -- "$v.*" $dir
+            echo "v$dir"
+            return 0
+        end
+    end
+    return 1
+end
+
+function nvm
+    switch $argv[1]
+        case use
+            set -e argv[1]
+            test "$argv[1]" = --silent; and set -e argv[1]
+            set -l resolved (__fake_nvm_resolve $argv[1]); or begin
+                echo "N/A: version \"$argv[1]\" is not yet installed." >&2
+                return 3
+            end
+            set -gx NVM_BIN "$NVM_DIR/versions/node/$resolved/bin"
+            set -gx PATH $NVM_BIN $__fake_nvm_path
+            echo "Now using node $resolved"
+        case current
+            node --version
+        case '*'
+            echo "fake nvm: unsupported command: $argv" >&2
+            return 1
+    end
+end
+"#;
+
+/// A fake nvm installation in a temporary directory
+pub struct FakeNvm {
+    dir: TempDir,
+}
+
+impl FakeNvm {
+    /// nvm with the given versions (`v22.11.0`) installed
+    pub fn new(versions: &[&str]) -> Self {
+        let fake = Self {
+            dir: TempDir::new().expect("Failed to create temp dir"),
+        };
+        for version in versions {
+            fake.install(version);
+        }
+        fs::write(fake.path().join("nvm.sh"), NVM_SH).expect("Failed to write nvm.sh");
+        fs::write(fake.path().join("nvm.fish"), NVM_FISH).expect("Failed to write nvm.fish");
+        fake
+    }
+
+    /// `$NVM_DIR`
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Install a version with a `node` that reports it
+    pub fn install(&self, version: &str) {
+        let bin = self.bin_dir(version);
+        fs::create_dir_all(&bin).expect("Failed to create version dir");
+        write_executable(&bin.join("node"), &format!("#!/bin/sh\necho {}\n", version));
+    }
+
+    /// Point an alias (`default`, `lts/*`, `lts/iron`) at a version or alias
+    pub fn alias(&self, name: &str, target: &str) {
+        let file = self.path().join("alias").join(name);
+        fs::create_dir_all(file.parent().unwrap()).expect("Failed to create alias dir");
+        fs::write(file, format!("{}\n", target)).expect("Failed to write alias");
+    }
+
+    /// Directory holding `node` for an installed version
+    pub fn bin_dir(&self, version: &str) -> PathBuf {
+        self.path().join("versions/node").join(version).join("bin")
+    }
+}
+
+/// Write a file and make it executable
+pub fn write_executable(path: &Path, content: &str) {
+    fs::write(path, content).expect("Failed to write executable");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .expect("Failed to make file executable");
+    }
+}
+
+/// A directory holding only the auto-nvm binary under test, for PATH
+pub fn auto_nvm_bin_dir() -> TempDir {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+    let name = if cfg!(windows) {
+        "auto-nvm.exe"
+    } else {
+        "auto-nvm"
+    };
+    fs::copy(env!("CARGO_BIN_EXE_auto-nvm"), dir.path().join(name))
+        .expect("Failed to copy auto-nvm");
+    dir
+}
+
+/// PATH made of the given directories followed by the system directories
+/// that hold `sh`, `ls`, `sort` and friends
+pub fn path_with(dirs: &[&Path]) -> std::ffi::OsString {
+    let system = ["/usr/local/bin", "/usr/bin", "/bin"].map(PathBuf::from);
+    std::env::join_paths(dirs.iter().map(|dir| dir.to_path_buf()).chain(system))
+        .expect("Failed to join PATH")
+}
+
+/// Run `script` in a non-interactive `shell` (`bash`, `zsh`, `fish`, `pwsh`)
+/// with the given environment, or `None` if the shell is not installed
+pub fn run_shell(shell: &str, script: &str, envs: &[(&str, &std::ffi::OsStr)]) -> Option<Output> {
+    let program = which_shell(shell)?;
+    let mut command = Command::new(program);
+    match shell {
+        "bash" => command.args(["--noprofile", "--norc", "-c", script]),
+        "zsh" => command.args(["-f", "-c", script]),
+        "pwsh" => command.args(["-NoProfile", "-NonInteractive", "-Command", script]),
+        _ => command.args(["--no-config", "-c", script]),
+    };
+    command.env_clear();
+    for (key, value) in envs {
+        command.env(key, value);
+    }
+    Some(command.output().expect("Failed to run shell"))
+}
+
+fn which_shell(shell: &str) -> Option<PathBuf> {
+    ["/usr/local/bin", "/usr/bin", "/bin", "/opt/homebrew/bin"]
+        .iter()
+        .map(|dir| Path::new(dir).join(shell))
+        .find(|path| path.is_file())
+}
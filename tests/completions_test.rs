@@ -0,0 +1,199 @@
+//! Install completions with `auto-nvm setup --completions` and ask real
+//! shells to complete installed versions against a fake nvm
+//!
+//! Shells that are not installed are skipped.
+
+mod common;
+
+use common::{auto_nvm_bin_dir, path_with, run_shell, FakeNvm};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+const BASH_SCRIPT: &str = r#"
+. "$COMPLETIONS"
+COMP_WORDS=(auto-nvm pin ""); COMP_CWORD=2
+_auto_nvm_dynamic auto-nvm "" pin; echo "pin: ${COMPREPLY[*]}"
+COMP_WORDS=(auto-nvm --quiet exec --version v22); COMP_CWORD=4
+_auto_nvm_dynamic auto-nvm v22 --version; echo "exec: ${COMPREPLY[*]}"
+COMP_WORDS=(auto-nvm pin --ex); COMP_CWORD=2
+_auto_nvm_dynamic auto-nvm --ex pin; echo "flags: ${COMPREPLY[*]}"
+"#;
+
+/// compinit runs before the auto-nvm block, as in most ~/.zshrc files
+const ZSH_SCRIPT: &str = r#"
+autoload -Uz compinit && compinit -u -d "$HOME/.zcompdump"
+source "$HOME/.zshrc"
+echo "comp: ${_comps[auto-nvm]}"
+"#;
+
+const FISH_SCRIPT: &str = r#"
+source $COMPLETIONS
+echo "pin: "(complete -C "auto-nvm pin " | string join ' ')
+echo "exec: "(complete -C "auto-nvm exec --version v22" | string join ' ')
+"#;
+
+/// Run `auto-nvm setup --completions` for `shell` in `home`, returning the
+/// installed completion file
+fn install_completions(shell: &str, home: &Path, path: &OsStr) -> PathBuf {
+    let output = Command::new(env!("CARGO_BIN_EXE_auto-nvm"))
+        .args(["--quiet", "setup", "--completions"])
+        .env("HOME", home)
+        .env("XDG_DATA_HOME", home.join(".local/share"))
+        .env("SHELL", format!("/bin/{}", shell))
+        .env("PATH", path)
+        .output()
+        .expect("Failed to run auto-nvm setup");
+    assert!(output.status.success(), "{:?}", output);
+
+    let file = match shell {
+        "bash" => home.join(".local/share/bash-completion/completions/auto-nvm"),
+        "zsh" => home.join(".local/share/zsh/site-functions/_auto-nvm"),
+        _ => home.join(".config/fish/completions/auto-nvm.fish"),
+    };
+    assert!(file.is_file(), "{} not installed", file.display());
+    file
+}
+
+fn complete(shell: &str, script: &str) -> Option<String> {
+    let nvm = FakeNvm::new(&["v20.18.0", "v22.11.0"]);
+    nvm.alias("default", "v22.11.0");
+    let home = TempDir::new().expect("Failed to create temp dir");
+    let bin = auto_nvm_bin_dir();
+    let path = path_with(&[bin.path()]);
+    let completions = install_completions(shell, home.path(), &path);
+
+    let output = run_shell(
+        shell,
+        script,
+        &[
+            ("HOME", home.path().as_os_str()),
+            ("PATH", &path),
+            ("NVM_DIR", nvm.path().as_os_str()),
+            ("COMPLETIONS", completions.as_os_str()),
+        ],
+    )?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    assert!(
+        output.status.success(),
+        "{} failed:\n{}\n{}",
+        shell,
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Some(stdout)
+}
+
+/// The words completed after `label: `
+fn completed<'a>(stdout: &'a str, label: &str) -> Vec<&'a str> {
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix(label)?.strip_prefix(": "))
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_bash_completes_versions() {
+    let Some(stdout) = complete("bash", BASH_SCRIPT) else {
+        eprintln!("bash not installed, skipping");
+        return;
+    };
+    assert_eq!(
+        completed(&stdout, "pin"),
+        ["v22.11.0", "v20.18.0", "default"]
+    );
+    assert_eq!(completed(&stdout, "exec"), ["v22.11.0"]);
+    assert!(
+        completed(&stdout, "flags").contains(&"--exact"),
+        "{}",
+        stdout
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_fish_completes_versions() {
+    let Some(stdout) = complete("fish", FISH_SCRIPT) else {
+        eprintln!("fish not installed, skipping");
+        return;
+    };
+    let pin = completed(&stdout, "pin");
+    assert!(
+        pin.contains(&"v20.18.0") && pin.contains(&"default"),
+        "{}",
+        stdout
+    );
+    assert_eq!(completed(&stdout, "exec"), ["--version=v22.11.0"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_zsh_block_loads_completions() {
+    let home = TempDir::new().expect("Failed to create temp dir");
+    let bin = auto_nvm_bin_dir();
+    let path = path_with(&[bin.path()]);
+    let setup = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_auto-nvm"))
+            .args(args)
+            .env_clear()
+            .env("HOME", home.path())
+            .env("SHELL", "/bin/zsh")
+            .env("PATH", &path)
+            .output()
+            .expect("Failed to run auto-nvm setup");
+        assert!(output.status.success(), "{:?}", output);
+    };
+
+    // A block written before the completions were installed gets the
+    // loading lines in place
+    let zshrc = home.path().join(".zshrc");
+    std::fs::write(&zshrc, "export EDITOR=vi\n").unwrap();
+    setup(&["--quiet", "setup"]);
+    std::fs::write(
+        &zshrc,
+        std::fs::read_to_string(&zshrc).unwrap() + "\nPROMPT='> '\n",
+    )
+    .unwrap();
+    let completions = install_completions("zsh", home.path(), &path);
+    let content = std::fs::read_to_string(&zshrc).unwrap();
+    let fpath = format!(
+        "fpath=('{}' $fpath)",
+        completions.parent().unwrap().display()
+    );
+    assert!(content.contains(&fpath), "{}", content);
+    assert!(content.find(&fpath) < content.find("# === AUTO-NVM END ==="));
+    assert!(content.ends_with("\nPROMPT='> '\n"), "{}", content);
+
+    let Some(output) = run_shell(
+        "zsh",
+        ZSH_SCRIPT,
+        &[("HOME", home.path().as_os_str()), ("PATH", &path)],
+    ) else {
+        eprintln!("zsh not installed, skipping");
+        return;
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        completed(&stdout, "comp"),
+        ["_auto-nvm"],
+        "{}\n{}",
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_completions_command() {
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_auto-nvm"))
+            .args(["completions", shell])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}: {:?}", shell, output);
+        assert!(String::from_utf8_lossy(&output.stdout).contains("auto-nvm"));
+    }
+}
@@ -1,19 +1,57 @@
+mod common;
+
+use common::{auto_nvm_bin_dir, path_with, FakeNvm};
 use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
 use std::path::Path;
 use std::process::Command;
+use std::sync::{Arc, Mutex};
 use tempfile::TempDir;
 
 /// Helper function to run auto-nvm command in a specific directory
 fn run_auto_nvm_in_dir(dir: &Path, args: &[&str]) -> std::process::Output {
+    run_auto_nvm_with_env(dir, args, &[])
+}
+
+/// Helper function to run auto-nvm with only the given environment variables,
+/// on top of an empty HOME and nvm and a PATH of the system directories
+fn run_auto_nvm_with_env(
+    dir: &Path,
+    args: &[&str],
+    envs: &[(&str, &Path)],
+) -> std::process::Output {
     let binary_path = env!("CARGO_BIN_EXE_auto-nvm");
+    let home = TempDir::new().expect("Failed to create temp dir");
 
     Command::new(binary_path)
         .args(args)
         .current_dir(dir)
+        .env_clear()
+        .env("HOME", home.path())
+        .env("NVM_DIR", home.path().join(".nvm"))
+        .env("PATH", path_with(&[]))
+        .envs(envs.iter().map(|(k, v)| (*k, *v)))
         .output()
         .expect("Failed to execute auto-nvm command")
 }
 
+/// Helper function to run auto-nvm against a fake nvm whose `active` version
+/// is the `node` on PATH
+fn run_auto_nvm_with_nvm(
+    dir: &Path,
+    args: &[&str],
+    nvm: &FakeNvm,
+    active: &str,
+) -> std::process::Output {
+    let path = path_with(&[&nvm.bin_dir(active)]);
+    run_auto_nvm_with_env(
+        dir,
+        args,
+        &[("NVM_DIR", nvm.path()), ("PATH", Path::new(&path))],
+    )
+}
+
 /// Helper function to create a temporary directory with .nvmrc
 fn create_temp_dir_with_nvmrc(content: &str) -> TempDir {
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -25,22 +63,28 @@ fn create_temp_dir_with_nvmrc(content: &str) -> TempDir {
 #[test]
 fn test_check_command_with_valid_semantic_version() {
     let temp_dir = create_temp_dir_with_nvmrc("18.17.0");
-    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check"]);
+    let nvm = FakeNvm::new(&["v18.17.0", "v20.18.0"]);
+    let output = run_auto_nvm_with_nvm(temp_dir.path(), &["check"], &nvm, "v18.17.0");
 
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("Found .nvmrc with version: 18.17.0"));
-    assert!(stdout.contains("Current Node.js version:"));
+    assert!(stdout.contains("Current Node.js version: v18.17.0"));
+    assert!(stdout.contains("✓ Version matches requirement"));
 }
 
 #[test]
 fn test_check_command_with_lts_version() {
     let temp_dir = create_temp_dir_with_nvmrc("lts");
-    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check"]);
+    let nvm = FakeNvm::new(&["v20.18.0", "v22.11.0"]);
+    nvm.alias("lts/*", "lts/iron");
+    nvm.alias("lts/iron", "v20.18.0");
+    let output = run_auto_nvm_with_nvm(temp_dir.path(), &["check"], &nvm, "v20.18.0");
 
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("Found .nvmrc with version: lts"));
+    assert!(stdout.contains("✓ Version matches requirement"));
 }
 
 #[test]
@@ -60,43 +104,49 @@ fn test_check_command_no_nvmrc() {
 
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
-    assert!(stdout.contains("No .nvmrc file found"));
+    assert!(stdout.contains("No version file found"));
 }
 
 #[test]
 fn test_switch_command_with_valid_version() {
     let temp_dir = create_temp_dir_with_nvmrc("18.17.0");
-    let output = run_auto_nvm_in_dir(temp_dir.path(), &["switch"]);
+    let nvm = FakeNvm::new(&["v18.17.0", "v20.18.0"]);
+    let output = run_auto_nvm_with_nvm(temp_dir.path(), &["switch"], &nvm, "v20.18.0");
 
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
-    assert!(stdout.contains("nvm use 18.17.0"));
+    assert_eq!(stdout, "nvm use 18.17.0\n");
 }
 
 #[test]
 fn test_switch_command_with_lts() {
     let temp_dir = create_temp_dir_with_nvmrc("lts");
-    let output = run_auto_nvm_in_dir(temp_dir.path(), &["switch"]);
+    let nvm = FakeNvm::new(&["v20.18.0"]);
+    nvm.alias("lts/*", "v20.18.0");
+    let output = run_auto_nvm_with_nvm(temp_dir.path(), &["switch"], &nvm, "v20.18.0");
 
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
-    assert!(stdout.contains("nvm use lts"));
+    assert_eq!(stdout, "nvm use 'lts/*'\n");
 }
 
 #[test]
 fn test_switch_command_no_nvmrc() {
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
-    let output = run_auto_nvm_in_dir(temp_dir.path(), &["switch"]);
+    let nvm = FakeNvm::new(&["v20.18.0"]);
+    let output = run_auto_nvm_with_nvm(temp_dir.path(), &["switch"], &nvm, "v20.18.0");
 
     assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
     let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("No .nvmrc file found"));
+    assert!(stderr.contains("No version file found"));
 }
 
 #[test]
 fn test_quiet_flag() {
     let temp_dir = create_temp_dir_with_nvmrc("18.17.0");
-    let output = run_auto_nvm_in_dir(temp_dir.path(), &["--quiet", "check"]);
+    let nvm = FakeNvm::new(&["v18.17.0"]);
+    let output = run_auto_nvm_with_nvm(temp_dir.path(), &["--quiet", "check"], &nvm, "v18.17.0");
 
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -108,7 +158,8 @@ fn test_quiet_flag() {
 fn test_version_parsing_edge_cases() {
     // Test with whitespace
     let temp_dir = create_temp_dir_with_nvmrc("  18.17.0  \n");
-    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check"]);
+    let nvm = FakeNvm::new(&["v18.17.0"]);
+    let output = run_auto_nvm_with_nvm(temp_dir.path(), &["check"], &nvm, "v18.17.0");
 
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
@@ -118,9 +169,1591 @@ fn test_version_parsing_edge_cases() {
 #[test]
 fn test_v_prefix_version() {
     let temp_dir = create_temp_dir_with_nvmrc("v16.14.0");
-    let output = run_auto_nvm_in_dir(temp_dir.path(), &["switch"]);
+    let nvm = FakeNvm::new(&["v16.14.0"]);
+    let output = run_auto_nvm_with_nvm(temp_dir.path(), &["switch"], &nvm, "v16.14.0");
 
     assert!(output.status.success());
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("nvm use v16.14.0"));
 }
+
+#[test]
+fn test_doctor_json_output() {
+    let temp_dir = create_temp_dir_with_nvmrc("18.17.0");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["doctor", "--json"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let results: serde_json::Value =
+        serde_json::from_str(&stdout).expect("doctor --json should print valid JSON");
+    let results = results
+        .as_array()
+        .expect("doctor --json should print an array");
+
+    let nvmrc_check = results
+        .iter()
+        .find(|r| r["name"] == "nvmrc")
+        .expect("doctor should check the .nvmrc");
+    assert_eq!(nvmrc_check["status"], "pass");
+
+    // Exit status reflects whether any check failed
+    let any_failed = results.iter().any(|r| r["status"] == "fail");
+    assert_eq!(output.status.success(), !any_failed);
+}
+
+#[test]
+fn test_check_format_json_installed_version() {
+    let temp_dir = create_temp_dir_with_nvmrc("18");
+    let nvm = FakeNvm::new(&["v18.17.0", "v18.19.1", "v20.10.0"]);
+    let output = run_auto_nvm_with_nvm(
+        temp_dir.path(),
+        &["check", "--format", "json"],
+        &nvm,
+        "v20.10.0",
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let result: serde_json::Value = serde_json::from_str(&stdout).expect("valid JSON");
+    assert_eq!(result["source"], "nvmrc");
+    assert_eq!(result["spec"], "18");
+    assert_eq!(result["resolved_version"], "v18.19.1");
+    assert_eq!(result["installed"], true);
+    assert_eq!(result["backend"], "nvm");
+    assert_eq!(result["status"], "mismatch");
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn test_check_format_exit_codes() {
+    let nvm_dir = FakeNvm::new(&["v20.10.0"]);
+
+    let temp_dir = create_temp_dir_with_nvmrc("16.20.2");
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["check", "--format", "plain"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("status: not-installed"));
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(5));
+
+    let temp_dir = create_temp_dir_with_nvmrc("not-a-valid-version");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check", "--format", "env"]);
+    assert_eq!(output.status.code(), Some(6));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("AUTO_NVM_STATUS='invalid-spec'"));
+}
+
+#[test]
+fn test_switch_format_json_includes_command() {
+    let temp_dir = create_temp_dir_with_nvmrc("v16.14.0");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["switch", "--format", "json"]);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let result: serde_json::Value = serde_json::from_str(&stdout).expect("valid JSON");
+    assert_eq!(result["command"], "nvm use v16.14.0");
+}
+
+#[test]
+fn test_switch_resolves_ranges_and_lts_aliases() {
+    let nvm_dir = FakeNvm::new(&["v22.11.0", "v24.10.0"]);
+    let envs = [("NVM_DIR", nvm_dir.path())];
+
+    // nvm cannot use a range itself, so the installed match is emitted
+    let temp_dir = create_temp_dir_with_nvmrc(">=22 <24");
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["switch"], &envs);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "nvm use v22.11.0\n"
+    );
+
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["check", "--format", "json"], &envs);
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["spec"], ">=22 <24");
+    assert_eq!(result["resolved_version"], "v22.11.0");
+
+    // With no installed match there is nothing nvm could use
+    let temp_dir = create_temp_dir_with_nvmrc(">=26");
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["switch"], &envs);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert!(String::from_utf8_lossy(&output.stderr).contains(
+        "Warning: no installed Node.js version satisfies >=26; run: auto-nvm install '>=26'"
+    ));
+
+    let temp_dir = create_temp_dir_with_nvmrc("lts/Jod");
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["switch"], &envs);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "nvm use lts/jod\n");
+}
+
+#[test]
+fn test_nvmrc_comments_and_lint() {
+    let temp_dir =
+        create_temp_dir_with_nvmrc("\u{feff}# pinned for CI\r\n\r\nv16.14.0 # gallium\r\n");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["switch"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "nvm use v16.14.0\n"
+    );
+
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check", "--lint"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("warning: UTF-8 byte order mark"));
+    assert!(stdout.contains("warning: Comments are only understood"));
+    assert!(stdout.contains(".nvmrc:3:10\n"));
+    assert!(stdout.contains("3 | v16.14.0 # gallium\n  |          ^^^^^^^^^"));
+
+    let temp_dir = create_temp_dir_with_nvmrc("22\n22.1..0\n");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check", "--lint"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("error: Only one Node.js version may be given"));
+    assert!(stdout.contains(".nvmrc:2:1\n"));
+
+    let temp_dir = create_temp_dir_with_nvmrc("22\n");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check", "--lint"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is portable"));
+}
+
+#[test]
+fn test_check_diagnostics() {
+    let temp_dir = create_temp_dir_with_nvmrc("# CI\n  22..1\n");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("error: Invalid version format: 22..1"));
+    assert!(stderr.contains(".nvmrc:2:3\n"));
+    assert!(stderr.contains("2 |   22..1\n  |   ^^^^^"));
+    assert!(stderr.contains("= help: did you mean `22.1`?"));
+    assert!(!stderr.contains("\x1b["));
+
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check", "--format", "json"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["diagnostics"][0]["span"], serde_json::json!([3, 5]));
+    assert_eq!(json["diagnostics"][0]["suggestion"], "22.1");
+
+    let temp_dir = create_temp_dir_with_nvmrc("18\n20\n");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(6));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["status"], "invalid-spec");
+    assert_eq!(json["source"], "nvmrc");
+    assert_eq!(json["diagnostics"][0]["line"], 2);
+
+    let temp_dir = create_temp_dir_with_nvmrc("lts/hydorgen\n");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("did you mean `lts/hydrogen`?"));
+
+    let temp_dir = create_temp_dir_with_nvmrc("v22.17\n");
+    let nvm_dir = FakeNvm::new(&["v22.11.0", "v24.1.0"]);
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["check"], &[("NVM_DIR", nvm_dir.path())]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("warning: Node.js v22.17 is not installed"));
+    assert!(stderr.contains("did you mean `v22.11.0`?"));
+}
+
+#[test]
+fn test_debug_log_records_hook_invocations() {
+    let temp_dir = create_temp_dir_with_nvmrc("22\n");
+    let nvm_dir = FakeNvm::new(&["v22.11.0"]);
+    let log_dir = TempDir::new().expect("Failed to create temp dir");
+    let log_file = log_dir.path().join("state/auto-nvm.log");
+    let envs = [
+        ("NVM_DIR", nvm_dir.path()),
+        ("AUTO_NVM_LOG", Path::new("debug")),
+        ("AUTO_NVM_LOG_FILE", log_file.as_path()),
+    ];
+
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["--quiet", "switch"], &envs);
+    assert!(output.status.success());
+
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["log", "tail", "-n", "50"], &envs);
+    assert!(output.status.success());
+    let log = String::from_utf8_lossy(&output.stdout);
+    assert!(log.contains("INFO "), "{}", log);
+    assert!(log.contains("--quiet switch (cwd "));
+    assert!(log.contains("DEBUG"));
+    assert!(log.contains(".nvmrc: found"));
+    assert!(log.contains("auto-nvm.toml: missing"));
+    assert!(log.contains("resolved 22 to v22.11.0"));
+    assert!(log.contains("emitted `nvm use 22` ("));
+    assert!(log.contains(" ms)"));
+
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["log", "tail", "-n", "1"], &envs);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 1);
+
+    // Logging is off unless AUTO_NVM_LOG is set
+    let quiet_file = log_dir.path().join("off.log");
+    run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["switch"],
+        &[("AUTO_NVM_LOG_FILE", quiet_file.as_path())],
+    );
+    assert!(!quiet_file.exists());
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["log", "tail"],
+        &[("AUTO_NVM_LOG_FILE", quiet_file.as_path())],
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("AUTO_NVM_LOG=debug"));
+}
+
+#[cfg(feature = "bench")]
+#[test]
+fn test_bench_reports_percentiles() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["bench", "--iterations", "3", "--json"]);
+    assert!(output.status.success());
+
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid JSON");
+    let names: Vec<&str> = results
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["name"].as_str().unwrap())
+        .collect();
+    assert!(names.contains(&"resolve (depth 8)"));
+    assert!(names.contains(&"index cache"));
+    assert!(names.contains(&"emit (nvm)"));
+    assert!(names.contains(&"switch (process)"));
+    assert!(results[0]["p95_ms"].as_f64().unwrap() >= results[0]["p50_ms"].as_f64().unwrap());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_check_strict_with_node_binary() {
+    let nvm_dir = FakeNvm::new(&["v18.17.0", "v20.10.0"]);
+    let node = nvm_dir.bin_dir("v18.17.0").join("node");
+    let node = node.to_str().unwrap();
+
+    // Matching version passes
+    let temp_dir = create_temp_dir_with_nvmrc("18");
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["check", "--strict", "--node", node],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("✓ Version matches requirement"));
+
+    // Installed but not active is a mismatch
+    let temp_dir = create_temp_dir_with_nvmrc("20");
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["check", "--strict", "--node", node],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert_eq!(output.status.code(), Some(3));
+
+    // Without --strict a mismatch is only reported
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["check", "--node", node],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("✗ Version mismatch!"));
+
+    // Not installed anywhere
+    let temp_dir = create_temp_dir_with_nvmrc("16");
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["--quiet", "check", "--strict", "--node", node],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert_eq!(output.status.code(), Some(4));
+}
+
+#[test]
+fn test_check_strict_invalid_and_missing() {
+    let temp_dir = create_temp_dir_with_nvmrc("not-a-valid-version");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check", "--strict"]);
+    assert_eq!(output.status.code(), Some(6));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid version format"));
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let output = run_auto_nvm_in_dir(temp_dir.path(), &["check", "--strict"]);
+    assert_eq!(output.status.code(), Some(5));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exec_runs_command_with_project_version() {
+    let nvm_dir = FakeNvm::new(&["v18.17.0", "v20.10.0"]);
+
+    let temp_dir = create_temp_dir_with_nvmrc("18");
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["exec", "--", "node", "--version"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "v18.17.0");
+
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["run", "--version", "20", "--", "sh", "-c", "echo $NVM_BIN"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .ends_with("versions/node/v20.10.0/bin"));
+
+    // --dir resolves the version file of another directory
+    let other_dir = TempDir::new().expect("Failed to create temp dir");
+    let output = run_auto_nvm_with_env(
+        other_dir.path(),
+        &[
+            "exec",
+            "--dir",
+            temp_dir.path().to_str().unwrap(),
+            "--",
+            "node",
+        ],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "v18.17.0");
+
+    // No version file and no --version
+    let output = run_auto_nvm_with_env(
+        other_dir.path(),
+        &["exec", "--", "node"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No version file found"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_shims_resolve_version_per_directory() {
+    let nvm_dir = FakeNvm::new(&["v18.17.0", "v20.10.0"]);
+    // A global package installed only under v20
+    fs::copy(
+        nvm_dir.path().join("versions/node/v20.10.0/bin/node"),
+        nvm_dir.path().join("versions/node/v20.10.0/bin/tsc"),
+    )
+    .expect("Failed to create fake tsc");
+    nvm_dir.alias("default", "v20.10.0");
+
+    let shims_dir = TempDir::new().expect("Failed to create temp dir");
+    let output = run_auto_nvm_with_env(
+        shims_dir.path(),
+        &[
+            "shims",
+            "install",
+            "--dir",
+            shims_dir.path().to_str().unwrap(),
+        ],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(output.status.success());
+    for name in ["node", "npm", "npx", "corepack", "tsc"] {
+        assert!(
+            shims_dir.path().join(name).exists(),
+            "missing shim {}",
+            name
+        );
+    }
+
+    let run_shim = |dir: &Path, name: &str| {
+        Command::new(shims_dir.path().join(name))
+            .current_dir(dir)
+            .env_clear()
+            .env("NVM_DIR", nvm_dir.path())
+            .env("PATH", path_with(&[]))
+            .output()
+            .expect("Failed to run shim")
+    };
+
+    // Nearest .nvmrc wins, even from a subdirectory
+    let project = create_temp_dir_with_nvmrc("18");
+    let nested = project.path().join("src");
+    fs::create_dir_all(&nested).expect("Failed to create nested dir");
+    let output = run_shim(&nested, "node");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "v18.17.0");
+
+    // Without a version file the nvm default alias is used
+    let output = run_shim(shims_dir.path(), "node");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "v20.10.0");
+
+    // Global bins missing from the resolved version fail clearly
+    let output = run_shim(&nested, "tsc");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("tsc is not installed"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_renamed_binary_is_not_a_shim() {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+    let binary_path = Path::new(env!("CARGO_BIN_EXE_auto-nvm"));
+    fs::copy(binary_path, dir.path().join("auto-nvm-0.2")).unwrap();
+    std::os::unix::fs::symlink(binary_path, dir.path().join("anvm")).unwrap();
+
+    for name in ["auto-nvm-0.2", "anvm"] {
+        let output = Command::new(dir.path().join(name))
+            .arg("--version")
+            .env_clear()
+            .output()
+            .expect("Failed to run auto-nvm");
+        assert!(output.status.success(), "{}: {:?}", name, output);
+        assert!(String::from_utf8_lossy(&output.stdout).starts_with("auto-nvm "));
+    }
+}
+
+#[test]
+fn test_pin_writes_version_files() {
+    let nvm = FakeNvm::new(&["v18.17.1", "v20.10.0"]);
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    // The active node is one of the installed versions, not the machine's
+    let active = nvm.bin_dir("v18.17.1");
+    let run = |args: &[&str]| {
+        run_auto_nvm_with_env(
+            temp_dir.path(),
+            args,
+            &[("NVM_DIR", nvm.path()), ("PATH", &active)],
+        )
+    };
+
+    // Default target is .nvmrc, and the spec is written as given
+    let output = run(&["pin", "18"]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".nvmrc")).unwrap(),
+        "18\n"
+    );
+
+    let output = run(&["pin", "18", "--exact"]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".nvmrc")).unwrap(),
+        "18.17.1\n"
+    );
+
+    // Uninstalled versions are refused unless forced
+    let output = run(&["pin", "16"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not installed"));
+
+    // package.json keeps its formatting; volta always gets an exact version
+    let package_json = "{\n    \"name\": \"app\",\n    \"private\": true\n}\n";
+    fs::write(temp_dir.path().join("package.json"), package_json).unwrap();
+    let output = run(&["pin", "20", "--file", "package-json-volta"]);
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("package.json")).unwrap(),
+        "{\n    \"name\": \"app\",\n    \"private\": true,\n    \"volta\": {\n        \"node\": \"20.10.0\"\n    }\n}\n"
+    );
+
+    let output = run(&["pin", "20", "--major", "--file", "tool-versions"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_pin_uses_configured_version_file() {
+    let nvm_dir = FakeNvm::new(&["v18.17.1"]);
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["pin", "v18.17.1", "--major"],
+        &[
+            ("NVM_DIR", nvm_dir.path()),
+            ("AUTO_NVM_VERSION_FILE", Path::new(".node-version")),
+        ],
+    );
+
+    assert!(output.status.success());
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join(".node-version")).unwrap(),
+        "18\n"
+    );
+    assert!(!temp_dir.path().join(".nvmrc").exists());
+
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["pin", "v18.17.1"],
+        &[
+            ("NVM_DIR", nvm_dir.path()),
+            ("AUTO_NVM_VERSION_FILE", Path::new("nodeversion")),
+        ],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid AUTO_NVM_VERSION_FILE value \"nodeversion\""));
+    assert!(stderr.contains("node-version"));
+}
+
+#[test]
+fn test_commands_read_pinned_version_file() {
+    let nvm_dir = FakeNvm::new(&["v18.17.1", "v20.10.0"]);
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let envs = [("NVM_DIR", nvm_dir.path())];
+
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["pin", "20", "--file", "node-version"],
+        &envs,
+    );
+    assert!(output.status.success());
+
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["switch"], &envs);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "nvm use 20\n");
+
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["check", "--format", "json"], &envs);
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["source"], "node-version");
+    assert_eq!(result["spec"], "20");
+
+    // .nvmrc comes first in the lookup order
+    fs::write(temp_dir.path().join(".nvmrc"), "18\n").unwrap();
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["switch"], &envs);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "nvm use 18\n");
+}
+
+#[test]
+fn test_engines_only_read_when_enabled() {
+    let nvm_dir = FakeNvm::new(&["v18.17.1", "v20.10.0"]);
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    fs::write(
+        temp_dir.path().join("package.json"),
+        "{\"name\": \"app\", \"engines\": {\"node\": \">=18 <20\"}}\n",
+    )
+    .unwrap();
+
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["--quiet", "switch"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    // As in a directory without a version file
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["--quiet", "switch"],
+        &[
+            ("NVM_DIR", nvm_dir.path()),
+            ("AUTO_NVM_READ_ENGINES", Path::new("1")),
+        ],
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "nvm use v18.17.1\n"
+    );
+}
+
+#[test]
+fn test_list_marks_aliases_and_project_usage() {
+    let nvm_dir = FakeNvm::new(&["v16.20.2", "v18.17.1", "v20.10.0"]);
+    nvm_dir.alias("default", "v20.10.0");
+
+    let projects = TempDir::new().unwrap();
+    fs::create_dir_all(projects.path().join("api")).unwrap();
+    fs::write(projects.path().join("api/.nvmrc"), "18\n").unwrap();
+
+    let output = run_auto_nvm_with_env(
+        projects.path(),
+        &["list", "--scan", "."],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("v20.10.0  (default)"));
+    assert!(stdout.contains("v16.20.2  (unused)"));
+    assert!(stdout.contains("used by ./api/.nvmrc"));
+
+    let output = run_auto_nvm_with_env(
+        projects.path(),
+        &["list", "--json"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(output.status.success());
+    let versions: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(versions[0]["version"], "v20.10.0");
+    assert_eq!(versions[0]["default"], true);
+    assert!(versions[0].get("used_by").is_none());
+}
+
+#[test]
+fn test_prune_removes_unused_versions() {
+    let nvm_dir = FakeNvm::new(&["v16.20.2", "v16.19.0", "v18.17.1", "v20.10.0"]);
+    nvm_dir.alias("default", "v20.10.0");
+
+    let projects = TempDir::new().unwrap();
+    fs::create_dir_all(projects.path().join("api")).unwrap();
+    fs::write(projects.path().join("api/.nvmrc"), "18\n").unwrap();
+    let installed = |version: &str| nvm_dir.path().join("versions/node").join(version).is_dir();
+
+    let output = run_auto_nvm_with_env(
+        projects.path(),
+        &["prune", "--scan", ".", "--dry-run"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Would remove v16.20.2"));
+    assert!(stdout.contains("Would remove v16.19.0"));
+    assert!(!stdout.contains("v18.17.1") && !stdout.contains("v20.10.0"));
+    assert!(installed("v16.20.2"));
+
+    let output = run_auto_nvm_with_env(
+        projects.path(),
+        &["prune", "--scan", ".", "--keep-latest-per-major"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Removed v16.19.0"));
+    assert!(!installed("v16.19.0"));
+    assert!(installed("v16.20.2") && installed("v18.17.1") && installed("v20.10.0"));
+
+    // A project whose version file can't be parsed might need any version
+    fs::create_dir_all(projects.path().join("legacy")).unwrap();
+    fs::write(projects.path().join("legacy/.nvmrc"), "sixteen\n").unwrap();
+    let output = run_auto_nvm_with_env(
+        projects.path(),
+        &["prune", "--scan", "."],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("legacy/.nvmrc"), "{}", stderr);
+    assert!(stderr.contains("--force"));
+    assert!(installed("v16.20.2"));
+
+    let output = run_auto_nvm_with_env(
+        projects.path(),
+        &["prune", "--scan", ".", "--force"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(output.status.success());
+    assert!(!installed("v16.20.2"));
+
+    // Without project directories nothing can be considered unused
+    let output = run_auto_nvm_with_env(projects.path(), &["prune"], &[("NVM_DIR", nvm_dir.path())]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--scan"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_migrate_globals_reinstalls_packages() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let nvm_dir = FakeNvm::new(&["v18.17.1", "v22.11.0"]);
+    let versions = nvm_dir.path().join("versions/node");
+    for (name, version) in [
+        ("pnpm", "8.15.1"),
+        ("typescript", "5.3.3"),
+        ("npm", "9.6.7"),
+    ] {
+        let dir = versions.join("v18.17.1/lib/node_modules").join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("package.json"),
+            format!(r#"{{"name": "{}", "version": "{}"}}"#, name, version),
+        )
+        .unwrap();
+    }
+
+    // Fake npm recording its arguments
+    let log = nvm_dir.path().join("npm.log");
+    let npm = versions.join("v22.11.0/bin/npm");
+    fs::create_dir_all(npm.parent().unwrap()).unwrap();
+    fs::write(
+        &npm,
+        format!("#!/bin/sh\necho \"$@\" >> '{}'\n", log.display()),
+    )
+    .unwrap();
+    fs::set_permissions(&npm, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let args = ["migrate-globals", "--from", "18", "--to", "22"];
+    let output = run_auto_nvm_with_env(
+        nvm_dir.path(),
+        &[&args[..], &["--dry-run"]].concat(),
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Would install into v22.11.0: pnpm@8.15.1 typescript@5.3.3"));
+    assert!(!log.exists());
+
+    let output = run_auto_nvm_with_env(nvm_dir.path(), &args, &[("NVM_DIR", nvm_dir.path())]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(&log).unwrap(),
+        "install --global pnpm@8.15.1 typescript@5.3.3\n"
+    );
+
+    let output = run_auto_nvm_with_env(
+        nvm_dir.path(),
+        &["migrate-globals", "--from", "18", "--to", "20"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not installed"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_package_manager_pinning() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let nvm_dir = FakeNvm::new(&["v22.11.0"]);
+    let node_bin = nvm_dir.path().join("versions/node/v22.11.0/bin");
+    fs::create_dir_all(&node_bin).unwrap();
+    fs::write(node_bin.join("corepack"), "").unwrap();
+
+    let project = create_temp_dir_with_nvmrc("22");
+    fs::write(
+        project.path().join("package.json"),
+        r#"{"name": "app", "packageManager": "pnpm@8.15.1+sha512.abc"}"#,
+    )
+    .unwrap();
+
+    let output = run_auto_nvm_with_env(project.path(), &["switch"], &[("NVM_DIR", nvm_dir.path())]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "nvm use 22\ncorepack enable pnpm\n"
+    );
+
+    // Once enabled, nothing more is emitted
+    fs::write(node_bin.join("pnpm"), "").unwrap();
+    let output = run_auto_nvm_with_env(project.path(), &["switch"], &[("NVM_DIR", nvm_dir.path())]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "nvm use 22\n");
+
+    // check reports the pnpm found on PATH, which must not be allowed to
+    // download anything when it is a corepack shim
+    let bin_dir = TempDir::new().unwrap();
+    let pnpm = bin_dir.path().join("pnpm");
+    fs::write(
+        &pnpm,
+        "#!/bin/sh\n[ \"$COREPACK_ENABLE_NETWORK\" = 0 ] || exit 1\necho 8.6.0\n",
+    )
+    .unwrap();
+    fs::set_permissions(&pnpm, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:/usr/bin:/bin", bin_dir.path().display());
+
+    let output = run_auto_nvm_with_env(
+        project.path(),
+        &["check"],
+        &[("NVM_DIR", nvm_dir.path()), ("PATH", Path::new(&path))],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Package manager mismatch! Required: pnpm@8.15.1, Current: pnpm 8.6.0"),
+        "{}",
+        stdout
+    );
+
+    let output = run_auto_nvm_with_env(
+        project.path(),
+        &["check", "--format", "json"],
+        &[("NVM_DIR", nvm_dir.path()), ("PATH", Path::new(&path))],
+    );
+    let value: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(value["package_manager"], "pnpm@8.15.1");
+    assert_eq!(value["package_manager_version"], "8.6.0");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_project_env_applied_and_removed_in_bash() {
+    if Command::new("bash").arg("--version").output().is_err() {
+        return;
+    }
+
+    let project = TempDir::new().unwrap();
+    fs::create_dir_all(project.path().join("src")).unwrap();
+    fs::write(
+        project.path().join("auto-nvm.toml"),
+        "path = [\"node_modules/.bin\"]\n\n[env]\nNODE_OPTIONS = \"--max-old-space-size=4096\"\nGREETING = \"it's $HOME\"\n",
+    )
+    .unwrap();
+    let outside = TempDir::new().unwrap();
+    let home = TempDir::new().unwrap();
+
+    let script = r#"
+        cd "$PROJECT/src" && eval "$("$AUTO_NVM" --quiet switch)"
+        echo "blocked: ${NODE_OPTIONS-unset}"
+        "$AUTO_NVM" --quiet allow
+        cd "$PROJECT/src" && eval "$("$AUTO_NVM" --quiet switch 2>/dev/null)"
+        echo "in: $NODE_OPTIONS|$GREETING|${PATH%%:*}"
+        cd "$OUTSIDE" && eval "$("$AUTO_NVM" --quiet switch 2>/dev/null)"
+        echo "out: ${NODE_OPTIONS-unset}|${GREETING-unset}|${AUTO_NVM_ENV_DIR-unset}|${PATH%%:*}"
+    "#;
+    let output = Command::new("bash")
+        .args(["--noprofile", "--norc", "-c", script])
+        .env("AUTO_NVM", env!("CARGO_BIN_EXE_auto-nvm"))
+        .env("PROJECT", project.path())
+        .env("OUTSIDE", outside.path())
+        .env("SHELL", "/bin/bash")
+        .env("PATH", "/usr/bin:/bin")
+        .env("HOME", home.path())
+        .env_remove("XDG_DATA_HOME")
+        .env_remove("AUTO_NVM_ENV_DIR")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // Not applied until allowed, and the hook says why even when quiet
+    assert!(stdout.contains("blocked: unset"), "{}", stdout);
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("is not allowed; review it, then run: auto-nvm allow"),
+        "{:?}",
+        output
+    );
+
+    assert!(
+        stdout.contains(&format!(
+            "in: --max-old-space-size=4096|it's $HOME|{}",
+            project.path().join("node_modules/.bin").display()
+        )),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("out: unset|unset|unset|/usr/bin"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_switch_prints_commands_for_requested_shell() {
+    let project = create_temp_dir_with_nvmrc("22");
+    fs::write(
+        project.path().join("auto-nvm.toml"),
+        "[env]\nGREETING = \"it's here\"\n",
+    )
+    .unwrap();
+    let home = TempDir::new().unwrap();
+    let auto_nvm = |args: &[&str]| {
+        run_auto_nvm_with_env(
+            project.path(),
+            args,
+            // The hooks say which shell they are; $SHELL may be another one
+            &[("SHELL", Path::new("/bin/bash")), ("HOME", home.path())],
+        )
+    };
+    assert!(auto_nvm(&["--quiet", "allow"]).status.success());
+    let switch = |shell: &str| {
+        let output = auto_nvm(&["--quiet", "switch", "--shell", shell]);
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let fish = switch("fish");
+    assert!(
+        fish.starts_with("set -gx GREETING 'it\\'s here'\n"),
+        "{}",
+        fish
+    );
+    assert!(fish.ends_with("\nnvm use 22\n"), "{}", fish);
+
+    let powershell = switch("powershell");
+    assert!(
+        powershell.starts_with("$env:GREETING = 'it''s here'\n"),
+        "{}",
+        powershell
+    );
+    assert!(switch("bash").starts_with("export GREETING='it'\\''s here'\n"));
+}
+
+#[test]
+fn test_scan_reports_workspace_drift() {
+    let nvm_dir = FakeNvm::new(&["v24.11.0"]);
+    let workspace = TempDir::new().unwrap();
+    for (dir, nvmrc) in [("api", "24\n"), ("legacy", "14\n"), ("vendor/lib", "18\n")] {
+        fs::create_dir_all(workspace.path().join(dir)).unwrap();
+        fs::write(workspace.path().join(dir).join(".nvmrc"), nvmrc).unwrap();
+    }
+    fs::write(workspace.path().join(".gitignore"), "vendor/\n").unwrap();
+
+    let output = run_auto_nvm_with_env(
+        workspace.path(),
+        &["scan", "--format", "json"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    assert!(output.status.success());
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+
+    assert_eq!(entries[0]["resolved_version"], "v24.11.0");
+    assert_eq!(entries[0]["issues"], serde_json::json!([]));
+    let kinds: Vec<&str> = entries[1]["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|issue| issue["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, vec!["end-of-life", "not-installed"]);
+
+    let output = run_auto_nvm_with_env(
+        workspace.path(),
+        &["scan", ".", "--format", "csv", "--exclude", "legacy"],
+        &[("NVM_DIR", nvm_dir.path())],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(stdout.starts_with("path,source,spec,resolved_version,issues\n"));
+}
+
+#[test]
+fn test_eol_warnings_and_schedule_update() {
+    let temp_dir = create_temp_dir_with_nvmrc("14.21.3");
+    let data_dir = TempDir::new().unwrap();
+    let schedule_file = data_dir.path().join("schedule.json");
+    let envs = [("AUTO_NVM_SCHEDULE_FILE", schedule_file.as_path())];
+
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["check", "--format", "json"], &envs);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["eol_warning"],
+        "Node.js 14 (Fermium) reached end-of-life on 2023-04-30"
+    );
+
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["switch"], &envs);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "nvm use 14.21.3\n");
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Warning: Node.js 14 (Fermium) reached end-of-life"));
+
+    // An offline update replaces the bundled dates
+    let update = data_dir.path().join("update.json");
+    fs::write(
+        &update,
+        r#"{"v14": {"start": "2020-04-21", "end": "2999-04-30", "codename": "Fermium"}}"#,
+    )
+    .unwrap();
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["schedule", "update", update.to_str().unwrap()],
+        &envs,
+    );
+    assert!(output.status.success());
+    assert!(schedule_file.exists());
+
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["switch"], &envs);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("end-of-life"));
+
+    fs::write(&update, "{\"v14\": {}}").unwrap();
+    let output = run_auto_nvm_with_env(
+        temp_dir.path(),
+        &["schedule", "update", update.to_str().unwrap()],
+        &envs,
+    );
+    assert!(!output.status.success());
+}
+
+/// A request received by the mirror stand-in
+#[derive(Debug, Clone, PartialEq)]
+struct MirrorRequest {
+    path: String,
+    if_none_match: Option<String>,
+    range: Option<String>,
+}
+
+/// Minimal HTTP stand-in for a Node.js mirror
+///
+/// Serves the given files below `/dist` with a fixed ETag, honours
+/// `If-None-Match` and `Range: bytes=N-`, and logs every request.
+fn start_mirror_server(files: Vec<(String, Vec<u8>)>) -> (String, Arc<Mutex<Vec<MirrorRequest>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind test server");
+    let url = format!("http://{}/dist", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let log = Arc::clone(&requests);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            let _ = reader.read_line(&mut request_line);
+            let path = request_line
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .trim_start_matches("/dist/")
+                .to_string();
+
+            let mut request = MirrorRequest {
+                path,
+                if_none_match: None,
+                range: None,
+            };
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+                let lower = line.to_ascii_lowercase();
+                if let Some(value) = lower.strip_prefix("if-none-match:") {
+                    request.if_none_match = Some(value.trim().to_string());
+                } else if let Some(value) = lower.strip_prefix("range:") {
+                    request.range = Some(value.trim().to_string());
+                }
+                line.clear();
+            }
+
+            let body = files
+                .iter()
+                .find(|(name, _)| *name == request.path)
+                .map(|(_, body)| body.clone());
+            let start = request
+                .range
+                .as_deref()
+                .and_then(|range| range.strip_prefix("bytes="))
+                .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+
+            let response: Vec<u8> = match (&body, start) {
+                (None, _) => {
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_vec()
+                }
+                (Some(_), _) if request.if_none_match.as_deref() == Some("\"v1\"") => {
+                    b"HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n"
+                        .to_vec()
+                }
+                (Some(body), Some(start)) if start < body.len() => {
+                    let mut response = format!(
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                        body.len() - start,
+                        start,
+                        body.len() - 1,
+                        body.len()
+                    )
+                    .into_bytes();
+                    response.extend_from_slice(&body[start..]);
+                    response
+                }
+                (Some(body), _) => {
+                    let mut response = format!(
+                        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    )
+                    .into_bytes();
+                    response.extend_from_slice(body);
+                    response
+                }
+            };
+            log.lock().unwrap().push(request);
+            let _ = stream.write_all(&response);
+        }
+    });
+
+    (url, requests)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_check_remote_reports_newer_release() {
+    const INDEX: &str = r#"[
+        {"version": "v22.11.0", "date": "2024-10-29", "lts": "Jod"},
+        {"version": "v22.12.0", "date": "2024-12-03", "lts": "Jod"},
+        {"version": "v20.10.0", "date": "2023-11-22", "lts": "Iron"}
+    ]"#;
+    let (mirror, requests) =
+        start_mirror_server(vec![("index.json".to_string(), INDEX.as_bytes().to_vec())]);
+
+    let nvm_dir = FakeNvm::new(&["v22.11.0"]);
+    let node = nvm_dir.bin_dir("v22.11.0").join("node");
+    let cache_dir = TempDir::new().unwrap();
+    let temp_dir = create_temp_dir_with_nvmrc("22");
+
+    let check = |args: &[&str], ttl: &str| {
+        run_auto_nvm_with_env(
+            temp_dir.path(),
+            &[args, &["--node", node.to_str().unwrap()]].concat(),
+            &[
+                ("NVM_DIR", nvm_dir.path()),
+                ("AUTO_NVM_NODE_MIRROR", Path::new(&mirror)),
+                ("AUTO_NVM_CACHE_DIR", cache_dir.path()),
+                ("AUTO_NVM_INDEX_TTL", Path::new(ttl)),
+            ],
+        )
+    };
+
+    let output = check(&["check", "--remote", "--format", "json"], "3600");
+    assert_eq!(output.status.code(), Some(0));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["available_version"], "v22.12.0");
+    assert_eq!(json["newer_version"], "v22.12.0");
+
+    // Fresh cache: no request; expired cache: revalidated with the ETag
+    check(&["check", "--remote"], "3600");
+    let output = check(&["check", "--remote"], "0");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Newer 22.x available: v22.12.0"));
+    let etags: Vec<Option<String>> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| request.if_none_match.clone())
+        .collect();
+    assert_eq!(etags, vec![None, Some("\"v1\"".to_string())]);
+
+    // Without --remote only the cached index is used
+    let output = check(&["check"], "0");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Newer 22.x available"));
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+/// Release archive name for the running platform, as the installer requests it
+fn release_archive_name(version: &str) -> Option<String> {
+    let os = match std::env::consts::OS {
+        "linux" => "linux",
+        "macos" => "darwin",
+        _ => return None,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        _ => return None,
+    };
+    Some(format!("node-{}-{}-{}.tar.xz", version, os, arch))
+}
+
+/// Build a release-style .tar.xz containing `bin/node`
+fn build_release_archive(version: &str) -> Vec<u8> {
+    let mut builder = tar::Builder::new(xz2::write::XzEncoder::new(Vec::new(), 1));
+    // Incompressible padding so the archive is large enough to resume halfway
+    let padding: Vec<u8> = (0..64 * 1024u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+        .collect();
+    for (path, content, mode) in [
+        (
+            "bin/node",
+            format!("#!/bin/sh\necho {}\n", version).into_bytes(),
+            0o755,
+        ),
+        ("share/padding", padding, 0o644),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(mode);
+        header.set_cksum();
+        builder
+            .append_data(
+                &mut header,
+                format!("node-{}-linux-x64/{}", version, path),
+                &content[..],
+            )
+            .unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_install_downloads_verifies_and_resumes() {
+    use sha2::{Digest, Sha256};
+
+    let version = "v22.12.0";
+    let archive_name = match release_archive_name(version) {
+        Some(name) => name,
+        None => return,
+    };
+    let archive = build_release_archive(version);
+    let checksum: String = Sha256::digest(&archive)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    let index = r#"[{"version": "v22.12.0", "date": "2024-12-03", "lts": "Jod"}]"#;
+    let shasums = format!("{}  {}\n", checksum, archive_name);
+    let (mirror, requests) = start_mirror_server(vec![
+        ("index.json".to_string(), index.as_bytes().to_vec()),
+        (format!("{}/SHASUMS256.txt", version), shasums.into_bytes()),
+        (format!("{}/{}", version, archive_name), archive.clone()),
+    ]);
+
+    let nvm_dir = TempDir::new().unwrap();
+    let cache_dir = TempDir::new().unwrap();
+    let temp_dir = create_temp_dir_with_nvmrc("22");
+
+    // Simulate an interrupted download of the first half
+    let downloads = cache_dir.path().join("downloads");
+    fs::create_dir_all(&downloads).unwrap();
+    let half = archive.len() / 2;
+    fs::write(
+        downloads.join(format!("{}.part", archive_name)),
+        &archive[..half],
+    )
+    .unwrap();
+
+    let envs = [
+        ("NVM_DIR", nvm_dir.path()),
+        ("AUTO_NVM_NODE_MIRROR", Path::new(&mirror)),
+        ("AUTO_NVM_CACHE_DIR", cache_dir.path()),
+    ];
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["install"], &envs);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Installed Node.js v22.12.0"));
+
+    let node = nvm_dir.path().join("versions/node/v22.12.0/bin/node");
+    let result = Command::new(&node).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&result.stdout).trim(), "v22.12.0");
+    assert!(!downloads.join(&archive_name).exists());
+
+    let archive_request = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|request| request.path.ends_with(".tar.xz"))
+        .cloned()
+        .unwrap();
+    assert_eq!(archive_request.range, Some(format!("bytes={}-", half)));
+
+    // Installing again is a no-op
+    let output = run_auto_nvm_with_env(temp_dir.path(), &["install", "lts/jod"], &envs);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("already installed"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_install_rejects_checksum_mismatch() {
+    let version = "v22.12.0";
+    let archive_name = match release_archive_name(version) {
+        Some(name) => name,
+        None => return,
+    };
+    let index = r#"[{"version": "v22.12.0", "date": "2024-12-03", "lts": "Jod"}]"#;
+    let shasums = format!("{}  {}\n", "0".repeat(64), archive_name);
+    let (mirror, _) = start_mirror_server(vec![
+        ("index.json".to_string(), index.as_bytes().to_vec()),
+        (format!("{}/SHASUMS256.txt", version), shasums.into_bytes()),
+        (
+            format!("{}/{}", version, archive_name),
+            build_release_archive(version),
+        ),
+    ]);
+
+    let nvm_dir = TempDir::new().unwrap();
+    let cache_dir = TempDir::new().unwrap();
+    let envs = [
+        ("NVM_DIR", nvm_dir.path()),
+        ("AUTO_NVM_NODE_MIRROR", Path::new(&mirror)),
+        ("AUTO_NVM_CACHE_DIR", cache_dir.path()),
+    ];
+    let output = run_auto_nvm_with_env(nvm_dir.path(), &["--quiet", "install", "22"], &envs);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Checksum mismatch"));
+    assert!(!nvm_dir.path().join("versions/node/v22.12.0").exists());
+    assert!(!cache_dir
+        .path()
+        .join("downloads")
+        .join(archive_name)
+        .exists());
+
+    // A missing signature fails without leaving the scratch dir behind
+    if which::which("gpg").is_ok() {
+        let output = run_auto_nvm_with_env(
+            nvm_dir.path(),
+            &["--quiet", "install", "22", "--gpg"],
+            &envs,
+        );
+        assert!(!output.status.success());
+        let leftovers: Vec<_> = fs::read_dir(cache_dir.path().join("downloads"))
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(".verify-"))
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+    }
+}
+
+/// Target triple of the auto-nvm release archives for this machine
+fn auto_nvm_target() -> Option<&'static str> {
+    match (std::env::consts::ARCH, std::env::consts::OS) {
+        ("x86_64", "linux") => Some("x86_64-unknown-linux-gnu"),
+        ("aarch64", "linux") => Some("aarch64-unknown-linux-gnu"),
+        ("x86_64", "macos") => Some("x86_64-apple-darwin"),
+        ("aarch64", "macos") => Some("aarch64-apple-darwin"),
+        _ => None,
+    }
+}
+
+/// A release feed directory with a .tar.gz like release.yml builds, holding
+/// an `auto-nvm` script, and a checksums.txt listing it
+fn create_release_feed(version: &str, target: &str, checksum: Option<&str>) -> TempDir {
+    use sha2::{Digest, Sha256};
+
+    let feed = TempDir::new().unwrap();
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        Vec::new(),
+        flate2::Compression::fast(),
+    ));
+    for (path, content) in [
+        ("README.md", "# auto-nvm\n".to_string()),
+        (
+            "auto-nvm",
+            format!("#!/bin/sh\necho \"new auto-nvm {} $*\"\n", version),
+        ),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, content.as_bytes())
+            .unwrap();
+    }
+    let archive = builder.into_inner().unwrap().finish().unwrap();
+
+    let name = format!("auto-nvm-v{}-{}.tar.gz", version, target);
+    let sha256: String = Sha256::digest(&archive)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    fs::write(feed.path().join(&name), archive).unwrap();
+    fs::write(
+        feed.path().join("checksums.txt"),
+        format!(
+            "{}  auto-nvm-v0.0.1-{}.tar.gz\n{}  {}\n",
+            "0".repeat(64),
+            target,
+            checksum.unwrap_or(&sha256),
+            name
+        ),
+    )
+    .unwrap();
+    feed
+}
+
+#[cfg(unix)]
+#[test]
+fn test_self_update_from_local_feed() {
+    let Some(target) = auto_nvm_target() else {
+        return;
+    };
+    let feed = create_release_feed("99.0.0", target, None);
+    let bin_dir = auto_nvm_bin_dir();
+    let exe = bin_dir.path().join("auto-nvm");
+    let feed_url = format!("file://{}", feed.path().display());
+
+    let output = Command::new(&exe)
+        .args(["self-update", "--check"])
+        .env("AUTO_NVM_RELEASE_URL", &feed_url)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("auto-nvm 99.0.0 is available"));
+
+    // Signatures are required unless skipped
+    let output = Command::new(&exe)
+        .arg("self-update")
+        .env("AUTO_NVM_RELEASE_URL", &feed_url)
+        .env("PATH", "")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--skip-signature"));
+
+    let output = Command::new(&exe)
+        .args(["self-update", "--skip-signature"])
+        .env("AUTO_NVM_RELEASE_URL", &feed_url)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("-> 99.0.0"), "{}", stdout);
+    // The shell integration is refreshed by the new binary
+    assert!(
+        stdout.contains("new auto-nvm 99.0.0 setup --refresh"),
+        "{}",
+        stdout
+    );
+    assert!(fs::read_to_string(&exe)
+        .unwrap()
+        .contains("new auto-nvm 99.0.0"));
+    let leftovers: Vec<_> = fs::read_dir(bin_dir.path()).unwrap().collect();
+    assert_eq!(leftovers.len(), 1);
+}
+
+/// A gpg home with a new passphrase-less signing key, and that key's public
+/// half exported to `key.asc` inside it
+fn create_signing_key(name: &str) -> TempDir {
+    let home = TempDir::new().unwrap();
+    let gpg = |args: &[&str]| {
+        let output = Command::new("gpg")
+            .arg("--homedir")
+            .arg(home.path())
+            .args(["--batch", "--pinentry-mode", "loopback", "--passphrase", ""])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        output.stdout
+    };
+    gpg(&[
+        "--quick-gen-key",
+        &format!("{} <{}@example.com>", name, name),
+        "ed25519",
+        "sign",
+        "never",
+    ]);
+    fs::write(home.path().join("key.asc"), gpg(&["--armor", "--export"])).unwrap();
+    home
+}
+
+fn sign(home: &Path, file: &Path) {
+    let output = Command::new("gpg")
+        .arg("--homedir")
+        .arg(home)
+        .args(["--batch", "--pinentry-mode", "loopback", "--passphrase", ""])
+        .args(["--yes", "--armor", "--detach-sign", "--output"])
+        .arg(file.with_extension("txt.asc"))
+        .arg(file)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_self_update_verifies_release_key() {
+    let Some(target) = auto_nvm_target() else {
+        return;
+    };
+    if which::which("gpg").is_err() {
+        return;
+    }
+    let release = create_signing_key("release");
+    let other = create_signing_key("other");
+    let feed = create_release_feed("99.0.0", target, None);
+    sign(other.path(), &feed.path().join("checksums.txt"));
+    let bin_dir = auto_nvm_bin_dir();
+    let exe = bin_dir.path().join("auto-nvm");
+    let before = fs::read(&exe).unwrap();
+    let self_update = |key: &Path| {
+        Command::new(&exe)
+            .args(["--quiet", "self-update"])
+            .env(
+                "AUTO_NVM_RELEASE_URL",
+                format!("file://{}", feed.path().display()),
+            )
+            .env("AUTO_NVM_RELEASE_KEY", key)
+            // The signer's key is in the default keyring, which must not count
+            .env("GNUPGHOME", other.path())
+            .output()
+            .unwrap()
+    };
+
+    let output = self_update(&release.path().join("key.asc"));
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("not signed by the auto-nvm release key"),
+        "{:?}",
+        output
+    );
+    assert_eq!(fs::read(&exe).unwrap(), before);
+
+    sign(release.path(), &feed.path().join("checksums.txt"));
+    let output = self_update(&release.path().join("key.asc"));
+    assert!(output.status.success(), "{:?}", output);
+    assert!(fs::read_to_string(&exe)
+        .unwrap()
+        .contains("new auto-nvm 99.0.0"));
+
+    for home in [&release, &other] {
+        let _ = Command::new("gpgconf")
+            .arg("--homedir")
+            .arg(home.path())
+            .args(["--kill", "gpg-agent"])
+            .output();
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_self_update_rejects_checksum_mismatch() {
+    let Some(target) = auto_nvm_target() else {
+        return;
+    };
+    let feed = create_release_feed("99.0.0", target, Some(&"1".repeat(64)));
+    let bin_dir = auto_nvm_bin_dir();
+    let exe = bin_dir.path().join("auto-nvm");
+    let before = fs::read(&exe).unwrap();
+
+    let output = Command::new(&exe)
+        .args(["--quiet", "self-update", "--skip-signature"])
+        .env(
+            "AUTO_NVM_RELEASE_URL",
+            format!("file://{}", feed.path().display()),
+        )
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Checksum mismatch"));
+    assert_eq!(fs::read(&exe).unwrap(), before);
+}
+
+#[test]
+fn test_self_update_up_to_date() {
+    let Some(target) = auto_nvm_target() else {
+        return;
+    };
+    let feed = create_release_feed("0.0.2", target, None);
+    let output = Command::new(env!("CARGO_BIN_EXE_auto-nvm"))
+        .args(["self-update", "--check"])
+        .env(
+            "AUTO_NVM_RELEASE_URL",
+            format!("file://{}", feed.path().display()),
+        )
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is up to date"));
+}
+
+#[test]
+fn test_help_topics() {
+    let output = Command::new(env!("CARGO_BIN_EXE_auto-nvm"))
+        .args(["help", "version-files"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    // Rendered from docs/topics/version-files.md
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let source = fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("docs/topics/version-files.md"),
+    )
+    .unwrap();
+    assert!(source.starts_with("# Version files\n"));
+    assert!(stdout.starts_with("Version files\n"));
+    assert!(stdout.contains("- `.tool-versions`: the `nodejs` entry"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_auto-nvm"))
+        .args(["help", "pin"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Usage: auto-nvm pin"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_auto-nvm"))
+        .args(["help", "nope"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown command or help topic"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_auto-nvm"))
+        .arg("--help")
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Help topics"));
+}
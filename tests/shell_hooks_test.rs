@@ -0,0 +1,314 @@
+//! Drive real shells through the integration scripts `auto-nvm setup`
+//! writes, against a fake nvm, and check which node ends up on PATH
+//!
+//! Shells that are not installed are skipped, except when `CI` is set: CI
+//! installs every shell, so a missing one there is a failure.
+
+mod common;
+
+use common::{auto_nvm_bin_dir, path_with, run_shell, FakeNvm};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+const BOURNE_SCRIPT: &str = r#"
+. "$NVM_DIR/nvm.sh"
+. "$RC"
+cd "$WS/project-a"; echo "cd: $(node --version) $NVM_BIN"
+pushd "$WS/project-b" >/dev/null; echo "pushd: $(node --version)"
+popd >/dev/null; echo "popd: $(node --version)"
+(cd "$WS/project-c"; echo "subshell: $(node --version)")
+echo "after subshell: $(node --version)"
+cd "$WS/plain"; echo "plain: $(node --version)"
+echo "PATH: $PATH"
+"#;
+
+const FISH_SCRIPT: &str = r#"
+source $NVM_DIR/nvm.fish
+source $RC
+cd $WS/project-a; echo "cd: "(node --version)" $NVM_BIN"
+pushd $WS/project-b; echo "pushd: "(node --version)
+popd; echo "popd: "(node --version)
+cd $WS/plain; echo "plain: "(node --version)
+echo "PATH: "(string join : $PATH)
+"#;
+
+/// `cd` into a project with an auto-nvm.toml and out again
+const BOURNE_ENV_SCRIPT: &str = r#"
+. "$NVM_DIR/nvm.sh"
+. "$RC"
+cd "$WS/project-env"; echo "env: $NODE_OPTIONS $(node --version)"
+cd "$WS/plain"; echo "left: ${NODE_OPTIONS-unset}"
+"#;
+
+const FISH_ENV_SCRIPT: &str = r#"
+source $NVM_DIR/nvm.fish
+source $RC
+cd $WS/project-env; echo "env: $NODE_OPTIONS "(node --version)
+cd $WS/plain; echo "left: "(set -q NODE_OPTIONS; and echo set; or echo unset)
+"#;
+
+/// There is no nvm for PowerShell on Unix; this one records what it was asked
+const PWSH_ENV_SCRIPT: &str = r#"
+function nvm { $global:NvmCall = "nvm $args" }
+. $env:RC
+cd (Join-Path $env:WS project-env); Write-Output "env: $env:NODE_OPTIONS $global:NvmCall"
+cd (Join-Path $env:WS plain); Write-Output "left: $(if (Test-Path Env:NODE_OPTIONS) { 'set' } else { 'unset' })"
+"#;
+
+/// Projects pinning different versions, one with a project environment, and
+/// a directory without .nvmrc
+fn create_workspace() -> TempDir {
+    let workspace = TempDir::new().expect("Failed to create temp dir");
+    for (project, spec) in [
+        ("project-a", "22.11"),
+        ("project-b", "lts/*"),
+        ("project-c", ">=20 <21"),
+        ("project-env", "22.11"),
+    ] {
+        let dir = workspace.path().join(project);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(".nvmrc"), format!("{}\n", spec)).unwrap();
+    }
+    fs::write(
+        workspace.path().join("project-env/auto-nvm.toml"),
+        "[env]\nNODE_OPTIONS = \"--max-old-space-size=4096\"\n",
+    )
+    .unwrap();
+    fs::create_dir_all(workspace.path().join("plain")).unwrap();
+    workspace
+}
+
+/// Run `auto-nvm setup` for `shell` in a fresh home, returning the rc file
+fn setup(shell: &str, home: &Path, path: &OsStr) -> PathBuf {
+    let rc = match shell {
+        "bash" => home.join(".bashrc"),
+        "zsh" => home.join(".zshrc"),
+        _ => home.join(".config/fish/config.fish"),
+    };
+    fs::create_dir_all(rc.parent().unwrap()).unwrap();
+    fs::write(&rc, "").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_auto-nvm"))
+        .arg("setup")
+        .env("HOME", home)
+        .env("XDG_DATA_HOME", home.join(".local/share"))
+        .env("SHELL", format!("/bin/{}", shell))
+        .env("PATH", path)
+        .output()
+        .expect("Failed to run auto-nvm setup");
+    assert!(output.status.success(), "{:?}", output);
+    assert!(home.join(".local/share/man/man1/auto-nvm.1").is_file());
+    assert!(home
+        .join(".local/share/man/man7/auto-nvm-hooks.7")
+        .is_file());
+    rc
+}
+
+/// Run `script` in `shell` with the hooks installed in a fresh home and a
+/// workspace from [`create_workspace`], returning stdout
+fn run_env_hooks(shell: &str, script: &str) -> Option<String> {
+    let nvm = FakeNvm::new(&["v22.11.0"]);
+    let workspace = create_workspace();
+    let home = TempDir::new().expect("Failed to create temp dir");
+    let bin = auto_nvm_bin_dir();
+    let path = path_with(&[bin.path()]);
+    // `setup` only targets PowerShell on Windows, so load the module directly
+    let rc = if shell == "pwsh" {
+        let rc = home.path().join("auto-nvm.ps1");
+        fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("shell-integration/powershell/auto-nvm.psm1"),
+            &rc,
+        )
+        .unwrap();
+        rc
+    } else {
+        setup(shell, home.path(), &path)
+    };
+    let output = Command::new(env!("CARGO_BIN_EXE_auto-nvm"))
+        .args(["--quiet", "allow"])
+        .arg(workspace.path().join("project-env"))
+        .env("HOME", home.path())
+        .env_remove("XDG_DATA_HOME")
+        .output()
+        .expect("Failed to run auto-nvm allow");
+    assert!(output.status.success(), "{:?}", output);
+
+    let output = run_shell(
+        shell,
+        script,
+        &[
+            ("HOME", home.path().as_os_str()),
+            ("SHELL", OsStr::new(shell)),
+            ("PATH", &path),
+            ("NVM_DIR", nvm.path().as_os_str()),
+            ("WS", workspace.path().as_os_str()),
+            ("RC", rc.as_os_str()),
+        ],
+    )?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    assert!(
+        output.status.success(),
+        "{} failed:\n{}\n{}",
+        shell,
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Some(stdout)
+}
+
+/// Note that `shell` is missing; fails instead when running in CI
+fn skip(shell: &str) {
+    assert!(
+        std::env::var_os("CI").is_none(),
+        "{} is not installed, but CI is set",
+        shell
+    );
+    eprintln!("{} not installed, skipping", shell);
+}
+
+/// The value printed after `label: `
+fn reported<'a>(stdout: &'a str, label: &str) -> Option<&'a str> {
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix(label)?.strip_prefix(": "))
+}
+
+/// Run `script` in `shell` with the hooks installed, returning stdout and
+/// stderr
+fn run_hooks(shell: &str, script: &str) -> Option<(String, String)> {
+    let nvm = FakeNvm::new(&["v20.18.0", "v22.11.0", "v22.20.0"]);
+    nvm.alias("lts/*", "lts/jod");
+    nvm.alias("lts/jod", "v22.20.0");
+    let workspace = create_workspace();
+    let home = TempDir::new().expect("Failed to create temp dir");
+    let bin = auto_nvm_bin_dir();
+    let path = path_with(&[bin.path()]);
+    let rc = setup(shell, home.path(), &path);
+
+    let output = run_shell(
+        shell,
+        &format!("cd \"$WS\"\n{}", script),
+        &[
+            ("HOME", home.path().as_os_str()),
+            ("SHELL", OsStr::new(shell)),
+            ("PATH", &path),
+            ("NVM_DIR", nvm.path().as_os_str()),
+            ("WS", workspace.path().as_os_str()),
+            ("RC", rc.as_os_str()),
+        ],
+    )?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    assert!(
+        output.status.success(),
+        "{} failed:\n{}\n{}",
+        shell,
+        stdout,
+        stderr
+    );
+
+    let expected_bin = nvm.bin_dir("v22.11.0");
+    assert_eq!(
+        reported(&stdout, "cd"),
+        Some(format!("v22.11.0 {}", expected_bin.display()).as_str()),
+        "{}",
+        stdout
+    );
+    assert_eq!(reported(&stdout, "pushd"), Some("v22.20.0"));
+    assert_eq!(reported(&stdout, "popd"), Some("v22.11.0"));
+    // No .nvmrc: the previous version stays active
+    assert_eq!(reported(&stdout, "plain"), Some("v22.11.0"));
+
+    // Exactly one nvm version on PATH, first
+    let path = reported(&stdout, "PATH").unwrap_or_default();
+    let entries: Vec<&str> = path.split(':').collect();
+    assert_eq!(entries.first(), Some(&expected_bin.to_str().unwrap()));
+    assert_eq!(
+        entries
+            .iter()
+            .filter(|entry| entry.contains("versions/node"))
+            .count(),
+        1,
+        "{}",
+        path
+    );
+    Some((stdout, stderr))
+}
+
+#[cfg(unix)]
+#[test]
+fn test_bash_hooks() {
+    let Some((stdout, stderr)) = run_hooks("bash", BOURNE_SCRIPT) else {
+        skip("bash");
+        return;
+    };
+    assert_eq!(reported(&stdout, "subshell"), Some("v20.18.0"));
+    assert_eq!(reported(&stdout, "after subshell"), Some("v22.11.0"));
+    // The hooks run `switch --quiet`, which still warns about EOL lines
+    assert_eq!(
+        stderr
+            .matches("Warning: Node.js 20 (Iron) reached end-of-life")
+            .count(),
+        1,
+        "{}",
+        stderr
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_zsh_hooks() {
+    let Some((stdout, stderr)) = run_hooks("zsh", BOURNE_SCRIPT) else {
+        skip("zsh");
+        return;
+    };
+    assert_eq!(reported(&stdout, "subshell"), Some("v20.18.0"));
+    assert_eq!(reported(&stdout, "after subshell"), Some("v22.11.0"));
+    // The hooks run `switch --quiet`, which still warns about EOL lines
+    assert_eq!(
+        stderr
+            .matches("Warning: Node.js 20 (Iron) reached end-of-life")
+            .count(),
+        1,
+        "{}",
+        stderr
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_fish_hooks() {
+    // fish has no subshells; command substitutions run in the same shell
+    if run_hooks("fish", FISH_SCRIPT).is_none() {
+        skip("fish");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_project_env_hooks() {
+    // `switch` prints one command per line; every shell must run them all
+    for (shell, script, node) in [
+        ("bash", BOURNE_ENV_SCRIPT, "v22.11.0"),
+        ("zsh", BOURNE_ENV_SCRIPT, "v22.11.0"),
+        ("fish", FISH_ENV_SCRIPT, "v22.11.0"),
+        ("pwsh", PWSH_ENV_SCRIPT, "nvm use 22.11"),
+    ] {
+        let Some(stdout) = run_env_hooks(shell, script) else {
+            skip(shell);
+            continue;
+        };
+        assert_eq!(
+            reported(&stdout, "env"),
+            Some(format!("--max-old-space-size=4096 {}", node).as_str()),
+            "{}: {}",
+            shell,
+            stdout
+        );
+        assert_eq!(reported(&stdout, "left"), Some("unset"), "{}", shell);
+    }
+}